@@ -0,0 +1,292 @@
+// The built-in functions, which are native functions (see `NativeFunction`) declared in the base scope of every interpreter.
+// Each is described here once, so that the interpreter, linter, language server and documentation generator agree on them.
+
+use crate::environment;
+use crate::error::ErrorType;
+use crate::interpreter::Interpreter;
+use crate::value::{Arity, NativeFunction, Value};
+
+/// A built-in function.
+pub struct Builtin {
+    pub name: &'static str,
+    pub arity: Arity,
+    pub updates_argument: bool,  // Whether the function changes the variable given as its first argument (see `NativeFunction`).
+    pub signature: &'static str,  // How the function is called, e.g., `append(array, value)`.
+    pub description: &'static str,  // A short description of what the function does.
+    pub code: fn(&mut Interpreter, Vec<Value>) -> Result<Value, ErrorType>,
+}
+
+impl Builtin {
+    /// Returns the built-in function as a value which can be declared in an environment.
+    pub fn native_function(&self) -> NativeFunction {
+        NativeFunction {
+            updates_argument: self.updates_argument,
+            ..NativeFunction::new(self.name, self.arity, self.code)
+        }
+    }
+}
+
+/// All the built-in functions, in alphabetical order of their names.
+pub const BUILTINS: [Builtin; 8] = [
+    Builtin {
+        name: "append",
+        arity: Arity::Exact(2),
+        updates_argument: true,
+        signature: "append(array, value)",
+        description: "Appends `value` to the end of the array in the variable `array`, and returns the changed array.",
+        code: append,
+    },
+    Builtin {
+        name: "args",
+        arity: Arity::Exact(0),
+        updates_argument: false,
+        signature: "args()",
+        description: "Returns the command-line arguments given to the script after its path, as an array of strings.",
+        code: args,
+    },
+    Builtin {
+        name: "input",
+        arity: Arity::Exact(1),
+        updates_argument: false,
+        signature: "input(prompt)",
+        description: "Prints `prompt`, then reads a line of input (stdin unless the program running the script provides another source) and returns it as a string, without surrounding whitespace.",
+        code: input,
+    },
+    Builtin {
+        name: "remove",
+        arity: Arity::Exact(2),
+        updates_argument: true,
+        signature: "remove(collection, key)",
+        description: "Removes the element at index `key` of an array, or the key `key` of a dictionary, in the variable `collection`, and returns the changed collection.",
+        code: remove,
+    },
+    Builtin {
+        name: "size",
+        arity: Arity::Exact(1),
+        updates_argument: false,
+        signature: "size(collection)",
+        description: "Returns the number of elements in an array, key-value pairs in a dictionary, or bytes in a string.",
+        code: size,
+    },
+    Builtin {
+        name: "sort",
+        arity: Arity::Exact(1),
+        updates_argument: false,
+        signature: "sort(array)",
+        description: "Returns a sorted copy of an array of numbers or of strings.",
+        code: sort,
+    },
+    Builtin {
+        name: "to_number",
+        arity: Arity::Exact(1),
+        updates_argument: false,
+        signature: "to_number(value)",
+        description: "Converts a Boolean, number or string to a number. `true` is 1 and `false` is 0.",
+        code: to_number,
+    },
+    Builtin {
+        name: "to_string",
+        arity: Arity::Exact(1),
+        updates_argument: false,
+        signature: "to_string(value)",
+        description: "Converts a Boolean, number or string to a string.",
+        code: to_string,
+    },
+];
+
+/// Returns the built-in function declared under `name`, if there is one.
+pub fn find(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+// The arguments of each function below have already been checked against its arity by the interpreter.
+
+fn append(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, ErrorType> {
+    let [target, value]: [Value; 2] = arguments.try_into().map_err(|_| arity_error(interpreter))?;
+    if let Value::Array(mut array) = target {
+        // The interpreter assigns the changed array back to the variable.
+        array.push(value);
+        Ok(Value::Array(array))
+    } else {
+        // We can only append to arrays.
+        Err(ErrorType::ExpectedType { expected: String::from("Array"), got: target.type_to_string(), line: interpreter.argument_line(0) })
+    }
+}
+
+fn args(interpreter: &mut Interpreter, _arguments: Vec<Value>) -> Result<Value, ErrorType> {
+    // Evaluate to an array of the command-line arguments as strings.
+    Ok(Value::Array(interpreter.args().iter().map(|arg| Value::String_(arg.clone())).collect()))
+}
+
+fn input(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, ErrorType> {
+    let line = interpreter.call_line();
+    let io_error = |e: std::io::Error| ErrorType::IoError { message: e.to_string(), line };
+
+    // Print the input prompt, then read input.
+    interpreter.write_output(&arguments[0].to_string()).map_err(io_error)?;
+    match interpreter.read_line().map_err(io_error)? {
+        Some(input) => Ok(Value::String_(input.trim().to_string())),
+        // No bytes were read, so the input has been closed.
+        None => Err(ErrorType::EndOfInput { line }),
+    }
+}
+
+fn remove(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, ErrorType> {
+    let [target, key]: [Value; 2] = arguments.try_into().map_err(|_| arity_error(interpreter))?;
+    match target {
+        Value::Array(mut array) => {
+            // Convert `key` into a `usize` index. Note `usize` is guaranteed to be non-negative.
+            let index = environment::index_value_to_usize(&key, interpreter.argument_line(1))?;
+            if index >= array.len() {
+                return Err(ErrorType::OutOfBoundsIndex { index, line: interpreter.argument_line(1) });
+            }
+            array.remove(index);
+            Ok(Value::Array(array))
+        },
+        Value::Dictionary(mut dict) => {
+            // We can let `HashTable` take care of the removal.
+            dict.remove(&key, interpreter.call_line())?;
+            Ok(Value::Dictionary(dict))
+        },
+        // If it is not an Array or a Dictionary variant, then raise an `ExpectedTypeError`, providing the received type.
+        _ => Err(ErrorType::ExpectedType { expected: String::from("Array or Dictionary"), got: target.type_to_string(), line: interpreter.argument_line(0) }),
+    }
+}
+
+fn size(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, ErrorType> {
+    match &arguments[0] {
+        Value::Array(array) => Ok(Value::Number(array.len() as f64)),
+        Value::Dictionary(dict) => Ok(Value::Number(dict.size() as f64)),
+        Value::String_(s) => Ok(Value::Number(s.len() as f64)),
+        value => Err(ErrorType::ExpectedType { expected: String::from("Array, Dictionary, or String"), got: value.type_to_string(), line: interpreter.call_line() }),
+    }
+}
+
+fn sort(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, ErrorType> {
+    match &arguments[0] {
+        Value::Array(array) => Ok(Value::Array(merge_sort(array, interpreter.argument_line(0))?)),
+        // We cannot sort objects which are not arrays, so raise an error.
+        value => Err(ErrorType::ExpectedType { expected: String::from("Array"), got: value.type_to_string(), line: interpreter.call_line() }),
+    }
+}
+
+fn to_number(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, ErrorType> {
+    match &arguments[0] {
+        Value::Bool(b) => Ok(Value::Number(if *b { 1.0 } else { 0.0 })),
+        Value::Number(x) => Ok(Value::Number(*x)),
+        // If something went wrong during Rust's conversion, raise an error.
+        Value::String_(s) => s.parse::<f64>().map(Value::Number).map_err(|_| ErrorType::CannotConvertToNumber { line: interpreter.call_line() }),
+        // We can only construct numeric representations of Booleans, numbers, and strings.
+        value => Err(ErrorType::ExpectedType { expected: String::from("Boolean, Number or String"), got: value.type_to_string(), line: interpreter.call_line() }),
+    }
+}
+
+fn to_string(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, ErrorType> {
+    match &arguments[0] {
+        Value::Bool(b) => Ok(Value::String_(b.to_string())),
+        Value::Number(x) => Ok(Value::String_(x.to_string())),
+        Value::String_(s) => Ok(Value::String_(s.clone())),
+        // We can only construct string representations of Booleans, numbers, and strings.
+        value => Err(ErrorType::ExpectedType { expected: String::from("Boolean, Number or String"), got: value.type_to_string(), line: interpreter.call_line() }),
+    }
+}
+
+/// Returns the error for a call with the wrong number of arguments. The interpreter checks this before calling the function,
+/// so this is only for destructuring the arguments.
+fn arity_error(interpreter: &Interpreter) -> ErrorType {
+    ErrorType::InternalError { message: String::from("a built-in function was called with the wrong number of arguments"), line: interpreter.call_line() }
+}
+
+/// Sorts the given array using merge sort.
+fn merge_sort(array_to_sort: &[Value], line: usize) -> Result<Vec<Value>, ErrorType> {
+    let n = array_to_sort.len();
+
+    // Base case.
+    if n <= 1 {
+        return Ok(array_to_sort.to_vec());
+    }
+
+    // Recursive case.
+
+    // Recursively sort the left and right halves of the array.
+    let left = merge_sort(&array_to_sort[0..n/2], line)?;
+    let right = merge_sort(&array_to_sort[n/2..], line)?;
+
+    // Merge the two sorted arrays using two pointers.
+    let mut left_index = 0;
+    let mut right_index = 0;
+    let mut merged = Vec::new();
+
+    while left_index < left.len() && right_index < right.len() {
+        match (&left[left_index], &right[right_index]) {
+            // Append the 'lower' of the two to the merged array, and advance the respective pointer.
+            (Value::Number(left_num), Value::Number(right_num)) => {
+                if left_num < right_num {
+                    merged.push(left[left_index].clone());
+                    left_index += 1;
+                } else {
+                    merged.push(right[right_index].clone());
+                    right_index += 1;
+                }
+            },
+            (Value::String_(left_str), Value::String_(right_str)) => {
+                if left_str < right_str {
+                    merged.push(left[left_index].clone());
+                    left_index += 1;
+                } else {
+                    merged.push(right[right_index].clone());
+                    right_index += 1;
+                }
+            },
+
+            // We only support comparisons between numbers and between strings.
+            (_, _) => {
+                return Err(ErrorType::BinaryTypeError {
+                    expected: String::from("Number or String"),
+                    got_left: left[left_index].type_to_string(),
+                    got_right: right[right_index].type_to_string(),
+                    line,
+                });
+            }
+        }
+    }
+
+    // Only one of `left` and `right` will have any elements left.
+    // Append the remainder to the merged array.
+    if left_index < left.len() {
+        while left_index < left.len() {
+            merged.push(left[left_index].clone());
+            left_index += 1;
+        }
+    }
+
+    if right_index < right.len() {
+        while right_index < right.len() {
+            merged.push(right[right_index].clone());
+            right_index += 1;
+        }
+    }
+
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::Value;
+
+    use super::{merge_sort, BUILTINS};
+
+    #[test]
+    fn table_is_sorted() {
+        assert!(BUILTINS.windows(2).all(|pair| pair[0].name < pair[1].name));
+        assert!(BUILTINS.iter().all(|builtin| builtin.signature.starts_with(&format!("{}(", builtin.name))));
+    }
+
+    #[test]
+    fn sorting() {
+        let numbers = vec![Value::Number(3.0), Value::Number(1.0), Value::Number(2.0)];
+        assert_eq!(merge_sort(&numbers, 1), Ok(vec![Value::Number(1.0), Value::Number(2.0), Value::Number(3.0)]));
+        let mixed = vec![Value::Number(1.0), Value::String_(String::from("a"))];
+        assert!(merge_sort(&mixed, 4).is_err_and(|e| e.line() == 4));
+    }
+}
@@ -0,0 +1,627 @@
+// Parsing of the command-line arguments, and the options which control how diagnostics (errors and warnings) are reported.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::coverage::CoverageOptions;
+use crate::doc::DocFormat;
+use crate::dump::DumpFormat;
+use crate::error::{self, ErrorType};
+use crate::json::Json;
+use crate::parser::Parser;
+use crate::profile::ProfileOptions;
+use crate::stmt::Stmt;
+use crate::tokenizer::Tokenizer;
+use crate::trace::TraceOptions;
+use crate::warning::{self, WarningType};
+
+/// The file extension of scripts, used to find them in directories.
+const EXTENSION: &str = "nea";
+
+/// The help message printed by `nea --help`.
+pub const HELP: &str = "\
+Usage: nea [options] [script [args...]]
+       nea [options] run <script> [args...]
+       nea [options] debug <script> [args...]
+       nea dap
+       nea lsp
+       nea [options] check <script>...
+       nea [options] (tokens | ast) <script>
+       nea [options] fmt [--check] <script>...
+       nea [options] test [path...]
+       nea [options] doc [path...]
+       nea [options] -e <code> [args...]
+
+Runs the script, or starts the REPL if no script is given. The script can be `-` to read it from stdin.
+
+Commands:
+  run <script> [args...]   run a script; the script can access the arguments after it with `args()`
+  debug <script> [args...] run a script in the debugger, which pauses before the first statement (enter `help` there for its commands)
+  dap                      start a Debug Adapter Protocol server on stdin and stdout, for debugging in editors such as VS Code
+  lsp                      start a Language Server Protocol server on stdin and stdout, for errors, navigation and completion in editors
+  check <script>...        check scripts for errors and warnings without running them
+  tokens <script>          print the tokens of a script
+  ast <script>             print the abstract syntax tree of a script
+  fmt <script>...          format scripts in place, or print the formatted code if the script is `-` or given with `-e`
+  test [path...]           run the `test_*` functions in scripts, searching directories for `.nea` files (by default, `.`)
+  doc [path...]            print a reference of the functions in scripts and their `##` comments, and of the built-in functions
+
+Options:
+  -e, --eval <code>        run the given code instead of a script
+  --warnings <mode>        `show` (the default) or `hide` warnings, or `deny` them, so that a script with warnings is not run or fails `check`
+  --error-format <format>  `human` (the default), or `json` to print each error and warning as a JSON object on its own line
+  --format <format>        `tree` (the default) or `json` output for `tokens` and `ast`; `markdown` (the default) or `html` for `doc`
+  --check                  for `fmt`, list the scripts which are not formatted instead of formatting them
+  --trace                  for `run`, log each statement, function call and assignment to stderr as the script runs
+  --trace-function <name>  only trace within calls to the function; can be given more than once, and implies `--trace`
+  --trace-depth <depth>    only trace up to the given depth of nested function calls, where 0 is the top level; implies `--trace`
+  --profile                for `run`, print to stderr how many times each function and line ran and how long they took
+  --profile-folded <file>  also write the time spent in each call stack to the file, for flame graph tools; implies `--profile`
+  --coverage               for `test`, print which lines, branches and functions of the scripts the tests ran
+  --coverage-lcov <file>   also write the coverage to the file in the lcov format, e.g., for CI services; implies `--coverage`
+  --coverage-min <percent> fail if less than the given percentage of lines are covered; implies `--coverage`
+  --coverage-min-branches <percent>
+                           fail if less than the given percentage of branches are covered; implies `--coverage`
+  -h, --help               print this help message
+  -V, --version            print the version
+";
+
+/// The subcommands, which have to come before the script.
+const SUBCOMMANDS: [&str; 10] = ["ast", "check", "dap", "debug", "doc", "fmt", "lsp", "run", "test", "tokens"];
+
+/// What to do with warnings found by the linter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WarningMode {
+    Show,
+    Hide,
+    Deny,  // Report the warnings and do not run the script, as if they were errors.
+}
+
+/// How to print errors and warnings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+/// The options given by flags, which apply to every command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub warnings: WarningMode,
+    pub error_format: ErrorFormat,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            warnings: WarningMode::Show,
+            error_format: ErrorFormat::Human,
+        }
+    }
+}
+
+/// Where to read source code from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Source {
+    File(String),
+    Stdin,  // Given as `-`.
+    Code(String),  // Given with `-e`.
+}
+
+impl Source {
+    /// Interprets a command-line argument as a source, where `-` means stdin.
+    fn from_arg(arg: &str) -> Self {
+        if arg == "-" {
+            Self::Stdin
+        } else {
+            Self::File(arg.to_string())
+        }
+    }
+
+    /// Returns the name of the source used in diagnostics, e.g., the file path.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::File(path) => path,
+            Self::Stdin => "<stdin>",
+            Self::Code(..) => "<eval>",
+        }
+    }
+
+    /// Reads the source code, returning a message describing the problem if it could not be read.
+    pub fn read(&self) -> Result<String, String> {
+        match self {
+            Self::File(path) => fs::read_to_string(path).map_err(|e| format!("Failed to read file `{}`: {}", path, e)),
+            Self::Stdin => {
+                let mut source = String::new();
+                io::stdin().read_to_string(&mut source).map_err(|e| format!("Failed to read from stdin: {}", e))?;
+                Ok(source)
+            },
+            Self::Code(code) => Ok(code.clone()),
+        }
+    }
+}
+
+/// The command given by the command-line arguments.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Repl,
+    Run {
+        source: Source,
+        args: Vec<String>,  // The arguments after the script, which are passed to it.
+        trace: Option<TraceOptions>,  // What to trace as the script runs, if it is traced.
+        profile: Option<ProfileOptions>,  // How to profile the script, if it is profiled.
+    },
+    Debug {
+        source: Source,
+        args: Vec<String>,
+    },
+    Dap,
+    Lsp,
+    Check {
+        sources: Vec<Source>,
+    },
+    Tokens {
+        source: Source,
+        format: DumpFormat,
+    },
+    Ast {
+        source: Source,
+        format: DumpFormat,
+    },
+    Fmt {
+        sources: Vec<Source>,
+        check: bool,  // Whether to only check that the sources are formatted.
+    },
+    Test {
+        paths: Vec<String>,  // The scripts and directories of scripts to run the tests of.
+        coverage: Option<CoverageOptions>,  // What coverage to report, if it is recorded.
+    },
+    Doc {
+        paths: Vec<String>,  // The scripts and directories of scripts to document.
+        format: DocFormat,
+    },
+    Help,
+    Version,
+}
+
+/// Parses the command-line arguments, excluding the name of the binary.
+/// Returns a message describing the problem if they are invalid.
+pub fn parse_args(args: &[String]) -> Result<(Command, Options), String> {
+    let mut options = Options::default();
+    let mut subcommand: Option<&str> = None;
+    let mut code: Option<String> = None;
+    let mut format: Option<String> = None;  // Which formats are valid depends on the command.
+    let mut check = false;
+    let mut trace: Option<TraceOptions> = None;
+    let mut profile: Option<ProfileOptions> = None;
+    let mut coverage: Option<CoverageOptions> = None;
+    let mut positionals: Vec<String> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok((Command::Help, options)),
+            "-V" | "--version" => return Ok((Command::Version, options)),
+            "-e" | "--eval" => code = Some(option_value(arg, args.next())?),
+            "--warnings" => {
+                options.warnings = match option_value(arg, args.next())?.as_str() {
+                    "show" => WarningMode::Show,
+                    "hide" => WarningMode::Hide,
+                    "deny" => WarningMode::Deny,
+                    other => return Err(format!("unknown warning mode `{}`; expected `show`, `hide` or `deny`", other)),
+                };
+            },
+            "--error-format" => {
+                options.error_format = match option_value(arg, args.next())?.as_str() {
+                    "human" => ErrorFormat::Human,
+                    "json" => ErrorFormat::Json,
+                    other => return Err(format!("unknown error format `{}`; expected `human` or `json`", other)),
+                };
+            },
+            "--format" => format = Some(option_value(arg, args.next())?),
+            "--check" => check = true,
+            "--trace" => {
+                trace.get_or_insert_with(TraceOptions::default);
+            },
+            "--trace-function" => {
+                // The filters imply `--trace`.
+                let name = option_value(arg, args.next())?;
+                trace.get_or_insert_with(TraceOptions::default).functions.push(name);
+            },
+            "--trace-depth" => {
+                let depth = option_value(arg, args.next())?;
+                let depth = depth.parse().map_err(|_| format!("`{}` expects a non-negative whole number, not `{}`", arg, depth))?;
+                trace.get_or_insert_with(TraceOptions::default).max_depth = Some(depth);
+            },
+            "--profile" => {
+                profile.get_or_insert_with(ProfileOptions::default);
+            },
+            "--profile-folded" => {
+                let path = option_value(arg, args.next())?;
+                profile.get_or_insert_with(ProfileOptions::default).folded = Some(path);
+            },
+            "--coverage" => {
+                coverage.get_or_insert_with(CoverageOptions::default);
+            },
+            "--coverage-lcov" => {
+                let path = option_value(arg, args.next())?;
+                coverage.get_or_insert_with(CoverageOptions::default).lcov = Some(path);
+            },
+            "--coverage-min" | "--coverage-min-branches" => {
+                let percent = option_value(arg, args.next())?;
+                let percent = percent.parse::<f64>().ok()
+                    .filter(|percent| (0.0..=100.0).contains(percent))
+                    .ok_or_else(|| format!("`{}` expects a percentage from 0 to 100, not `{}`", arg, percent))?;
+                let options = coverage.get_or_insert_with(CoverageOptions::default);
+                if arg == "--coverage-min" {
+                    options.min_lines = Some(percent);
+                } else {
+                    options.min_branches = Some(percent);
+                }
+            },
+            "--" => {
+                // Everything after `--` is positional, even if it starts with `-`.
+                positionals.extend(args.by_ref().cloned());
+            },
+            option if option.starts_with('-') && option != "-" => return Err(format!("unknown option `{}`", option)),
+            name if subcommand.is_none() && positionals.is_empty() && code.is_none() && SUBCOMMANDS.contains(&name) => {
+                subcommand = Some(name);
+            },
+            _ => {
+                positionals.push(arg.clone());
+                if matches!(subcommand, None | Some("run" | "debug")) {
+                    // The remaining arguments belong to the script, so they are not parsed as options.
+                    positionals.extend(args.by_ref().cloned());
+                }
+            },
+        }
+    }
+
+    if trace.is_some() && profile.is_some() {
+        return Err(String::from("`--trace` and `--profile` cannot be used together, as tracing would slow down what is profiled"));
+    }
+    // The options which only some commands use, and whether they were given, checked once the command is known.
+    let (traced, profiled, covered) = (trace.is_some(), profile.is_some(), coverage.is_some());
+
+    let command = match subcommand {
+        Some(name @ ("check" | "fmt")) => {
+            let mut sources: Vec<Source> = code.into_iter().map(Source::Code).collect();
+            sources.extend(positionals.iter().map(|arg| Source::from_arg(arg)));
+            if sources.is_empty() {
+                return Err(format!("`{}` expects at least one script", name));
+            }
+            if name == "check" {
+                Command::Check { sources }
+            } else {
+                Command::Fmt { sources, check }
+            }
+        },
+        Some("debug") => {
+            let source = match code {
+                Some(code) => Source::Code(code),
+                None if !positionals.is_empty() => Source::from_arg(&positionals.remove(0)),
+                None => return Err(String::from("`debug` expects a script")),
+            };
+            if source == Source::Stdin {
+                return Err(String::from("`debug` cannot read the script from stdin, as it reads commands from stdin"));
+            }
+            Command::Debug { source, args: positionals }
+        },
+        Some("dap") => {
+            if code.is_some() || !positionals.is_empty() {
+                return Err(String::from("`dap` does not take a script, as the editor chooses it"));
+            }
+            Command::Dap
+        },
+        Some("lsp") => {
+            if code.is_some() || !positionals.is_empty() {
+                return Err(String::from("`lsp` does not take a script, as the editor sends the documents it opens"));
+            }
+            Command::Lsp
+        },
+        Some("test") => {
+            if code.is_some() {
+                return Err(String::from("`test` cannot run code given with `-e`"));
+            }
+            if positionals.is_empty() {
+                positionals.push(String::from("."));
+            }
+            Command::Test { paths: positionals, coverage }
+        },
+        Some("doc") => {
+            if code.is_some() {
+                return Err(String::from("`doc` cannot document code given with `-e`"));
+            }
+            if positionals.is_empty() {
+                positionals.push(String::from("."));
+            }
+            let format = match format.as_deref() {
+                None | Some("markdown") => DocFormat::Markdown,
+                Some("html") => DocFormat::Html,
+                Some(other) => return Err(format!("unknown format `{}`; expected `markdown` or `html`", other)),
+            };
+            Command::Doc { paths: positionals, format }
+        },
+        Some(name @ ("tokens" | "ast")) => {
+            let mut sources: Vec<Source> = code.into_iter().map(Source::Code).collect();
+            sources.extend(positionals.iter().map(|arg| Source::from_arg(arg)));
+            if sources.len() != 1 {
+                return Err(format!("`{}` expects exactly one script", name));
+            }
+            let source = sources.remove(0);
+            let format = match format.as_deref() {
+                None | Some("tree") => DumpFormat::Tree,
+                Some("json") => DumpFormat::Json,
+                Some(other) => return Err(format!("unknown format `{}`; expected `tree` or `json`", other)),
+            };
+            if name == "tokens" {
+                Command::Tokens { source, format }
+            } else {
+                Command::Ast { source, format }
+            }
+        },
+        _ => match code {
+            Some(code) => Command::Run { source: Source::Code(code), args: positionals, trace, profile },
+            None if !positionals.is_empty() => {
+                let source = Source::from_arg(&positionals.remove(0));
+                Command::Run { source, args: positionals, trace, profile }
+            },
+            None if subcommand.is_some() => return Err(String::from("`run` expects a script")),
+            None => Command::Repl,
+        },
+    };
+
+    // Options for other commands are rejected rather than ignored, as they would silently do nothing.
+    let misplaced = [
+        (format.is_some(), matches!(command, Command::Tokens {..} | Command::Ast {..} | Command::Doc {..}), "`--format` only applies to `tokens`, `ast` and `doc`"),
+        (check, matches!(command, Command::Fmt {..}), "`--check` only applies to `fmt`"),
+        (traced, matches!(command, Command::Run {..}), "the `--trace` options only apply to `run`"),
+        (profiled, matches!(command, Command::Run {..}), "the `--profile` options only apply to `run`"),
+        (covered, matches!(command, Command::Test {..}), "the `--coverage` options only apply to `test`"),
+    ];
+    if let Some((.., message)) = misplaced.iter().find(|(given, applies, _)| *given && !applies) {
+        return Err(message.to_string());
+    }
+    Ok((command, options))
+}
+
+/// Returns the value following an option, e.g., the code after `-e`.
+fn option_value(option: &str, value: Option<&String>) -> Result<String, String> {
+    value.cloned().ok_or_else(|| format!("`{}` expects a value", option))
+}
+
+/// Reports errors found in the source named `name` in the chosen format.
+pub fn report_errors(errors: &[ErrorType], name: &str, options: &Options) {
+    write_errors(&mut io::stdout().lock(), errors, name, options);
+}
+
+/// Writes errors found in the source named `name` to `output` in the chosen format, e.g., to the error output of an interpreter.
+pub fn write_errors(output: &mut dyn Write, errors: &[ErrorType], name: &str, options: &Options) {
+    match options.error_format {
+        ErrorFormat::Human => error::write_errors(output, errors),
+        ErrorFormat::Json => {
+            for error in errors {
+                let json = Json::Object(vec![
+                    (String::from("severity"), Json::from("error")),
+                    (String::from("file"), Json::from(name)),
+                    (String::from("line"), Json::from(error.line())),
+                    (String::from("message"), Json::from(error.message())),
+                    (String::from("hint"), Json::from(error.hint())),
+                ]);
+                // As in `error::write_errors()`, a closed stdout is ignored.
+                let _ = writeln!(output, "{}", json);
+            }
+        },
+    }
+}
+
+/// Reports warnings found in the source named `name` in the chosen format, unless they are hidden.
+pub fn report_warnings(warnings: &[WarningType], name: &str, options: &Options) {
+    match (options.warnings, options.error_format) {
+        (WarningMode::Hide, _) => (),
+        (_, ErrorFormat::Human) => warning::report_warnings(warnings),
+        (_, ErrorFormat::Json) => {
+            let mut stdout = io::stdout().lock();
+            for warning in warnings {
+                let json = Json::Object(vec![
+                    (String::from("severity"), Json::from("warning")),
+                    (String::from("file"), Json::from(name)),
+                    (String::from("line"), Json::from(warning.line())),
+                    (String::from("message"), Json::from(warning::message(warning))),
+                    (String::from("name"), Json::from(warning.name())),
+                ]);
+                let _ = writeln!(stdout, "{}", json);
+            }
+        },
+    }
+}
+
+/// Adds `path` to `files` if it is a script, or every script in it, in order of their paths, if it is a directory.
+pub fn find_scripts(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        // A file given directly is run whatever its extension is. This also reports a missing file when it is read.
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            find_scripts(&entry, files)?;
+        } else if entry.extension().is_some_and(|extension| extension == EXTENSION) {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Tokenizes and parses the source code, returning every error found.
+pub fn parse_script(source: &str) -> Result<Vec<Stmt>, Vec<ErrorType>> {
+    let (tokens, mut errors) = Tokenizer::new(source).tokenize();
+    match Parser::new(tokens).parse() {
+        Ok(ast) if errors.is_empty() => Ok(ast),
+        Ok(..) => Err(errors),
+        Err(parser_errors) => {
+            errors.extend(parser_errors);
+            Err(errors)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coverage::CoverageOptions;
+    use crate::doc::DocFormat;
+    use crate::dump::DumpFormat;
+    use crate::profile::ProfileOptions;
+    use crate::trace::TraceOptions;
+
+    use super::{parse_args, Command, ErrorFormat, Options, Source, WarningMode};
+
+    /// Parses the space-separated arguments.
+    fn parse(args: &str) -> Result<(Command, Options), String> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn repl() {
+        assert_eq!(parse(""), Ok((Command::Repl, Options::default())));
+    }
+
+    #[test]
+    fn run() {
+        let expected = Command::Run { source: Source::File(String::from("a.nea")), args: vec![String::from("x"), String::from("--y")], trace: None, profile: None };
+        assert_eq!(parse("a.nea x --y").unwrap().0, expected);
+        assert_eq!(parse("run a.nea x --y").unwrap().0, expected);
+        assert_eq!(parse("run - 1").unwrap().0, Command::Run { source: Source::Stdin, args: vec![String::from("1")], trace: None, profile: None });
+        // A script called `run` can still be run with `--`.
+        assert_eq!(parse("-- run").unwrap().0, Command::Run { source: Source::File(String::from("run")), args: Vec::new(), trace: None, profile: None });
+    }
+
+    #[test]
+    fn trace() {
+        let Command::Run { trace, .. } = parse("run --trace a.nea --trace").unwrap().0 else { panic!() };
+        assert_eq!(trace, Some(TraceOptions::default()));
+        let Command::Run { trace, .. } = parse("--trace-function f --trace-function g --trace-depth 2 a.nea").unwrap().0 else { panic!() };
+        assert_eq!(trace, Some(TraceOptions { functions: vec![String::from("f"), String::from("g")], max_depth: Some(2) }));
+        assert!(parse("--trace-depth -1 a.nea").is_err());
+    }
+
+    #[test]
+    fn profile() {
+        let Command::Run { profile, .. } = parse("run --profile a.nea").unwrap().0 else { panic!() };
+        assert_eq!(profile, Some(ProfileOptions::default()));
+        let Command::Run { profile, .. } = parse("--profile-folded out.folded a.nea").unwrap().0 else { panic!() };
+        assert_eq!(profile, Some(ProfileOptions { folded: Some(String::from("out.folded")) }));
+        assert!(parse("--trace --profile a.nea").is_err());
+    }
+
+    #[test]
+    fn eval() {
+        assert_eq!(parse("-e 1 run").unwrap().0, Command::Run { source: Source::Code(String::from("1")), args: vec![String::from("run")], trace: None, profile: None });
+        assert_eq!(parse("--eval"), Err(String::from("`--eval` expects a value")));
+    }
+
+    #[test]
+    fn check() {
+        let (command, options) = parse("check a.nea --warnings deny b.nea").unwrap();
+        assert_eq!(command, Command::Check { sources: vec![Source::File(String::from("a.nea")), Source::File(String::from("b.nea"))] });
+        assert_eq!(options.warnings, WarningMode::Deny);
+        assert!(parse("check").is_err());
+    }
+
+    #[test]
+    fn misplaced_options() {
+        assert_eq!(parse("--format json a.nea"), Err(String::from("`--format` only applies to `tokens`, `ast` and `doc`")));
+        assert!(parse("check --check a.nea").is_err());
+        assert!(parse("debug --trace-depth 1 a.nea").is_err());
+        assert!(parse("--profile").is_err());
+        assert!(parse("--coverage-min 50 a.nea").is_err());
+        // After the script, they are arguments of the script rather than options.
+        assert!(parse("a.nea --format json").is_ok());
+    }
+
+    #[test]
+    fn dumps() {
+        assert_eq!(parse("tokens a.nea").unwrap().0, Command::Tokens { source: Source::File(String::from("a.nea")), format: DumpFormat::Tree });
+        assert_eq!(parse("ast --format json -").unwrap().0, Command::Ast { source: Source::Stdin, format: DumpFormat::Json });
+        assert_eq!(parse("ast -e 1").unwrap().0, Command::Ast { source: Source::Code(String::from("1")), format: DumpFormat::Tree });
+        assert!(parse("ast a.nea b.nea").is_err());
+        assert!(parse("tokens --format yaml a.nea").is_err());
+        assert!(parse("tokens --format html a.nea").is_err());
+    }
+
+    #[test]
+    fn fmt() {
+        let sources = vec![Source::File(String::from("a.nea")), Source::Stdin];
+        assert_eq!(parse("fmt a.nea -").unwrap().0, Command::Fmt { sources: sources.clone(), check: false });
+        assert_eq!(parse("fmt a.nea --check -").unwrap().0, Command::Fmt { sources, check: true });
+        assert!(parse("fmt").is_err());
+    }
+
+    #[test]
+    fn debug() {
+        assert_eq!(parse("debug a.nea x").unwrap().0, Command::Debug { source: Source::File(String::from("a.nea")), args: vec![String::from("x")] });
+        assert_eq!(parse("debug -e 1").unwrap().0, Command::Debug { source: Source::Code(String::from("1")), args: Vec::new() });
+        assert!(parse("debug").is_err());
+        assert!(parse("debug -").is_err());
+    }
+
+    #[test]
+    fn dap() {
+        assert_eq!(parse("dap").unwrap().0, Command::Dap);
+        assert!(parse("dap a.nea").is_err());
+    }
+
+    #[test]
+    fn lsp() {
+        assert_eq!(parse("lsp").unwrap().0, Command::Lsp);
+        assert!(parse("lsp -e 1").is_err());
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(parse("test").unwrap().0, Command::Test { paths: vec![String::from(".")], coverage: None });
+        assert_eq!(parse("test a b.nea").unwrap().0, Command::Test { paths: vec![String::from("a"), String::from("b.nea")], coverage: None });
+    }
+
+    #[test]
+    fn coverage() {
+        let Command::Test { coverage, .. } = parse("test --coverage").unwrap().0 else { panic!() };
+        assert_eq!(coverage, Some(CoverageOptions::default()));
+        let Command::Test { coverage, .. } = parse("test --coverage-lcov lcov.info --coverage-min 80 --coverage-min-branches 62.5 tests").unwrap().0 else { panic!() };
+        assert_eq!(coverage, Some(CoverageOptions { lcov: Some(String::from("lcov.info")), min_lines: Some(80.0), min_branches: Some(62.5) }));
+        assert!(parse("test --coverage-min 101").is_err());
+        assert!(parse("test --coverage-min all").is_err());
+    }
+
+    #[test]
+    fn doc() {
+        assert_eq!(parse("doc").unwrap().0, Command::Doc { paths: vec![String::from(".")], format: DocFormat::Markdown });
+        assert_eq!(parse("doc --format html src lib.nea").unwrap().0, Command::Doc { paths: vec![String::from("src"), String::from("lib.nea")], format: DocFormat::Html });
+        assert!(parse("doc --format json").is_err());
+        assert!(parse("doc -e 1").is_err());
+    }
+
+    #[test]
+    fn options() {
+        let (command, options) = parse("--error-format json --warnings hide run a.nea --help").unwrap();
+        assert_eq!(command, Command::Run { source: Source::File(String::from("a.nea")), args: vec![String::from("--help")], trace: None, profile: None });
+        assert_eq!(options, Options { warnings: WarningMode::Hide, error_format: ErrorFormat::Json });
+        assert_eq!(parse("--help a.nea").unwrap().0, Command::Help);
+        assert_eq!(parse("-V").unwrap().0, Command::Version);
+    }
+
+    #[test]
+    fn invalid_options() {
+        assert_eq!(parse("--nope"), Err(String::from("unknown option `--nope`")));
+        assert!(parse("--warnings loud").is_err());
+        assert!(parse("--error-format xml").is_err());
+        assert!(parse("run").is_err());
+    }
+}
@@ -0,0 +1,256 @@
+// Conversions between `Value` and Rust types, so that programs embedding the interpreter can move data in and out of scripts,
+// e.g., `Value::from(vec![1, 2])` or `Vec::<String>::try_from(value)`.
+// Conversions from Rust types cannot fail, except for maps whose keys cannot be hashed (see `HashTable`).
+// Conversions to Rust types fail with the error a script would raise, on line 0 as there is no line in the source code to report.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::error::ErrorType;
+use crate::hash_table::HashTable;
+use crate::value::Value;
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Self::Number(x)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(x: f32) -> Self {
+        Self::Number(f64::from(x))
+    }
+}
+
+// Every number is an `f64`, so integers beyond 2^53 in magnitude are rounded to the nearest one it can represent.
+macro_rules! from_integer {
+    ($($integer:ty),*) => {
+        $(
+            impl From<$integer> for Value {
+                fn from(x: $integer) -> Self {
+                    Self::Number(x as f64)
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::String_(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::String_(s)
+    }
+}
+
+impl From<char> for Value {
+    fn from(c: char) -> Self {
+        Self::String_(String::from(c))
+    }
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Self::Null
+    }
+}
+
+impl From<HashTable> for Value {
+    fn from(dict: HashTable) -> Self {
+        Self::Dictionary(dict)
+    }
+}
+
+/// `None` is converted to `null`.
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Self {
+        option.map_or(Self::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(vec: Vec<T>) -> Self {
+        Self::Array(vec.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Fails if a key cannot be hashed, e.g., if it is itself a dictionary.
+impl<K: Into<Value>, V: Into<Value>> TryFrom<HashMap<K, V>> for Value {
+    type Error = ErrorType;
+
+    fn try_from(map: HashMap<K, V>) -> Result<Self, Self::Error> {
+        let mut dict = HashTable::new();
+        for (key, value) in map {
+            dict.insert(key.into(), value.into(), 0)?;
+        }
+        Ok(Self::Dictionary(dict))
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = ErrorType;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            _ => Err(expected("Boolean", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = ErrorType;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(x) => Ok(x),
+            _ => Err(expected("Number", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for f32 {
+    type Error = ErrorType;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        f64::try_from(value).map(|x| x as f32)
+    }
+}
+
+// Only whole numbers in the range of the integer type are converted, rather than being rounded or saturated.
+macro_rules! try_into_integer {
+    ($($integer:ty),*) => {
+        $(
+            impl TryFrom<Value> for $integer {
+                type Error = ErrorType;
+
+                fn try_from(value: Value) -> Result<Self, Self::Error> {
+                    let x = f64::try_from(value)?;
+                    // `MAX + 1` is a power of two, so unlike `MAX` it is exactly representable as an `f64`.
+                    if x.fract() == 0.0 && x >= <$integer>::MIN as f64 && x < <$integer>::MAX as f64 + 1.0 {
+                        Ok(x as $integer)
+                    } else {
+                        Err(ErrorType::NumberOutOfRange { number: x, target: String::from(stringify!($integer)), line: 0 })
+                    }
+                }
+            }
+        )*
+    };
+}
+
+try_into_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl TryFrom<Value> for String {
+    type Error = ErrorType;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String_(s) => Ok(s),
+            _ => Err(expected("String", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for HashTable {
+    type Error = ErrorType;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Dictionary(dict) => Ok(dict),
+            _ => Err(expected("Dictionary", &value)),
+        }
+    }
+}
+
+/// `null` is converted to `None`, and any other value to `Some`.
+impl<T: TryFrom<Value, Error = ErrorType>> TryFrom<Value> for Option<T> {
+    type Error = ErrorType;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Null => Ok(None),
+            _ => T::try_from(value).map(Some),
+        }
+    }
+}
+
+impl<T: TryFrom<Value, Error = ErrorType>> TryFrom<Value> for Vec<T> {
+    type Error = ErrorType;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Array(array) => array.into_iter().map(T::try_from).collect(),
+            _ => Err(expected("Array", &value)),
+        }
+    }
+}
+
+impl<K, V> TryFrom<Value> for HashMap<K, V>
+where
+    K: TryFrom<Value, Error = ErrorType> + Eq + Hash,
+    V: TryFrom<Value, Error = ErrorType>,
+{
+    type Error = ErrorType;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        HashTable::try_from(value)?
+            .flatten()
+            .into_iter()
+            .map(|entry| Ok((K::try_from(entry.key)?, V::try_from(entry.value)?)))
+            .collect()
+    }
+}
+
+/// Returns the error for a value which is not of the type being converted to.
+fn expected(expected: &str, got: &Value) -> ErrorType {
+    ErrorType::ExpectedType { expected: String::from(expected), got: got.type_to_string(), line: 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::error::ErrorType;
+    use crate::value::Value;
+
+    #[test]
+    fn to_value() {
+        assert_eq!(Value::from(vec![Some(1), None]), Value::Array(vec![Value::Number(1.0), Value::Null]));
+        assert_eq!(Value::from("a"), Value::String_(String::from("a")));
+        let map = HashMap::from([("k", vec![true])]);
+        let Ok(Value::Dictionary(dict)) = Value::try_from(map) else { panic!() };
+        assert_eq!(dict.get(&Value::from("k"), 1), Ok(&Value::Array(vec![Value::Bool(true)])));
+    }
+
+    #[test]
+    fn from_value() {
+        let array = Value::from(vec![Value::from(1), Value::Null]);
+        assert_eq!(Vec::<Option<u8>>::try_from(array.clone()), Ok(vec![Some(1), None]));
+        assert_eq!(Vec::<u8>::try_from(array), Err(ErrorType::ExpectedType { expected: String::from("Number"), got: String::from("Null"), line: 0 }));
+
+        let map = HashMap::from([(String::from("a"), 1.5), (String::from("b"), 2.0)]);
+        assert_eq!(HashMap::<String, f64>::try_from(Value::try_from(map.clone()).unwrap()), Ok(map));
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(i32::try_from(Value::from(-3)), Ok(-3));
+        assert_eq!(u8::try_from(Value::from(256)), Err(ErrorType::NumberOutOfRange { number: 256.0, target: String::from("u8"), line: 0 }));
+        assert!(u32::try_from(Value::from(-1)).is_err());
+        assert!(i64::try_from(Value::from(0.5)).is_err());
+        assert!(i64::try_from(Value::Number(f64::NAN)).is_err());
+        assert!(i64::try_from(Value::Number(2f64.powi(63))).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+// Code coverage for `nea test --coverage`, which records which statements the tests executed, which way the conditions of
+// `if` statements and `while` loops went, and which functions were called. It is reported as a summary in the terminal
+// and, optionally, as an lcov file (see https://github.com/linux-test-project/lcov), which CI services and editors can read.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::error::ErrorType;
+use crate::interpreter::{Hook, Interpreter};
+use crate::stmt::{Stmt, StmtType};
+use crate::value::Value;
+
+/// What coverage to record and report, given by the options of `nea test`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CoverageOptions {
+    pub lcov: Option<String>,  // The path of the lcov file to write, if any.
+    pub min_lines: Option<f64>,  // The percentage of lines which have to be covered, if any.
+    pub min_branches: Option<f64>,  // The percentage of branches which have to be covered, if any.
+}
+
+/// A statement which branches: an `if` statement, whose branches are its `then` and `else` bodies,
+/// or a `while` loop, whose branches are running the body and exiting the loop.
+#[derive(Clone, Debug, PartialEq)]
+struct Branch {
+    line: usize,
+    id: usize,  // The `branch` of the statement, which identifies it even among others on the same line with the same condition.
+    taken: [usize; 2],  // The number of times the condition was `true` and `false`, respectively.
+}
+
+/// A user-defined function.
+#[derive(Clone, Debug, PartialEq)]
+struct Function {
+    name: String,
+    line: usize,
+    calls: usize,
+}
+
+/// The coverage of a script.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileCoverage {
+    path: String,
+    lines: BTreeMap<usize, usize>,  // The number of times statements on each line with statements were executed.
+    branches: Vec<Branch>,
+    functions: Vec<Function>,
+}
+
+impl FileCoverage {
+    /// Initialises the coverage of a script, where nothing has been executed yet.
+    pub fn new(path: &str, ast: &[Stmt]) -> Self {
+        let mut coverage = Self {
+            path: path.to_string(),
+            lines: BTreeMap::new(),
+            branches: Vec::new(),
+            functions: Vec::new(),
+        };
+        for stmt in ast {
+            coverage.add_statement(stmt);
+        }
+        coverage
+    }
+
+    /// Adds a statement, and the statements within it, as not executed yet.
+    fn add_statement(&mut self, stmt: &Stmt) {
+        // Blocks only group other statements, so they are not executed themselves, like in `Interpreter::execute()`.
+        if !matches!(stmt.stmt_type, StmtType::Block {..}) {
+            self.lines.insert(stmt.line, 0);
+        }
+        match &stmt.stmt_type {
+            StmtType::Block { body } => {
+                for stmt in body {
+                    self.add_statement(stmt);
+                }
+            },
+            StmtType::Function { name, body, .. } => {
+                // If a name is declared more than once, calls are counted for the first function.
+                if !self.functions.iter().any(|function| function.name == *name) {
+                    self.functions.push(Function { name: name.clone(), line: stmt.line, calls: 0 });
+                }
+                self.add_statement(body);
+            },
+            StmtType::If { then_body, else_body, branch, .. } => {
+                self.branches.push(Branch { line: stmt.line, id: *branch, taken: [0, 0] });
+                self.add_statement(then_body);
+                if let Some(else_body) = else_body {
+                    self.add_statement(else_body);
+                }
+            },
+            StmtType::While { body, branch, .. } => {
+                self.branches.push(Branch { line: stmt.line, id: *branch, taken: [0, 0] });
+                self.add_statement(body);
+            },
+            _ => {},
+        }
+    }
+
+    /// Returns the number of lines with statements, and how many of them were executed.
+    fn line_counts(&self) -> (usize, usize) {
+        (self.lines.len(), self.lines.values().filter(|count| **count > 0).count())
+    }
+
+    /// Returns the number of branches, and how many of them were taken.
+    fn branch_counts(&self) -> (usize, usize) {
+        let taken = self.branches.iter().flat_map(|branch| branch.taken).filter(|count| *count > 0).count();
+        (self.branches.len() * 2, taken)
+    }
+
+    /// Returns the number of functions, and how many of them were called.
+    fn function_counts(&self) -> (usize, usize) {
+        (self.functions.len(), self.functions.iter().filter(|function| function.calls > 0).count())
+    }
+
+    /// Returns the record of the script in an lcov file.
+    fn lcov_record(&self) -> String {
+        let mut record = format!("TN:\nSF:{}\n", self.path);
+        for function in &self.functions {
+            record.push_str(&format!("FN:{},{}\n", function.line, function.name));
+        }
+        for function in &self.functions {
+            record.push_str(&format!("FNDA:{},{}\n", function.calls, function.name));
+        }
+        let (found, hit) = self.function_counts();
+        record.push_str(&format!("FNF:{}\nFNH:{}\n", found, hit));
+
+        // Branch statements on the same line are numbered as blocks, in the order they appear.
+        let mut blocks: BTreeMap<usize, usize> = BTreeMap::new();
+        for branch in &self.branches {
+            let block = blocks.entry(branch.line).or_default();
+            // A branch whose condition was never evaluated is given as `-` rather than 0.
+            let evaluated = branch.taken.iter().any(|count| *count > 0);
+            for (index, count) in branch.taken.iter().enumerate() {
+                let taken = if evaluated { count.to_string() } else { String::from("-") };
+                record.push_str(&format!("BRDA:{},{},{},{}\n", branch.line, block, index, taken));
+            }
+            *block += 1;
+        }
+        let (found, hit) = self.branch_counts();
+        record.push_str(&format!("BRF:{}\nBRH:{}\n", found, hit));
+
+        for (line, count) in &self.lines {
+            record.push_str(&format!("DA:{},{}\n", line, count));
+        }
+        let (found, hit) = self.line_counts();
+        record.push_str(&format!("LF:{}\nLH:{}\nend_of_record\n", found, hit));
+        record
+    }
+}
+
+/// Returns the contents of an lcov file for the scripts.
+pub fn lcov(files: &[FileCoverage]) -> String {
+    files.iter().map(FileCoverage::lcov_record).collect()
+}
+
+/// Returns what percentage `hit` is of `found`. Having nothing to cover counts as full coverage.
+fn percentage(found: usize, hit: usize) -> f64 {
+    if found == 0 { 100.0 } else { hit as f64 * 100.0 / found as f64 }
+}
+
+/// Formats the number covered out of the total, with the percentage, e.g., `3/4  75.0%`.
+fn format_counts((found, hit): (usize, usize)) -> String {
+    format!("{}/{} {:>6.1}%", hit, found, percentage(found, hit))
+}
+
+/// Adds up the counts of each file.
+fn total(files: &[FileCoverage], counts: fn(&FileCoverage) -> (usize, usize)) -> (usize, usize) {
+    files.iter().map(counts).fold((0, 0), |(found, hit), (file_found, file_hit)| (found + file_found, hit + file_hit))
+}
+
+/// Returns a table of the coverage of each script and the total.
+pub fn summary(files: &[FileCoverage]) -> String {
+    let width = files.iter().map(|file| file.path.len()).chain(["coverage".len()]).max().unwrap_or_default();
+    let mut summary = format!("{:<width$}  {:>16}  {:>16}  {:>16}\n", "coverage", "lines", "branches", "functions");
+    let rows = files.iter()
+        .map(|file| (file.path.as_str(), file.line_counts(), file.branch_counts(), file.function_counts()))
+        .chain([("total", total(files, FileCoverage::line_counts), total(files, FileCoverage::branch_counts), total(files, FileCoverage::function_counts))]);
+    for (name, lines, branches, functions) in rows {
+        summary.push_str(&format!(
+            "{:<width$}  {:>16}  {:>16}  {:>16}\n",
+            name, format_counts(lines), format_counts(branches), format_counts(functions),
+        ));
+    }
+    summary
+}
+
+/// Returns a message for each threshold which the total coverage is below.
+pub fn check_thresholds(files: &[FileCoverage], options: &CoverageOptions) -> Vec<String> {
+    let mut messages = Vec::new();
+    let checks = [
+        ("lines", options.min_lines, total(files, FileCoverage::line_counts)),
+        ("branches", options.min_branches, total(files, FileCoverage::branch_counts)),
+    ];
+    for (name, minimum, (found, hit)) in checks {
+        if let Some(minimum) = minimum {
+            let actual = percentage(found, hit);
+            if actual < minimum {
+                messages.push(format!("The coverage of {} is {:.1}%, which is below the minimum of {}%.", name, actual, minimum));
+            }
+        }
+    }
+    messages
+}
+
+/// Records the coverage of a script as it runs into a `FileCoverage` which is shared with whoever reports it,
+/// so that the coverage of several runs, e.g., of each test, adds up.
+pub struct Recorder {
+    coverage: Arc<Mutex<FileCoverage>>,
+}
+
+impl Recorder {
+    /// Initialises a new instance of `Recorder`, which records into `coverage`.
+    pub fn new(coverage: Arc<Mutex<FileCoverage>>) -> Self {
+        Self { coverage }
+    }
+}
+
+impl Hook for Recorder {
+    fn before_statement(&mut self, _interpreter: &mut Interpreter, stmt: &Stmt) -> Result<(), ErrorType> {
+        *self.coverage.lock().unwrap().lines.entry(stmt.line).or_default() += 1;
+        Ok(())
+    }
+
+    fn condition_evaluated(&mut self, _interpreter: &Interpreter, stmt: &Stmt, value: bool) {
+        let (StmtType::If { branch: id, .. } | StmtType::While { branch: id, .. }) = &stmt.stmt_type else { return };
+        let mut coverage = self.coverage.lock().unwrap();
+        if let Some(branch) = coverage.branches.iter_mut().find(|branch| branch.id == *id) {
+            branch.taken[if value { 0 } else { 1 }] += 1;
+        }
+    }
+
+    fn function_entered(&mut self, _interpreter: &Interpreter, name: &str, _parameters: &[String], _arguments: &[Value]) {
+        // Functions can be called by other names, e.g., after `var g = f`, but those are not counted.
+        if let Some(function) = self.coverage.lock().unwrap().functions.iter_mut().find(|function| function.name == name) {
+            function.calls += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::tokenizer::Tokenizer;
+
+    use super::{check_thresholds, lcov, summary, CoverageOptions, FileCoverage, Recorder};
+
+    /// Runs the source code with the recorder, and returns the coverage.
+    fn coverage(source: &str) -> FileCoverage {
+        let (tokens, _) = Tokenizer::new(source).tokenize();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let coverage = Arc::new(Mutex::new(FileCoverage::new("a.nea", &ast)));
+        let mut interpreter = Interpreter::new();
+        interpreter.set_hook(Box::new(Recorder::new(Arc::clone(&coverage))));
+        interpreter.interpret(ast).unwrap();
+        drop(interpreter);
+        Arc::try_unwrap(coverage).unwrap().into_inner().unwrap()
+    }
+
+    const SOURCE: &str = "\
+func sign(x) {
+    if (x < 0) { return -1 } else if (x == 0) { return 0 }
+    return 1
+}
+func unused() {
+    return null
+}
+var i = 0
+while (i < 2) {
+    i = i + 1 + sign(i)
+}
+";
+
+    #[test]
+    fn lcov_file() {
+        assert_eq!(lcov(&[coverage(SOURCE)]), "\
+TN:
+SF:a.nea
+FN:1,sign
+FN:5,unused
+FNDA:2,sign
+FNDA:0,unused
+FNF:2
+FNH:1
+BRDA:2,0,0,0
+BRDA:2,0,1,2
+BRDA:2,1,0,1
+BRDA:2,1,1,1
+BRDA:9,0,0,2
+BRDA:9,0,1,1
+BRF:6
+BRH:5
+DA:1,1
+DA:2,5
+DA:3,1
+DA:5,1
+DA:6,0
+DA:8,1
+DA:9,1
+DA:10,2
+LF:8
+LH:7
+end_of_record
+");
+    }
+
+    #[test]
+    fn same_condition_on_same_line() {
+        let coverage = coverage("var a = false\nif (a) { print 1 } else { if (a) { print 2 } }\n");
+        assert!(lcov(&[coverage]).contains("BRDA:2,0,0,0\nBRDA:2,0,1,1\nBRDA:2,1,0,0\nBRDA:2,1,1,1\n"));
+    }
+
+    #[test]
+    fn summary_and_thresholds() {
+        let files = [coverage(SOURCE), coverage("if (false) {\n    print 1\n}\n")];
+        assert_eq!(summary(&files), "\
+coverage             lines          branches         functions
+a.nea          7/8   87.5%       5/6   83.3%       1/2   50.0%
+a.nea          1/2   50.0%       1/2   50.0%       0/0  100.0%
+total         8/10   80.0%       6/8   75.0%       1/2   50.0%
+");
+
+        let options = CoverageOptions { lcov: None, min_lines: Some(80.0), min_branches: Some(90.0) };
+        assert_eq!(check_thresholds(&files, &options), vec![String::from("The coverage of branches is 75.0%, which is below the minimum of 90%.")]);
+        assert!(check_thresholds(&files, &CoverageOptions::default()).is_empty());
+    }
+}
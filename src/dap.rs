@@ -0,0 +1,643 @@
+// The Debug Adapter Protocol (DAP) server used by `nea dap`, so that editors such as VS Code can debug scripts.
+// See https://microsoft.github.io/debug-adapter-protocol/specification for the messages.
+//
+// Messages are JSON objects, each preceded by a `Content-Length` header, which are read from stdin and written to stdout.
+// Scripts have a single thread, and the interpreter runs on the same thread as the server, so requests are only read
+// before the script starts, while it is paused, and after it has finished. As stdout carries the messages, the output of the script
+// is sent to the editor in `output` events instead, and `input()` has no input to read.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::debugger::{self, Mode, Stepping};
+use crate::error::ErrorType;
+use crate::interpreter::{Hook, Interpreter};
+use crate::json::{self, Json};
+use crate::parser::Parser;
+use crate::stmt::Stmt;
+use crate::tokenizer::Tokenizer;
+use crate::value::Value;
+
+/// The ID of the only thread of a script.
+const THREAD_ID: usize = 1;
+
+/// A request from the editor.
+struct Request {
+    seq: usize,
+    command: String,
+    arguments: Json,  // `null` if the request has no arguments.
+}
+
+/// Reads requests from the editor, and writes responses and events to it.
+struct Connection {
+    input: Box<dyn BufRead + Send>,
+    output: Box<dyn Write + Send>,
+    seq: usize,  // The sequence number of the last message sent.
+    disconnected: bool,  // Whether the editor has disconnected, after which the server exits.
+}
+
+impl Connection {
+    /// Initialises a new instance of `Connection`.
+    fn new(input: Box<dyn BufRead + Send>, output: Box<dyn Write + Send>) -> Self {
+        Self {
+            input,
+            output,
+            seq: 0,
+            disconnected: false,
+        }
+    }
+
+    /// Reads the next request, or returns `None` if there are no more, e.g., the editor has closed stdin.
+    /// Messages which are not valid requests are skipped.
+    fn read_request(&mut self) -> Option<Request> {
+        loop {
+            let message = json::read_message(&mut self.input)?;
+            let seq = message.get("seq").and_then(Json::as_usize);
+            let command = message.get("command").and_then(Json::as_str);
+            if let (Some(seq), Some(command), Some("request")) = (seq, command, message.get("type").and_then(Json::as_str)) {
+                return Some(Request {
+                    seq,
+                    command: command.to_string(),
+                    arguments: message.get("arguments").cloned().unwrap_or(Json::Null),
+                });
+            }
+        }
+    }
+
+    /// Sends a message with the given members, after its sequence number. A `null` body is left out, as bodies are optional.
+    fn send(&mut self, members: Vec<(&str, Json)>) {
+        self.seq += 1;
+        let mut message = vec![(String::from("seq"), Json::from(self.seq))];
+        message.extend(members.into_iter()
+            .filter(|(name, value)| *name != "body" || *value != Json::Null)
+            .map(|(name, value)| (name.to_string(), value)));
+        json::write_message(&mut self.output, &Json::Object(message));
+    }
+
+    /// Sends a successful response to `request` with the given body.
+    fn respond(&mut self, request: &Request, body: Json) {
+        self.send(vec![
+            ("type", Json::from("response")),
+            ("request_seq", Json::from(request.seq)),
+            ("success", Json::from(true)),
+            ("command", Json::from(request.command.as_str())),
+            ("body", body),
+        ]);
+    }
+
+    /// Sends an unsuccessful response to `request`, with a message which the editor shows to the user.
+    fn fail(&mut self, request: &Request, message: &str) {
+        self.send(vec![
+            ("type", Json::from("response")),
+            ("request_seq", Json::from(request.seq)),
+            ("success", Json::from(false)),
+            ("command", Json::from(request.command.as_str())),
+            ("message", Json::from(message)),
+        ]);
+    }
+
+    /// Sends an event with the given body.
+    fn event(&mut self, event: &str, body: Json) {
+        self.send(vec![
+            ("type", Json::from("event")),
+            ("event", Json::from(event)),
+            ("body", body),
+        ]);
+    }
+
+    /// Sends text to be shown in the editor's debug console, where `category` is, e.g., `stdout` or `stderr`.
+    fn output(&mut self, category: &str, text: &str) {
+        self.event("output", object(vec![("category", Json::from(category)), ("output", Json::from(text))]));
+    }
+
+    /// Handles the requests which do not depend on the state of the script: `threads`, `disconnect` and `terminate`.
+    /// Any other request is refused with `message`.
+    fn handle_other(&mut self, request: &Request, message: &str) {
+        match request.command.as_str() {
+            "threads" => self.respond(request, threads()),
+            "disconnect" | "terminate" => {
+                self.respond(request, Json::Null);
+                self.disconnected = true;
+            },
+            _ => self.fail(request, message),
+        }
+    }
+}
+
+/// Sends the output of the script to the editor in `output` events. It is wrapped in a `LineWriter`, so that each line is sent in one event.
+struct OutputEvents(Arc<Mutex<Connection>>);
+
+impl Write for OutputEvents {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut connection = self.0.try_lock().map_err(|e| io::Error::other(e.to_string()))?;
+        connection.output("stdout", &String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The script to debug, given in the `launch` request.
+struct Launch {
+    program: PathBuf,
+    lines: usize,  // The number of lines in the script.
+    ast: Vec<Stmt>,
+    args: Vec<String>,
+    stop_on_entry: bool,  // Whether to pause before the first statement.
+    no_debug: bool,  // Whether to run the script without pausing at all.
+}
+
+/// Runs the server on stdin and stdout until the editor disconnects.
+pub fn run_server() {
+    let connection = Connection::new(Box::new(BufReader::new(io::stdin())), Box::new(io::stdout()));
+    serve(Arc::new(Mutex::new(connection)));
+}
+
+/// Handles a debugging session: configuring it, running the script, and waiting for the editor to disconnect.
+fn serve(connection: Arc<Mutex<Connection>>) {
+    let mut launch: Option<Launch> = None;
+    let mut breakpoints = BTreeSet::new();
+    let mut configured = false;
+
+    // The editor initializes the server, launches the script and sets the breakpoints before the script starts.
+    while launch.is_none() || !configured {
+        let Some(request) = connection.lock().unwrap().read_request() else { return };
+        let mut connection = connection.lock().unwrap();
+        match request.command.as_str() {
+            "initialize" => connection.respond(&request, object(vec![
+                ("supportsConfigurationDoneRequest", Json::from(true)),
+                ("supportsEvaluateForHovers", Json::from(true)),
+                ("supportsTerminateRequest", Json::from(true)),
+            ])),
+            "launch" => match load(&request.arguments) {
+                Ok(loaded) => {
+                    launch = Some(loaded);
+                    connection.respond(&request, Json::Null);
+                    // The editor sends the breakpoints once the server is ready for them.
+                    connection.event("initialized", Json::Null);
+                },
+                Err(message) => connection.fail(&request, &message),
+            },
+            "setBreakpoints" => {
+                let script = launch.as_ref().map(|launch| (launch.program.as_path(), launch.lines));
+                let body = set_breakpoints(&request.arguments, script, &mut breakpoints);
+                connection.respond(&request, body);
+            },
+            "setExceptionBreakpoints" => connection.respond(&request, Json::Null),
+            "configurationDone" => {
+                connection.respond(&request, Json::Null);
+                configured = true;
+                if launch.is_none() {
+                    connection.output("stderr", "There is no script to run, as the launch request did not succeed.\n");
+                }
+            },
+            _ => connection.handle_other(&request, "the script has not been launched yet"),
+        }
+        if connection.disconnected {
+            return;
+        }
+    }
+    let Some(launch) = launch else { return };
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_args(launch.args);
+    interpreter.set_output(Box::new(LineWriter::new(OutputEvents(Arc::clone(&connection)))));
+    interpreter.set_input(Box::new(io::empty()));
+    if !launch.no_debug {
+        let mut stepping = Stepping::new(if launch.stop_on_entry { Mode::Step } else { Mode::Continue });
+        stepping.breakpoints = breakpoints;
+        interpreter.set_hook(Box::new(Adapter {
+            connection: Arc::clone(&connection),
+            program: launch.program,
+            lines: launch.lines,
+            stepping,
+            entry: true,
+            references: Vec::new(),
+        }));
+    }
+
+    let result = interpreter.interpret(launch.ast);
+    // Dropping the interpreter flushes the output of the script, so that it is sent before the script is reported to have exited.
+    drop(interpreter);
+    let exit_code = match result {
+        // The script is also stopped when the editor disconnects or terminates it.
+        Ok(()) | Err(ErrorType::ThrownStop {..}) => 0,
+        Err(e) => {
+            let mut report = format!("An error has occurred.\n{}\n", e.message());
+            if let Some(hint) = e.hint() {
+                report.push_str(&format!("    Hint: {}\n", hint));
+            }
+            connection.lock().unwrap().output("stderr", &report);
+            1
+        },
+    };
+
+    let mut connection = connection.lock().unwrap();
+    if connection.disconnected {
+        return;
+    }
+    connection.event("exited", object(vec![("exitCode", Json::from(exit_code))]));
+    connection.event("terminated", Json::Null);
+    while !connection.disconnected {
+        let Some(request) = connection.read_request() else { return };
+        connection.handle_other(&request, "the script has finished");
+    }
+}
+
+/// Reads and parses the script given in the arguments of a `launch` request.
+fn load(arguments: &Json) -> Result<Launch, String> {
+    let program = arguments.get("program").and_then(Json::as_str).ok_or("`program` has to be the path of the script to debug")?;
+    let source = fs::read_to_string(program).map_err(|e| format!("Failed to read file `{}`: {}", program, e))?;
+
+    let (tokens, mut errors) = Tokenizer::new(&source).tokenize();
+    match Parser::new(tokens).parse() {
+        Ok(ast) if errors.is_empty() => Ok(Launch {
+            program: PathBuf::from(program),
+            lines: source.lines().count(),
+            ast,
+            args: arguments.get("args").and_then(Json::as_array).unwrap_or_default()
+                .iter()
+                .filter_map(|arg| arg.as_str().map(String::from))
+                .collect(),
+            stop_on_entry: arguments.get("stopOnEntry").and_then(Json::as_bool).unwrap_or(false),
+            no_debug: arguments.get("noDebug").and_then(Json::as_bool).unwrap_or(false),
+        }),
+        result => {
+            if let Err(parser_errors) = result {
+                errors.extend(parser_errors);
+            }
+            let messages: Vec<String> = errors.iter().map(ErrorType::message).collect();
+            Err(format!("`{}` has errors:\n{}", program, messages.join("\n")))
+        },
+    }
+}
+
+/// Replaces the breakpoints with the ones in the arguments of a `setBreakpoints` request, and returns the body of the response.
+/// `script` is the path of the script and its number of lines, if it has been launched. Breakpoints in other files,
+/// or on lines the script does not have, are not verified, i.e., they are never hit.
+fn set_breakpoints(arguments: &Json, script: Option<(&Path, usize)>, breakpoints: &mut BTreeSet<usize>) -> Json {
+    let path = arguments.get("source").and_then(|source| source.get("path")).and_then(Json::as_str);
+    let (in_script, lines) = match (path, script) {
+        (Some(path), Some((program, lines))) => (same_file(Path::new(path), program), lines),
+        _ => (false, 0),
+    };
+
+    if in_script {
+        breakpoints.clear();
+    }
+    let requested = arguments.get("breakpoints").and_then(Json::as_array).unwrap_or_default();
+    let verified: Vec<Json> = requested.iter()
+        .filter_map(|breakpoint| breakpoint.get("line").and_then(Json::as_usize))
+        .map(|line| {
+            let verified = in_script && line >= 1 && line <= lines;
+            if verified {
+                breakpoints.insert(line);
+            }
+            object(vec![("verified", Json::from(verified)), ("line", Json::from(line))])
+        })
+        .collect();
+    object(vec![("breakpoints", Json::Array(verified))])
+}
+
+/// Returns whether the paths are of the same file, e.g., an absolute and a relative path of it.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Returns the body of the response to a `threads` request.
+fn threads() -> Json {
+    object(vec![("threads", Json::Array(vec![object(vec![("id", Json::from(THREAD_ID)), ("name", Json::from("main"))])]))])
+}
+
+/// Returns a JSON object with the given members.
+fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(members.into_iter().map(|(name, value)| (name.to_string(), value)).collect())
+}
+
+/// What a variables reference refers to. References are given to the editor while the script is paused, so that it can
+/// request the variables in scopes and the elements of arrays and dictionaries, and they are forgotten when it resumes.
+enum Reference {
+    Scopes(Range<usize>),  // The indices of scopes in the environment, whose variables are shown together.
+    Value(Value),  // An array or dictionary.
+}
+
+/// Pauses the interpreter as the editor requests, and answers the editor's requests while it is paused.
+struct Adapter {
+    connection: Arc<Mutex<Connection>>,
+    program: PathBuf,
+    lines: usize,
+    stepping: Stepping,
+    entry: bool,  // Whether the next statement is the first one.
+    references: Vec<Reference>,  // The reference of each is its index plus 1, as 0 means there is no reference.
+}
+
+impl Adapter {
+    /// Answers requests until one resumes or stops the script.
+    fn pause(&mut self, interpreter: &mut Interpreter, reason: &str, line: usize) -> Result<(), ErrorType> {
+        self.references.clear();
+        self.connection.lock().unwrap().event("stopped", object(vec![
+            ("reason", Json::from(reason)),
+            ("threadId", Json::from(THREAD_ID)),
+            ("allThreadsStopped", Json::from(true)),
+        ]));
+
+        loop {
+            let Some(request) = self.connection.lock().unwrap().read_request() else {
+                // The editor has gone, so the script is stopped as there is no way to resume it.
+                return Err(ErrorType::ThrownStop { line });
+            };
+            let depth = interpreter.frames().len();
+            let mode = match request.command.as_str() {
+                "continue" => Some(Mode::Continue),
+                "next" => Some(Mode::Next(depth)),
+                "stepIn" => Some(Mode::Step),
+                // Stepping out of the top level runs the script to the end, or to the next breakpoint.
+                "stepOut" => Some(if depth > 1 { Mode::Finish(depth) } else { Mode::Continue }),
+                _ => None,
+            };
+            if let Some(mode) = mode {
+                self.stepping.mode = mode;
+                self.connection.lock().unwrap().respond(&request, object(vec![("allThreadsContinued", Json::from(true))]));
+                return Ok(());
+            }
+
+            // The connection is not borrowed while evaluating, as the script may print.
+            let response = match request.command.as_str() {
+                "stackTrace" => Ok(self.stack_trace(interpreter, &request.arguments)),
+                "scopes" => self.scopes(interpreter, &request.arguments),
+                "variables" => self.variables(interpreter, &request.arguments),
+                "evaluate" => self.evaluate(interpreter, &request.arguments),
+                "setBreakpoints" => {
+                    let script = Some((self.program.as_path(), self.lines));
+                    Ok(set_breakpoints(&request.arguments, script, &mut self.stepping.breakpoints))
+                },
+                // The script is already paused.
+                "pause" => Ok(Json::Null),
+                _ => {
+                    let mut connection = self.connection.lock().unwrap();
+                    connection.handle_other(&request, "this request is not supported");
+                    if connection.disconnected {
+                        return Err(ErrorType::ThrownStop { line });
+                    }
+                    continue;
+                },
+            };
+            let mut connection = self.connection.lock().unwrap();
+            match response {
+                Ok(body) => connection.respond(&request, body),
+                Err(message) => connection.fail(&request, &message),
+            }
+        }
+    }
+
+    /// Returns the body of the response to a `stackTrace` request, with the function call being executed first.
+    /// The ID of each frame is its index in the call stack plus 1, starting from the top level.
+    fn stack_trace(&self, interpreter: &Interpreter, arguments: &Json) -> Json {
+        let frames = interpreter.frames();
+        let start = arguments.get("startFrame").and_then(Json::as_usize).unwrap_or(0);
+        let levels = arguments.get("levels").and_then(Json::as_usize).filter(|levels| *levels > 0).unwrap_or(frames.len());
+        let name = self.program.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        let stack_frames = frames.iter()
+            .enumerate()
+            .rev()
+            .skip(start)
+            .take(levels)
+            .map(|(index, frame)| object(vec![
+                ("id", Json::from(index + 1)),
+                ("name", Json::from(frame.function.as_str())),
+                ("source", object(vec![
+                    ("name", Json::from(name.as_str())),
+                    ("path", Json::from(self.program.to_string_lossy().into_owned())),
+                ])),
+                ("line", Json::from(frame.line)),
+                ("column", Json::from(1)),
+            ]))
+            .collect();
+        object(vec![("stackFrames", Json::Array(stack_frames)), ("totalFrames", Json::from(frames.len()))])
+    }
+
+    /// Returns the body of the response to a `scopes` request: the scopes of the frame's own variables, and the global scope.
+    fn scopes(&mut self, interpreter: &Interpreter, arguments: &Json) -> Result<Json, String> {
+        let frames = interpreter.frames();
+        let index = arguments.get("frameId").and_then(Json::as_usize).and_then(|id| id.checked_sub(1))
+            .filter(|index| *index < frames.len())
+            .ok_or("there is no such frame")?;
+
+        // A frame's scopes go up to the first scope of the frame it called. The global scope is shown separately.
+        let start = frames[index].scope.max(1);
+        let end = frames.get(index + 1).map_or(interpreter.scope_count(), |frame| frame.scope);
+        let locals = self.reference(Reference::Scopes(start..end.max(start)));
+        let globals = self.reference(Reference::Scopes(0..1));
+        Ok(object(vec![("scopes", Json::Array(vec![
+            object(vec![("name", Json::from("Locals")), ("variablesReference", Json::from(locals)), ("expensive", Json::from(false))]),
+            object(vec![("name", Json::from("Globals")), ("variablesReference", Json::from(globals)), ("expensive", Json::from(false))]),
+        ]))]))
+    }
+
+    /// Returns the body of the response to a `variables` request: the variables in scopes, or the elements of an array or dictionary.
+    fn variables(&mut self, interpreter: &Interpreter, arguments: &Json) -> Result<Json, String> {
+        let reference = arguments.get("variablesReference").and_then(Json::as_usize)
+            .and_then(|reference| reference.checked_sub(1))
+            .and_then(|index| self.references.get(index))
+            .ok_or("there is no such variable")?;
+
+        let named: Vec<(String, Value)> = match reference {
+            Reference::Scopes(range) => {
+                // A name declared in more than one of the scopes shows the value in the innermost one, like when it is used.
+                let mut bindings = BTreeMap::new();
+                for index in range.clone() {
+                    bindings.extend(interpreter.scope_bindings(index));
+                }
+                bindings.into_iter().filter(|(_, value)| !matches!(value, Value::NativeFunction(..))).collect()
+            },
+            Reference::Value(Value::Array(array)) => array.iter().cloned().enumerate().map(|(i, value)| (i.to_string(), value)).collect(),
+            Reference::Value(Value::Dictionary(dict)) => dict.flatten().into_iter().map(|key_value| (key_value.key.repr(), key_value.value)).collect(),
+            Reference::Value(..) => Vec::new(),
+        };
+
+        let variables = named.into_iter()
+            .map(|(name, value)| {
+                let (repr, type_, reference) = self.describe(value);
+                object(vec![
+                    ("name", Json::from(name)),
+                    ("value", Json::from(repr)),
+                    ("type", Json::from(type_)),
+                    ("variablesReference", Json::from(reference)),
+                ])
+            })
+            .collect();
+        Ok(object(vec![("variables", Json::Array(variables))]))
+    }
+
+    /// Returns the body of the response to an `evaluate` request. As scoping is dynamic, the expression is always evaluated
+    /// in the current frame, where the variables of the frames which called it are visible too.
+    fn evaluate(&mut self, interpreter: &mut Interpreter, arguments: &Json) -> Result<Json, String> {
+        let text = arguments.get("expression").and_then(Json::as_str).unwrap_or_default();
+        let expression = debugger::parse_expression(text).ok_or_else(|| format!("`{}` is not an expression", text))?;
+        let value = interpreter.evaluate(&expression).map_err(|e| e.message())?;
+        let (repr, type_, reference) = self.describe(value);
+        Ok(object(vec![
+            ("result", Json::from(repr)),
+            ("type", Json::from(type_)),
+            ("variablesReference", Json::from(reference)),
+        ]))
+    }
+
+    /// Returns the representation, type and variables reference of a value in `variables` and `evaluate` responses.
+    /// Arrays and dictionaries are given a reference so that their elements can be expanded, and other values have none, i.e., 0.
+    fn describe(&mut self, value: Value) -> (String, String, usize) {
+        let repr = value.repr();
+        let type_ = value.type_to_string();
+        let expandable = match &value {
+            Value::Array(array) => !array.is_empty(),
+            Value::Dictionary(dict) => dict.size() > 0,
+            _ => false,
+        };
+        let reference = if expandable { self.reference(Reference::Value(value)) } else { 0 };
+        (repr, type_, reference)
+    }
+
+    /// Stores what a reference refers to, and returns the reference.
+    fn reference(&mut self, reference: Reference) -> usize {
+        self.references.push(reference);
+        self.references.len()
+    }
+}
+
+impl Hook for Adapter {
+    fn before_statement(&mut self, interpreter: &mut Interpreter, stmt: &Stmt) -> Result<(), ErrorType> {
+        let entry = std::mem::replace(&mut self.entry, false);
+        if !self.stepping.pause_before(interpreter.frames().len(), stmt.line) {
+            return Ok(());
+        }
+        let reason = if self.stepping.breakpoints.contains(&stmt.line) {
+            "breakpoint"
+        } else if entry {
+            "entry"
+        } else {
+            "step"
+        };
+        self.pause(interpreter, reason, stmt.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    use nea::stream::Buffer;
+
+    use crate::json::Json;
+
+    use super::{serve, Connection};
+
+    /// Runs a session where the editor sends the given requests, each a command and its arguments, and returns the messages sent back.
+    fn session(requests: &[(&str, &str)]) -> Vec<Json> {
+        let mut input = String::new();
+        for (seq, (command, arguments)) in requests.iter().enumerate() {
+            let body = format!(r#"{{"seq":{},"type":"request","command":"{}","arguments":{}}}"#, seq + 1, command, arguments);
+            input.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+        }
+        let output = Buffer::new();
+        serve(Arc::new(Mutex::new(Connection::new(Box::new(Cursor::new(input)), Box::new(output.clone())))));
+
+        let output = output.contents();
+        output.split("Content-Length: ")
+            .skip(1)
+            .map(|message| Json::parse(message.split_once("\r\n\r\n").unwrap().1).unwrap())
+            .collect()
+    }
+
+    /// Returns the command of a response or the event of an event, e.g., `response initialize` or `event stopped`.
+    fn summary(message: &Json) -> String {
+        let type_ = message.get("type").and_then(Json::as_str).unwrap();
+        let name = message.get("command").or(message.get("event")).and_then(Json::as_str).unwrap();
+        format!("{} {}", type_, name)
+    }
+
+    /// Returns the body of the response to the request with the given command.
+    fn body<'a>(messages: &'a [Json], command: &str) -> &'a Json {
+        messages.iter()
+            .find(|message| message.get("command").and_then(Json::as_str) == Some(command))
+            .and_then(|message| message.get("body"))
+            .unwrap()
+    }
+
+    #[test]
+    fn debug_session() {
+        let path = env::temp_dir().join(format!("nea-dap-test-{}.nea", std::process::id()));
+        fs::write(&path, "func square(n) {\n    var result = [n, n * n]\n    return result\n}\nprint(\"start\")\nprint(square(3))\n").unwrap();
+        let program = Json::from(path.to_string_lossy().into_owned()).to_string();
+
+        let messages = session(&[
+            ("initialize", r#"{"adapterID":"nea"}"#),
+            ("launch", &format!(r#"{{"program":{}}}"#, program)),
+            ("setBreakpoints", &format!(r#"{{"source":{{"path":{}}},"breakpoints":[{{"line":3}},{{"line":99}}]}}"#, program)),
+            ("configurationDone", "null"),
+            ("stackTrace", r#"{"threadId":1}"#),
+            ("scopes", r#"{"frameId":2}"#),
+            ("variables", r#"{"variablesReference":1}"#),
+            ("variables", r#"{"variablesReference":3}"#),
+            ("evaluate", r#"{"expression":"n + 1"}"#),
+            ("evaluate", r#"{"expression":"missing"}"#),
+            ("continue", r#"{"threadId":1}"#),
+            ("disconnect", "null"),
+        ]);
+        fs::remove_file(&path).unwrap();
+
+        let summaries: Vec<String> = messages.iter().map(summary).collect();
+        assert_eq!(summaries, vec![
+            "response initialize", "response launch", "event initialized", "response setBreakpoints", "response configurationDone",
+            "event output", "event stopped", "response stackTrace", "response scopes", "response variables", "response variables",
+            "response evaluate", "response evaluate", "response continue", "event output", "event exited", "event terminated", "response disconnect",
+        ]);
+
+        assert_eq!(body(&messages, "setBreakpoints").to_string(), r#"{"breakpoints":[{"verified":true,"line":3},{"verified":false,"line":99}]}"#);
+        assert_eq!(messages[5].get("body").unwrap().to_string(), r#"{"category":"stdout","output":"start\n"}"#);
+        assert_eq!(messages[6].get("body").unwrap().to_string(), r#"{"reason":"breakpoint","threadId":1,"allThreadsStopped":true}"#);
+
+        let frames = body(&messages, "stackTrace").get("stackFrames").and_then(Json::as_array).unwrap();
+        let frames: Vec<(Option<usize>, Option<&str>, Option<usize>)> = frames.iter()
+            .map(|frame| (frame.get("id").and_then(Json::as_usize), frame.get("name").and_then(Json::as_str), frame.get("line").and_then(Json::as_usize)))
+            .collect();
+        assert_eq!(frames, vec![(Some(2), Some("square"), Some(3)), (Some(1), Some("<script>"), Some(6))]);
+
+        assert_eq!(messages[9].get("body").unwrap().to_string(), concat!(
+            r#"{"variables":[{"name":"n","value":"3","type":"Number","variablesReference":0},"#,
+            r#"{"name":"result","value":"[3, 9]","type":"Array","variablesReference":3}]}"#,
+        ));
+        assert_eq!(messages[10].get("body").unwrap().to_string(), concat!(
+            r#"{"variables":[{"name":"0","value":"3","type":"Number","variablesReference":0},"#,
+            r#"{"name":"1","value":"9","type":"Number","variablesReference":0}]}"#,
+        ));
+        assert_eq!(messages[11].get("body").unwrap().to_string(), r#"{"result":"4","type":"Number","variablesReference":0}"#);
+        assert_eq!(messages[12].get("success"), Some(&Json::Bool(false)));
+        assert_eq!(messages[14].get("body").unwrap().to_string(), r#"{"category":"stdout","output":"[3, 9]\n"}"#);
+        assert_eq!(messages[15].get("body").unwrap().to_string(), r#"{"exitCode":0}"#);
+    }
+
+    #[test]
+    fn launch_errors() {
+        let messages = session(&[
+            ("initialize", "{}"),
+            ("launch", r#"{"program":"/nonexistent/script.nea"}"#),
+            ("configurationDone", "null"),
+            ("disconnect", "null"),
+        ]);
+        let summaries: Vec<String> = messages.iter().map(summary).collect();
+        assert_eq!(summaries, vec!["response initialize", "response launch", "response configurationDone", "event output", "response disconnect"]);
+        assert_eq!(messages[1].get("success"), Some(&Json::Bool(false)));
+    }
+}
@@ -0,0 +1,370 @@
+// The interactive debugger used by `nea debug`, which pauses the interpreter before statements so that the script can be inspected.
+
+use std::collections::BTreeSet;
+use std::io::Write;
+
+use crate::error::ErrorType;
+use crate::expr::Expr;
+use crate::interpreter::{Hook, Interpreter, SCRIPT_FRAME};
+use crate::parser::Parser;
+use crate::stmt::{Stmt, StmtType};
+use crate::tokenizer::Tokenizer;
+use crate::value::Value;
+
+/// The prompt printed when the debugger waits for a command.
+const PROMPT: &str = "(debug) ";
+
+/// The number of lines shown before and after the current line by `list`.
+const LIST_CONTEXT: usize = 3;
+
+/// The help message printed by `help`.
+const HELP: &str = "\
+Commands:
+  break <line>    (b) pause whenever the line is reached; without a line, list the breakpoints
+  delete <line>   (d) remove the breakpoint on the line
+  step            (s) run to the next statement, stepping into function calls
+  next            (n) run to the next statement in the current function, stepping over function calls
+  finish          (f) run until the current function returns
+  continue        (c) run until a breakpoint is reached
+  print <expr>    (p) evaluate an expression in the current frame, e.g., `print x[0] + 1`
+  vars            (v) list the variables in every scope, innermost first
+  backtrace       (bt) show the call stack, innermost call first
+  list            (l) show the source code around the current line
+  help            (h) print this help message
+  quit            (q) stop the script
+An empty line repeats the previous command.
+";
+
+/// When to pause next, apart from at breakpoints.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Step,  // Before the next statement.
+    Next(usize),  // Before the next statement with at most this many frames on the call stack.
+    Finish(usize),  // Before the next statement with fewer than this many frames on the call stack.
+    Continue,  // Only at breakpoints.
+}
+
+/// Decides when to pause the interpreter: at breakpoints, and as chosen by the last command which resumed it.
+/// This is shared by the debugger and the DAP server (see `dap.rs`).
+pub struct Stepping {
+    pub breakpoints: BTreeSet<usize>,  // The lines to pause at.
+    pub mode: Mode,
+    last_location: (usize, usize),  // The number of frames and the line of the last statement, so that a line with many statements only hits its breakpoint once.
+}
+
+impl Stepping {
+    /// Initialises a new instance of `Stepping` with no breakpoints, which pauses in the given mode.
+    pub fn new(mode: Mode) -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            mode,
+            last_location: (0, 0),
+        }
+    }
+
+    /// Records that a statement on `line` with `depth` frames on the call stack is about to be executed, and returns whether to pause before it.
+    pub fn pause_before(&mut self, depth: usize, line: usize) -> bool {
+        let at_mode = match self.mode {
+            Mode::Step => true,
+            Mode::Next(max_depth) => depth <= max_depth,
+            Mode::Finish(max_depth) => depth < max_depth,
+            Mode::Continue => false,
+        };
+        let at_breakpoint = self.breakpoints.contains(&line) && self.last_location != (depth, line);
+        self.last_location = (depth, line);
+        at_mode || at_breakpoint
+    }
+}
+
+/// Pauses the interpreter before statements and reads commands to inspect and control it.
+/// Commands are read line by line from `input`, and everything is written to `output`.
+pub struct Debugger {
+    lines: Vec<String>,  // The lines of the source code of the script.
+    input: Box<dyn Iterator<Item = String> + Send>,
+    output: Box<dyn Write + Send>,
+    stepping: Stepping,
+    last_command: String,  // Repeated when an empty line is entered.
+}
+
+impl Debugger {
+    /// Initialises a new instance of `Debugger` for the given source code. It pauses before the first statement.
+    pub fn new(source: &str, input: Box<dyn Iterator<Item = String> + Send>, output: Box<dyn Write + Send>) -> Self {
+        Self {
+            lines: source.lines().map(String::from).collect(),
+            input,
+            output,
+            stepping: Stepping::new(Mode::Step),
+            last_command: String::new(),
+        }
+    }
+
+    /// Reads and runs commands until one resumes or stops the script.
+    fn pause(&mut self, interpreter: &mut Interpreter, line: usize) -> Result<(), ErrorType> {
+        if self.stepping.breakpoints.contains(&line) {
+            let _ = writeln!(self.output, "Breakpoint at line {}.", line);
+        }
+        self.write_line(line, line);
+
+        loop {
+            let _ = write!(self.output, "{}", PROMPT);
+            let _ = self.output.flush();
+            let command = match self.input.next() {
+                Some(command) => command.trim().to_string(),
+                // There are no more commands, e.g., stdin was closed, so the script is stopped as there is no way to resume it.
+                None => return Err(ErrorType::ThrownStop { line }),
+            };
+            let command = if command.is_empty() {
+                self.last_command.clone()
+            } else {
+                self.last_command = command.clone();
+                command
+            };
+
+            let (name, argument) = match command.split_once(char::is_whitespace) {
+                Some((name, argument)) => (name, argument.trim()),
+                None => (command.as_str(), ""),
+            };
+            let depth = interpreter.frames().len();
+            match name {
+                "s" | "step" => self.stepping.mode = Mode::Step,
+                "n" | "next" => self.stepping.mode = Mode::Next(depth),
+                "f" | "finish" => {
+                    if depth == 1 {
+                        let _ = writeln!(self.output, "`finish` has to be used within a function.");
+                        continue;
+                    }
+                    self.stepping.mode = Mode::Finish(depth);
+                },
+                "c" | "continue" => self.stepping.mode = Mode::Continue,
+                "q" | "quit" => return Err(ErrorType::ThrownStop { line }),
+                "b" | "break" => self.set_breakpoint(argument),
+                "d" | "delete" => self.delete_breakpoint(argument),
+                "p" | "print" => self.print(interpreter, argument),
+                "v" | "vars" => self.vars(interpreter),
+                "bt" | "backtrace" => self.backtrace(interpreter),
+                "l" | "list" => {
+                    let first = line.saturating_sub(LIST_CONTEXT).max(1);
+                    for number in first..=(line + LIST_CONTEXT).min(self.lines.len()) {
+                        self.write_line(number, line);
+                    }
+                },
+                "h" | "help" => {
+                    let _ = write!(self.output, "{}", HELP);
+                },
+                "" => (),
+                _ => {
+                    let _ = writeln!(self.output, "Unknown command `{}`. Enter `help` for a list of commands.", name);
+                },
+            }
+
+            if matches!(name, "s" | "step" | "n" | "next" | "f" | "finish" | "c" | "continue") {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Writes a line of the source code with its number, marking it with `->` if it is the current line.
+    fn write_line(&mut self, number: usize, current: usize) {
+        let marker = if number == current { "->" } else { "  " };
+        let text = self.lines.get(number.wrapping_sub(1)).map_or("", String::as_str);
+        let _ = writeln!(self.output, "{} {:>4} | {}", marker, number, text);
+    }
+
+    /// Sets a breakpoint on the line given by `argument`, or lists the breakpoints if there is no argument.
+    fn set_breakpoint(&mut self, argument: &str) {
+        if argument.is_empty() {
+            if self.stepping.breakpoints.is_empty() {
+                let _ = writeln!(self.output, "There are no breakpoints.");
+            }
+            for line in &self.stepping.breakpoints {
+                let _ = writeln!(self.output, "Breakpoint at line {}.", line);
+            }
+            return;
+        }
+
+        match argument.parse::<usize>() {
+            Ok(line) if line >= 1 && line <= self.lines.len() => {
+                self.stepping.breakpoints.insert(line);
+                let _ = writeln!(self.output, "Set a breakpoint at line {}.", line);
+            },
+            _ => {
+                let _ = writeln!(self.output, "`{}` is not a line of the script, which has {} lines.", argument, self.lines.len());
+            },
+        }
+    }
+
+    /// Removes the breakpoint on the line given by `argument`.
+    fn delete_breakpoint(&mut self, argument: &str) {
+        match argument.parse::<usize>() {
+            Ok(line) if self.stepping.breakpoints.remove(&line) => {
+                let _ = writeln!(self.output, "Deleted the breakpoint at line {}.", line);
+            },
+            _ => {
+                let _ = writeln!(self.output, "There is no breakpoint at line `{}`.", argument);
+            },
+        }
+    }
+
+    /// Evaluates the expression in `argument` and prints its value. As scoping is dynamic, it can use any variable in the current frame
+    /// or the frames which called it.
+    fn print(&mut self, interpreter: &mut Interpreter, argument: &str) {
+        match parse_expression(argument).map(|expression| interpreter.evaluate(&expression)) {
+            Some(Ok(value)) => {
+                let _ = writeln!(self.output, "{}", value.repr());
+            },
+            Some(Err(e)) => {
+                let _ = writeln!(self.output, "{}", e.message());
+            },
+            None => {
+                let _ = writeln!(self.output, "`print` expects an expression, e.g., `print x`.");
+            },
+        }
+    }
+
+    /// Lists the variables in every scope, starting with the innermost. The built-in functions are left out.
+    fn vars(&mut self, interpreter: &Interpreter) {
+        let frames = interpreter.frames();
+        for index in (0..interpreter.scope_count()).rev() {
+            let bindings: Vec<(String, Value)> = interpreter.scope_bindings(index)
+                .into_iter()
+                .filter(|(_, value)| !matches!(value, Value::NativeFunction(..)))
+                .collect();
+            if index == 0 {
+                let _ = writeln!(self.output, "Globals:");
+            } else {
+                // The scope belongs to the innermost frame which started at or before it.
+                let function = frames.iter().rev().find(|frame| frame.scope <= index).map_or(SCRIPT_FRAME, |frame| frame.function.as_str());
+                let _ = writeln!(self.output, "Scope {} (in `{}`):", index, function);
+            }
+            for (name, value) in bindings {
+                let _ = writeln!(self.output, "  {} = {}", name, value.repr());
+            }
+        }
+    }
+
+    /// Prints the call stack, starting with the function call being executed.
+    fn backtrace(&mut self, interpreter: &Interpreter) {
+        for (number, frame) in interpreter.frames().iter().rev().enumerate() {
+            let _ = writeln!(self.output, "#{} `{}` at line {}", number, frame.function, frame.line);
+        }
+    }
+}
+
+/// Parses `text` as a single expression, e.g., one entered to be evaluated, or returns `None` if it is not one.
+pub fn parse_expression(text: &str) -> Option<Expr> {
+    let (tokens, errors) = Tokenizer::new(text).tokenize();
+    match (errors.is_empty(), Parser::new(tokens).parse()) {
+        (true, Ok(mut ast)) if ast.len() == 1 => match ast.remove(0).stmt_type {
+            StmtType::Expression { expression } => Some(expression),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl Hook for Debugger {
+    fn before_statement(&mut self, interpreter: &mut Interpreter, stmt: &Stmt) -> Result<(), ErrorType> {
+        if self.stepping.pause_before(interpreter.frames().len(), stmt.line) {
+            self.pause(interpreter, stmt.line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nea::stream::Buffer;
+
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::tokenizer::Tokenizer;
+
+    use super::Debugger;
+
+    /// Debugs the source code, entering each of the commands in turn, and returns what the debugger wrote.
+    fn debug(source: &str, commands: &[&str]) -> String {
+        let output = Buffer::new();
+        let commands: Vec<String> = commands.iter().map(|command| command.to_string()).collect();
+        let debugger = Debugger::new(source, Box::new(commands.into_iter()), Box::new(output.clone()));
+
+        let (tokens, _) = Tokenizer::new(source).tokenize();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_hook(Box::new(debugger));
+        let _ = interpreter.interpret(ast);
+
+        output.contents()
+    }
+
+    const SOURCE: &str = "\
+func square(n) {
+    var result = n * n
+    return result
+}
+var x = 3
+var y = square(x)
+var z = y + 1";
+
+    #[test]
+    fn stepping() {
+        assert_eq!(debug(SOURCE, &["next", "next", "step", "", "finish", "continue"]), "\
+->    1 | func square(n) {
+(debug) ->    5 | var x = 3
+(debug) ->    6 | var y = square(x)
+(debug) ->    2 |     var result = n * n
+(debug) ->    3 |     return result
+(debug) ->    7 | var z = y + 1
+(debug) ");
+    }
+
+    #[test]
+    fn breakpoints() {
+        let output = debug(SOURCE, &["break 3", "b 9", "continue", "backtrace", "delete 3", "break", "c"]);
+        assert_eq!(output, "\
+->    1 | func square(n) {
+(debug) Set a breakpoint at line 3.
+(debug) `9` is not a line of the script, which has 7 lines.
+(debug) Breakpoint at line 3.
+->    3 |     return result
+(debug) #0 `square` at line 3
+#1 `<script>` at line 6
+(debug) Deleted the breakpoint at line 3.
+(debug) There are no breakpoints.
+(debug) ");
+    }
+
+    #[test]
+    fn inspection() {
+        let output = debug(SOURCE, &["b 3", "c", "vars", "print result + x", "p [n, \"a\"]", "p missing", "p var", "quit", "next"]);
+        assert_eq!(output, "\
+->    1 | func square(n) {
+(debug) Set a breakpoint at line 3.
+(debug) Breakpoint at line 3.
+->    3 |     return result
+(debug) Scope 2 (in `square`):
+  result = 9
+Scope 1 (in `square`):
+  n = 3
+Globals:
+  square = <function>
+  x = 3
+(debug) 12
+(debug) [3, \"a\"]
+(debug) Line 1: `missing` is not defined.
+(debug) `print` expects an expression, e.g., `print x`.
+(debug) ");
+    }
+
+    #[test]
+    fn list() {
+        let output = debug(SOURCE, &["b 6", "c", "list", "finish", "q"]);
+        assert!(output.contains("\
+(debug)       3 |     return result
+      4 | }
+      5 | var x = 3
+->    6 | var y = square(x)
+      7 | var z = y + 1
+(debug) `finish` has to be used within a function.
+"));
+    }
+}
@@ -0,0 +1,234 @@
+// The documentation generator used by `nea doc`, which lists the functions and variables declared at the top level of scripts
+// with the text of their `##` comments, followed by the built-in functions, as a Markdown or HTML reference.
+
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+use crate::builtins::BUILTINS;
+use crate::cli::{self, ErrorFormat, Options};
+use crate::stmt::{Stmt, StmtType};
+
+/// The format of the generated reference.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+/// A documented declaration.
+#[derive(Clone, Debug, PartialEq)]
+struct Item {
+    signature: String,  // How the declaration is used, e.g., `add(a, b)` for a function or `var total` for a variable.
+    doc: Option<String>,
+}
+
+/// The declarations of a script.
+#[derive(Clone, Debug, PartialEq)]
+struct Script {
+    path: String,
+    items: Vec<Item>,
+}
+
+/// Generates the reference for each path, which is either a script or a directory searched recursively for scripts,
+/// and prints it. Fails without printing it if any script could not be read or has syntax errors.
+pub fn run_doc(paths: &[String], format: DocFormat, options: &Options) -> ExitCode {
+    let mut files = Vec::new();
+    for path in paths {
+        if let Err(e) = cli::find_scripts(Path::new(path), &mut files) {
+            eprintln!("Failed to read `{}`: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut scripts = Vec::new();
+    let mut success = true;
+    for file in &files {
+        match read_script(file, options) {
+            Some(script) => scripts.push(script),
+            None => success = false,
+        }
+    }
+    if !success {
+        return ExitCode::FAILURE;
+    }
+
+    let reference = match format {
+        DocFormat::Markdown => markdown(&scripts),
+        DocFormat::Html => html(&scripts),
+    };
+    print!("{}", reference);
+    ExitCode::SUCCESS
+}
+
+/// Reads and parses a script, returning its declarations, or reporting why it could not be.
+fn read_script(file: &Path, options: &Options) -> Option<Script> {
+    let name = file.display().to_string();
+    let source = match fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Failed to read file `{}`: {}", name, e);
+            return None;
+        },
+    };
+    match cli::parse_script(&source) {
+        Ok(ast) => Some(Script { items: items(&ast), path: name }),
+        Err(errors) => {
+            if options.error_format == ErrorFormat::Human {
+                // As with `nea check`, several files may be read, so say which one the errors are in.
+                println!("In `{}`:", name);
+            }
+            cli::report_errors(&errors, &name, options);
+            None
+        },
+    }
+}
+
+/// Returns the declarations at the top level of a script, in the order they are declared.
+/// Every function is listed, but only variables with documentation, as most are not meant to be used by other scripts.
+fn items(ast: &[Stmt]) -> Vec<Item> {
+    ast.iter()
+        .filter_map(|stmt| match &stmt.stmt_type {
+            StmtType::Function { name, parameters, doc, .. } => Some(Item {
+                signature: format!("{}({})", name, parameters.join(", ")),
+                doc: doc.as_deref().map(String::from),
+            }),
+            StmtType::VarDecl { name, doc: Some(doc), .. } => Some(Item {
+                signature: format!("var {}", name),
+                doc: Some(doc.to_string()),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the built-in functions as items, in alphabetical order.
+fn builtin_items() -> Vec<Item> {
+    BUILTINS.iter()
+        .map(|builtin| Item { signature: builtin.signature.to_string(), doc: Some(builtin.description.to_string()) })
+        .collect()
+}
+
+/// Returns the reference in Markdown. The documentation itself is included as it is, so it can use Markdown.
+fn markdown(scripts: &[Script]) -> String {
+    let mut output = String::from("# Reference\n");
+    let sections = scripts.iter()
+        .map(|script| (script.path.clone(), script.items.clone()))
+        .chain([(String::from("Built-in functions"), builtin_items())]);
+    for (heading, items) in sections {
+        output.push_str(&format!("\n## {}\n", heading));
+        if items.is_empty() {
+            output.push_str("\nNothing is declared.\n");
+        }
+        for item in items {
+            output.push_str(&format!("\n### `{}`\n", item.signature));
+            if let Some(doc) = item.doc {
+                output.push_str(&format!("\n{}\n", doc));
+            }
+        }
+    }
+    output
+}
+
+/// Returns the reference as an HTML page. Each paragraph of the documentation, separated by blank lines, is a `<p>` element.
+fn html(scripts: &[Script]) -> String {
+    let mut output = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Reference</title>\n</head>\n<body>\n<h1>Reference</h1>\n");
+    let sections = scripts.iter()
+        .map(|script| (script.path.clone(), script.items.clone()))
+        .chain([(String::from("Built-in functions"), builtin_items())]);
+    for (heading, items) in sections {
+        output.push_str(&format!("<h2>{}</h2>\n", escape(&heading)));
+        if items.is_empty() {
+            output.push_str("<p>Nothing is declared.</p>\n");
+        }
+        for item in items {
+            output.push_str(&format!("<h3><code>{}</code></h3>\n", escape(&item.signature)));
+            let doc = item.doc.unwrap_or_default();
+            for paragraph in doc.split("\n\n").map(str::trim).filter(|paragraph| !paragraph.is_empty()) {
+                output.push_str(&format!("<p>{}</p>\n", escape(paragraph)));
+            }
+        }
+    }
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+/// Escapes the characters which have a special meaning in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli;
+
+    use super::{html, items, markdown, Item, Script};
+
+    const SOURCE: &str = "\
+## Adds `a` and `b`.
+##
+## Both must be numbers.
+func add(a, b) {
+    ## Not at the top level.
+    func inner() {}
+    return a + b
+}
+func undocumented() {}
+## The <initial> total.
+var total = 0
+var hidden = 1
+";
+
+    fn script() -> Script {
+        Script { path: String::from("lib.nea"), items: items(&cli::parse_script(SOURCE).unwrap()) }
+    }
+
+    #[test]
+    fn declarations() {
+        assert_eq!(script().items, vec![
+            Item { signature: String::from("add(a, b)"), doc: Some(String::from("Adds `a` and `b`.\n\nBoth must be numbers.")) },
+            Item { signature: String::from("undocumented()"), doc: None },
+            Item { signature: String::from("var total"), doc: Some(String::from("The <initial> total.")) },
+        ]);
+    }
+
+    #[test]
+    fn formats() {
+        let markdown = markdown(&[script()]);
+        assert!(markdown.starts_with("\
+# Reference
+
+## lib.nea
+
+### `add(a, b)`
+
+Adds `a` and `b`.
+
+Both must be numbers.
+
+### `undocumented()`
+
+### `var total`
+
+The <initial> total.
+
+## Built-in functions
+
+### `append(array, value)`
+
+Appends "));
+
+        let html = html(&[script()]);
+        assert!(html.contains("\
+<h2>lib.nea</h2>
+<h3><code>add(a, b)</code></h3>
+<p>Adds `a` and `b`.</p>
+<p>Both must be numbers.</p>
+<h3><code>undocumented()</code></h3>
+<h3><code>var total</code></h3>
+<p>The &lt;initial&gt; total.</p>
+<h2>Built-in functions</h2>
+"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+}
@@ -0,0 +1,313 @@
+// Dumps of the tokens and abstract syntax trees produced by the tokenizer and parser, used by `nea tokens`, `nea ast` and the REPL.
+// Both are converted to JSON first, which is either printed as it is, for external tools, or rendered as an indented tree, for people.
+
+use crate::expr::{Expr, ExprType};
+use crate::json::Json;
+use crate::stmt::{Doc, Stmt, StmtType};
+use crate::token::{Literal, Token};
+
+/// How to print a dump.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DumpFormat {
+    Tree,
+    Json,
+}
+
+/// Returns the tokens in the given format.
+pub fn dump_tokens(tokens: &[Token], format: DumpFormat) -> String {
+    match format {
+        DumpFormat::Tree => {
+            // Tokens are not nested, so they are listed in a table rather than a tree.
+            tokens.iter()
+                .map(|token| format!("{:<5} {:<14} {}\n", token.line, format!("{:?}", token.type_), Json::from(token.lexeme.as_str())))
+                .collect()
+        },
+        DumpFormat::Json => Json::Array(tokens.iter().map(token_to_json).collect()).pretty() + "\n",
+    }
+}
+
+/// Returns the abstract syntax tree in the given format.
+pub fn dump_ast(ast: &[Stmt], format: DumpFormat) -> String {
+    let statements: Vec<Json> = ast.iter().map(stmt_to_json).collect();
+    match format {
+        DumpFormat::Tree => {
+            let mut output = String::new();
+            for statement in &statements {
+                write_node(&mut output, statement, 0);
+            }
+            output
+        },
+        DumpFormat::Json => Json::Array(statements).pretty() + "\n",
+    }
+}
+
+/// Converts a token to JSON. The value is only included for literals.
+fn token_to_json(token: &Token) -> Json {
+    let mut members = vec![
+        (String::from("type"), Json::from(format!("{:?}", token.type_))),
+        (String::from("lexeme"), Json::from(token.lexeme.as_str())),
+        (String::from("line"), Json::from(token.line)),
+        (String::from("column"), Json::from(token.column)),
+    ];
+    if token.literal != Literal::Null {
+        members.push((String::from("value"), literal_to_json(&token.literal)));
+    }
+    Json::Object(members)
+}
+
+/// Converts a literal value to the equivalent JSON value.
+fn literal_to_json(literal: &Literal) -> Json {
+    match literal {
+        Literal::Number(x) => Json::Number(*x),
+        Literal::String_(s) => Json::from(s.as_str()),
+        Literal::Bool(b) => Json::from(*b),
+        Literal::Null => Json::Null,
+    }
+}
+
+/// Returns a JSON object for a node of the syntax tree, with its type and line followed by `fields`.
+fn node(type_: &str, line: usize, fields: Vec<(&str, Json)>) -> Json {
+    let mut members = vec![
+        (String::from("type"), Json::from(type_)),
+        (String::from("line"), Json::from(line)),
+    ];
+    members.extend(fields.into_iter().map(|(name, value)| (name.to_string(), value)));
+    Json::Object(members)
+}
+
+/// Adds the documentation of a declaration to its fields, if it has any, so that undocumented declarations are not cluttered.
+fn with_doc<'a>(doc: &Doc, mut fields: Vec<(&'a str, Json)>) -> Vec<(&'a str, Json)> {
+    if let Some(doc) = doc {
+        fields.push(("doc", Json::from(&**doc)));
+    }
+    fields
+}
+
+/// Converts a statement, including the statements and expressions within it, to JSON.
+fn stmt_to_json(stmt: &Stmt) -> Json {
+    match &stmt.stmt_type {
+        StmtType::Assert { condition, message, .. } => node("Assert", stmt.line, vec![
+            ("condition", expr_to_json(condition)),
+            ("message", message.as_ref().map_or(Json::Null, |message| expr_to_json(message))),
+        ]),
+        StmtType::Block { body } => node("Block", stmt.line, vec![
+            ("body", Json::Array(body.iter().map(stmt_to_json).collect())),
+        ]),
+        StmtType::Break => node("Break", stmt.line, vec![]),
+        StmtType::Expression { expression } => node("Expression", stmt.line, vec![
+            ("expression", expr_to_json(expression)),
+        ]),
+        StmtType::Function { name, parameters, body, doc } => node("Function", stmt.line, with_doc(doc, vec![
+            ("name", Json::from(name.as_str())),
+            ("parameters", Json::Array(parameters.iter().map(|parameter| Json::from(parameter.as_str())).collect())),
+            ("body", stmt_to_json(body)),
+        ])),
+        StmtType::If { condition, then_body, else_body, .. } => node("If", stmt.line, vec![
+            ("condition", expr_to_json(condition)),
+            ("then_body", stmt_to_json(then_body)),
+            ("else_body", else_body.as_ref().map_or(Json::Null, |else_body| stmt_to_json(else_body))),
+        ]),
+        StmtType::Print { expression } => node("Print", stmt.line, vec![
+            ("expression", expr_to_json(expression)),
+        ]),
+        StmtType::Return { expression } => node("Return", stmt.line, vec![
+            ("expression", expr_to_json(expression)),
+        ]),
+        StmtType::VarDecl { name, value, doc } => node("VarDecl", stmt.line, with_doc(doc, vec![
+            ("name", Json::from(name.as_str())),
+            ("value", expr_to_json(value)),
+        ])),
+        StmtType::While { condition, body, .. } => node("While", stmt.line, vec![
+            ("condition", expr_to_json(condition)),
+            ("body", stmt_to_json(body)),
+        ]),
+    }
+}
+
+/// Converts an expression, including the expressions within it, to JSON.
+fn expr_to_json(expr: &Expr) -> Json {
+    match &expr.expr_type {
+        ExprType::Array { elements } => node("Array", expr.line, vec![
+            ("elements", Json::Array(elements.iter().map(expr_to_json).collect())),
+        ]),
+        ExprType::Assignment { target, value } => node("Assignment", expr.line, vec![
+            ("target", expr_to_json(target)),
+            ("value", expr_to_json(value)),
+        ]),
+        ExprType::Binary { left, operator, right } => node("Binary", expr.line, vec![
+            ("operator", Json::from(operator.lexeme.as_str())),
+            ("left", expr_to_json(left)),
+            ("right", expr_to_json(right)),
+        ]),
+        ExprType::Call { callee, arguments } => node("Call", expr.line, vec![
+            ("callee", expr_to_json(callee)),
+            ("arguments", Json::Array(arguments.iter().map(expr_to_json).collect())),
+        ]),
+        ExprType::Dictionary { elements } => node("Dictionary", expr.line, vec![
+            ("elements", Json::Array(elements.iter().map(|key_value| Json::Object(vec![
+                (String::from("key"), expr_to_json(&key_value.key)),
+                (String::from("value"), expr_to_json(&key_value.value)),
+            ])).collect())),
+        ]),
+        ExprType::Element { array, index } => node("Element", expr.line, vec![
+            ("array", expr_to_json(array)),
+            ("index", expr_to_json(index)),
+        ]),
+        ExprType::Grouping { expression } => node("Grouping", expr.line, vec![
+            ("expression", expr_to_json(expression)),
+        ]),
+        ExprType::Literal { value } => node("Literal", expr.line, vec![
+            ("value", literal_to_json(value)),
+        ]),
+        ExprType::Unary { operator, right } => node("Unary", expr.line, vec![
+            ("operator", Json::from(operator.lexeme.as_str())),
+            ("right", expr_to_json(right)),
+        ]),
+        ExprType::Variable { name } => node("Variable", expr.line, vec![
+            ("name", Json::from(name.as_str())),
+        ]),
+    }
+}
+
+/// Returns the heading of a node of the tree, e.g., `Literal (line 1)`, or the value itself if it is not a node, e.g., `5`.
+/// Objects without a type, i.e., the key-value pairs of dictionaries, have no heading.
+fn heading(json: &Json) -> String {
+    match json {
+        Json::Object(members) => {
+            let type_ = members.iter().find(|(key, _)| key == "type").map(|(_, type_)| type_);
+            let line = members.iter().find(|(key, _)| key == "line").map(|(_, line)| line);
+            match (type_, line) {
+                (Some(Json::String(type_)), Some(line)) => format!("{} (line {})", type_, line),
+                (Some(Json::String(type_)), None) => type_.clone(),
+                _ => String::new(),
+            }
+        },
+        _ => json.to_string(),
+    }
+}
+
+/// Writes a node of the tree: its heading on the current line, followed by its fields on the following lines.
+/// The fields are indented one level more than `depth`, the indentation level of the node.
+fn write_node(output: &mut String, json: &Json, depth: usize) {
+    output.push_str(&heading(json));
+    output.push('\n');
+
+    if let Json::Object(members) = json {
+        for (key, value) in members.iter().filter(|(key, _)| key != "type" && key != "line") {
+            output.push_str(&format!("{}{}:", "  ".repeat(depth + 1), key));
+            match value {
+                Json::Array(elements) if !elements.is_empty() => {
+                    // Each element is a node of its own, listed on a line starting with `-`.
+                    output.push('\n');
+                    for element in elements {
+                        output.push_str(&format!("{}-", "  ".repeat(depth + 2)));
+                        write_child(output, element, depth + 2);
+                    }
+                },
+                _ => write_child(output, value, depth + 1),
+            }
+        }
+    }
+}
+
+/// Writes a node after the name of a field or a `-`, separated by a space unless it has no heading.
+fn write_child(output: &mut String, json: &Json, depth: usize) {
+    if !heading(json).is_empty() {
+        output.push(' ');
+    }
+    write_node(output, json, depth);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::Parser;
+    use crate::tokenizer::Tokenizer;
+
+    use super::{dump_ast, dump_tokens, DumpFormat};
+
+    #[test]
+    fn tokens() {
+        let (tokens, _) = Tokenizer::new("var a = \"x\"").tokenize();
+        assert_eq!(dump_tokens(&tokens, DumpFormat::Tree), "\
+1     Var            \"var\"
+1     Identifier     \"a\"
+1     Equal          \"=\"
+1     String_        \"\\\"x\\\"\"
+1     Eof            \"\"
+");
+        let json = dump_tokens(&tokens[3..4], DumpFormat::Json);
+        assert_eq!(json, "[\n  {\n    \"type\": \"String_\",\n    \"lexeme\": \"\\\"x\\\"\",\n    \"line\": 1,\n    \"column\": 9,\n    \"value\": \"x\"\n  }\n]\n");
+    }
+
+    #[test]
+    fn ast_tree() {
+        let (tokens, _) = Tokenizer::new("func f(a) {\n    print(a[0] + -1)\n}\n## A dictionary.\nvar d = {1: f()}").tokenize();
+        let ast = Parser::new(tokens).parse().unwrap();
+        assert_eq!(dump_ast(&ast, DumpFormat::Tree), "\
+Function (line 1)
+  name: \"f\"
+  parameters:
+    - \"a\"
+  body: Block (line 3)
+    body:
+      - Print (line 2)
+        expression: Grouping (line 2)
+          expression: Binary (line 2)
+            operator: \"+\"
+            left: Element (line 2)
+              array: Variable (line 2)
+                name: \"a\"
+              index: Literal (line 2)
+                value: 0
+            right: Unary (line 2)
+              operator: \"-\"
+              right: Literal (line 2)
+                value: 1
+VarDecl (line 5)
+  name: \"d\"
+  value: Dictionary (line 5)
+    elements:
+      -
+        key: Literal (line 5)
+          value: 1
+        value: Call (line 5)
+          callee: Variable (line 5)
+            name: \"f\"
+          arguments: []
+  doc: \"A dictionary.\"
+");
+    }
+
+    #[test]
+    fn ast_json() {
+        let (tokens, _) = Tokenizer::new("if (x) {} else { break }").tokenize();
+        let ast = Parser::new(tokens).parse().unwrap();
+        assert_eq!(dump_ast(&ast, DumpFormat::Json), r#"[
+  {
+    "type": "If",
+    "line": 1,
+    "condition": {
+      "type": "Variable",
+      "line": 1,
+      "name": "x"
+    },
+    "then_body": {
+      "type": "Block",
+      "line": 1,
+      "body": []
+    },
+    "else_body": {
+      "type": "Block",
+      "line": 1,
+      "body": [
+        {
+          "type": "Break",
+          "line": 1
+        }
+      ]
+    }
+  }
+]
+"#);
+    }
+}
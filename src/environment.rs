@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 
-use crate::value::{Value, BuiltinFunction};
+use crate::builtins::BUILTINS;
+use crate::value::Value;
 use crate::error::ErrorType;
+use crate::suggestion;
+use crate::token;
 
 /// Allows the updating of elements in multi-dimensional arrays and dictionaries.
 #[derive(Debug)]
@@ -15,20 +18,21 @@ pub struct Environment {
     scopes: Vec<HashMap<String, Value>>,  // The 'linked list' of variable scopes. Each scope contains a hash map of name-value pairs.
 }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
     /// Initialises a new instance of `Environment`.
     pub fn new() -> Self {
         Self {
             // Initialises the built-in functions in the base scope.
-            scopes: vec![HashMap::from([
-                (String::from("append"), Value::BuiltinFunction(BuiltinFunction::Append)),
-                (String::from("input"), Value::BuiltinFunction(BuiltinFunction::Input)),
-                (String::from("remove"), Value::BuiltinFunction(BuiltinFunction::Remove)),
-                (String::from("size"), Value::BuiltinFunction(BuiltinFunction::Size)),
-                (String::from("sort"), Value::BuiltinFunction(BuiltinFunction::Sort)),
-                (String::from("to_number"), Value::BuiltinFunction(BuiltinFunction::ToNumber)),
-                (String::from("to_string"), Value::BuiltinFunction(BuiltinFunction::ToString)),
-            ])],
+            scopes: vec![BUILTINS
+                .iter()
+                .map(|builtin| (String::from(builtin.name), Value::NativeFunction(builtin.native_function())))
+                .collect()],
         }
     }
 
@@ -38,21 +42,47 @@ impl Environment {
     }
 
     /// Exits and removes the right-most scope.
-    pub fn exit_scope(&mut self) {
-        self.scopes.pop();
-        if self.scopes.is_empty() {
-            panic!("Exited out of base scope.");
+    /// The base scope is never removed, so that there is always a scope to declare to.
+    pub fn exit_scope(&mut self, line: usize) -> Result<(), ErrorType> {
+        if self.scopes.len() <= 1 {
+            return Err(ErrorType::InternalError { message: String::from("attempted to exit the base scope"), line });
         }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    /// Returns the number of scopes, including the base scope.
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Returns the name-value pairs declared in the scope at `index`, sorted by name, or none if there is no such scope.
+    /// The base scope has index 0.
+    pub fn scope(&self, index: usize) -> Vec<(String, Value)> {
+        let mut bindings: Vec<(String, Value)> = self.scopes.get(index)
+            .map(|scope| scope.iter().map(|(name, value)| (name.clone(), value.clone())).collect())
+            .unwrap_or_default();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
     }
 
     /// Declares a name-value pair in the current scope.
-    pub fn declare(&mut self, name: String, value: &Value) {
-        if let Some(last_scope) = self.scopes.last_mut() {
-            // If there is at least one scope, insert the name-value pair into the right-most scope.
-            last_scope.insert(name, value.clone());
-        } else {
-            // Should be unreachable.
-            panic!("No scopes to declare to.");
+    /// Returns the value the name was previously declared with in the current scope, if it was.
+    pub fn declare(&mut self, name: String, value: &Value) -> Option<Value> {
+        // There is always at least one scope, as `exit_scope()` never removes the base scope.
+        // Insert the name-value pair into the right-most scope.
+        self.scopes.last_mut().and_then(|last_scope| last_scope.insert(name, value.clone()))
+    }
+
+    /// Returns the value associated with `name` in the base scope, if there is one.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.scopes.first().and_then(|scope| scope.get(name)).cloned()
+    }
+
+    /// Declares a name-value pair in the base scope, replacing any value already associated with `name` there.
+    pub fn declare_global(&mut self, name: &str, value: Value) {
+        if let Some(scope) = self.scopes.first_mut() {
+            scope.insert(name.to_string(), value);
         }
     }
 
@@ -68,7 +98,7 @@ impl Environment {
         }
         // We have iterated through all the scopes and no value have been found to be associated with `name`.
         // So raise a `NameError`, giving the `name` in question to be as detailed as possible.
-        Err(ErrorType::NameError { name, line })
+        Err(self.name_error(name, line))
     }
 
     /// Updates the value associated with the pointer. Again, update the one in the right-most scope only.
@@ -77,14 +107,14 @@ impl Environment {
             // Iterate from the right-most scope.
             if let Some(object) = scope.get_mut(&pointer.name) {
                 // If there is a value associated with `pointer.name`...
-                if !pointer.indices.is_empty() {
+                if let Some((last_index, indices)) = pointer.indices.split_last() {
                     // If indices were provided...
 
                     // This is the array/dictionary associated with `pointer.name`.
                     let mut current_element = object;
 
                     // For each index in `pointer.indices` except the last, replace `current_element` with `current_element[index]`.
-                    for i in pointer.indices.iter().take(pointer.indices.len() - 1) {
+                    for i in indices {
                         match current_element {
                             Value::Array(array) => {
                                 // If `current_element` is an array, we have to convert the index into `usize` and make sure
@@ -111,7 +141,6 @@ impl Environment {
                     // 1. Dictionaries can insert key-value pairs with the last key if it does not exist already.
                     //    For example, `a[1][5] = 1` inserts `5` as a key if it does not exist already (`a[1]` is a dictionary).
                     // 2. For strings, you have to do it this way to allow mutations like `a[2][1] = 'h'`.
-                    let last_index = pointer.indices.last().unwrap();
                     match current_element {
                         Value::Array(array) => {
                             // As above.
@@ -126,14 +155,14 @@ impl Environment {
                         },
                         Value::Dictionary(dict) => {
                             // `HashTable` inserts key-value pairs if the key does not exist already and updates them otherwise.
-                            dict.insert(last_index, value, line)?;
+                            dict.insert(last_index.clone(), value.clone(), line)?;
                         },
                         Value::String_(s) => {
                             // Convert the index value into a `usize`.
                             let idx = index_value_to_usize(last_index, line)?;
 
-                            // Make sure it is not out-of-bounds.
-                            if s.get(idx..idx+1).is_none() {
+                            // Make sure it is not out-of-bounds. `idx` may be as large as `usize::MAX`, so the addition is checked.
+                            if idx.checked_add(1).and_then(|end| s.get(idx..end)).is_none() {
                                 return Err(ErrorType::OutOfBoundsIndex { index: idx, line });
                             }
 
@@ -161,7 +190,36 @@ impl Environment {
         }
         // We have iterated through all the scopes and no value have been found to be associated with `name`.
         // So raise a `NameError`, giving the `name` in question to be as detailed as possible.
-        Err(ErrorType::NameError { name: pointer.name.clone(), line })
+        Err(self.name_error(pointer.name.clone(), line))
+    }
+
+    /// Returns every name visible from the current scope, including the built-in functions.
+    /// A name declared in more than one scope is only returned once.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.scopes.iter().flat_map(|scope| scope.keys().cloned()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Returns every name visible from the current scope with its value, sorted by name.
+    /// A name declared in more than one scope is only returned once, with the value in the right-most scope.
+    pub fn bindings(&self) -> Vec<(String, Value)> {
+        self.names()
+            .into_iter()
+            .filter_map(|name| {
+                let value = self.scopes.iter().rev().find_map(|scope| scope.get(&name))?.clone();
+                Some((name, value))
+            })
+            .collect()
+    }
+
+    /// Constructs a `NameError` for `name`, suggesting the closest visible name or keyword if `name` looks like a typo.
+    fn name_error(&self, name: String, line: usize) -> ErrorType {
+        let names = self.names();
+        let candidates = names.iter().map(String::as_str).chain(token::KEYWORDS);
+        let suggestion = suggestion::closest_match(&name, candidates);
+        ErrorType::NameError { name, suggestion, line }
     }
 }
 
@@ -238,10 +296,10 @@ mod tests {
         let _ = env.update(&Pointer { name: String::from("b"), indices: vec![] }, &Value::Number(30.0), 1);
         assert_eq!(env.get(String::from("b"), 1), Ok(Value::Number(30.0)));
 
-        env.exit_scope();
+        assert_eq!(env.exit_scope(1), Ok(()));
         assert_eq!(env.get(String::from("b"), 1), Ok(Value::Number(30.0)));
 
-        env.exit_scope();
+        assert_eq!(env.exit_scope(1), Ok(()));
         assert_eq!(env.get(String::from("a"), 1), Ok(Value::Number(10.0)));
         assert_eq!(env.get(String::from("b"), 1), Ok(Value::Number(2.0)));
     }
@@ -249,13 +307,56 @@ mod tests {
     #[test]
     fn name_error_get() {
         let env = Environment::new();
-        assert_eq!(env.get(String::from("b"), 1), Err(ErrorType::NameError { name: String::from("b"), line: 1 }));
+        assert_eq!(env.get(String::from("b"), 1), Err(ErrorType::NameError { name: String::from("b"), suggestion: None, line: 1 }));
     }
 
     #[test]
     fn name_error_assign() {
         let mut env = Environment::new();
-        assert_eq!(env.update(&Pointer { name: String::from("b"), indices: vec![] }, &Value::Null, 1), Err(ErrorType::NameError { name: String::from("b"), line: 1 }));
+        assert_eq!(env.update(&Pointer { name: String::from("b"), indices: vec![] }, &Value::Null, 1), Err(ErrorType::NameError { name: String::from("b"), suggestion: None, line: 1 }));
+    }
+
+    #[test]
+    fn name_error_suggestions() {
+        //  var counter = 0
+        //  {
+        //      var total = 1
+        //      "countr?"
+        //      "totl?"
+        //      "to_strin?"
+        //      "function?"
+        //  }
+        let mut env = Environment::new();
+        env.declare(String::from("counter"), &Value::Number(0.0));
+        env.new_scope();
+        env.declare(String::from("total"), &Value::Number(1.0));
+        assert_eq!(env.get(String::from("countr"), 1), Err(ErrorType::NameError { name: String::from("countr"), suggestion: Some(String::from("counter")), line: 1 }));
+        assert_eq!(env.get(String::from("totl"), 1), Err(ErrorType::NameError { name: String::from("totl"), suggestion: Some(String::from("total")), line: 1 }));
+        assert_eq!(env.get(String::from("to_strin"), 1), Err(ErrorType::NameError { name: String::from("to_strin"), suggestion: Some(String::from("to_string")), line: 1 }));
+        assert_eq!(env.get(String::from("function"), 1), Err(ErrorType::NameError { name: String::from("function"), suggestion: Some(String::from("func")), line: 1 }));
+    }
+
+    #[test]
+    fn exit_base_scope() {
+        let mut env = Environment::new();
+        env.new_scope();
+        assert_eq!(env.exit_scope(1), Ok(()));
+        assert!(matches!(env.exit_scope(2), Err(ErrorType::InternalError { line: 2, .. })));
+        // The base scope, and so the built-in functions, must still be there.
+        assert!(matches!(env.get(String::from("size"), 3), Ok(Value::NativeFunction(function)) if function.name == "size"));
+    }
+
+    #[test]
+    fn bindings() {
+        let mut env = Environment::new();
+        env.declare(String::from("b"), &Value::Number(1.0));
+        env.new_scope();
+        env.declare(String::from("b"), &Value::Number(2.0));
+        env.declare(String::from("a"), &Value::Null);
+        let bindings = env.bindings();
+        let user_bindings: Vec<&(String, Value)> = bindings.iter().filter(|(name, _)| name == "a" || name == "b").collect();
+        assert_eq!(user_bindings, vec![&(String::from("a"), Value::Null), &(String::from("b"), Value::Number(2.0))]);
+        assert!(bindings.iter().any(|(name, value)| name == "size" && matches!(value, Value::NativeFunction(..))));
     }
 
     #[test]
@@ -1,14 +1,22 @@
-use crate::value::Value;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::value::{Arity, Value};
 
 /// Possible errors that may occur during execution. This type will be used when bubbling up errors.
 #[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
 pub enum ErrorType {
     // Lexical analysis errors, i.e., tokenization errors.
     UnexpectedCharacter {
         character: char,
-        line: usize,
+        line: usize,  // The line and column of the character.
+        column: usize,
+    },
+    UnterminatedString {
+        line: usize,  // The line and column of the opening quote.
+        column: usize,
     },
-    UnterminatedString,
 
     // Syntax analysis errors, i.e., syntax errors.
     ExpectedCharacter {
@@ -39,10 +47,41 @@ pub enum ErrorType {
     ExpectedColonAfterKey {
         line: usize,
     },
+    UnclosedBracket {
+        expected: char,  // The closing bracket that was expected.
+        opened_line: usize,  // The line of the matching opening bracket.
+        line: usize,
+    },
+    UnclosedCall {
+        opened_line: usize,  // The line of the opening `(` of the call.
+        line: usize,
+    },
+    UnexpectedClosingBracket {
+        bracket: char,
+        line: usize,
+    },
+    AssignmentInCondition {
+        line: usize,
+    },
+    ElseWithoutIf {
+        line: usize,
+    },
+    UnexpectedSemicolon {
+        line: usize,
+    },
+    KeywordAsName {
+        keyword: String,
+        line: usize,
+    },
+    NestingTooDeep {
+        depth: usize,  // The maximum nesting depth, which was exceeded.
+        line: usize,
+    },
     
     // Environment errors.
     NameError {
         name: String,
+        suggestion: Option<String>,  // The closest name or keyword, if `name` looks like a typo of one.
         line: usize,
     },
     NotIndexable {
@@ -88,6 +127,14 @@ pub enum ErrorType {
     LoopConditionNotBoolean {
         line: usize,
     },
+    AssertConditionNotBoolean {
+        line: usize,
+    },
+    AssertionFailed {
+        condition: String,  // The source code of the condition which was `false`.
+        message: Option<String>,  // The message given in the `assert` statement, if there was one.
+        line: usize,
+    },
     CannotCallName {
         line: usize,
     },
@@ -96,9 +143,29 @@ pub enum ErrorType {
         param_number: usize,
         line: usize,
     },
+    ArgNumberNotAccepted {
+        arg_number: usize,
+        accepted: Arity,  // The numbers of arguments the native function accepts, when it is not a single number.
+        line: usize,
+    },
     CannotConvertToNumber {
         line: usize,
     },
+    NumberOutOfRange {
+        number: f64,
+        target: String,  // The Rust type the number was being converted to, e.g., `u8`.
+        line: usize,
+    },
+    StackOverflow {
+        line: usize,
+    },
+    IoError {
+        message: String,  // The description of the error given by the operating system.
+        line: usize,
+    },
+    EndOfInput {
+        line: usize,
+    },
 
     // Hash table errors.
     CannotHashFunction {
@@ -123,122 +190,328 @@ pub enum ErrorType {
         value: Value,
         line: usize,
     },
+    // Used by the debugger to stop the script when the user quits.
+    ThrownStop {
+        line: usize,
+    },
+
+    // Internal errors.
+    // These indicate a bug in the interpreter rather than in the script, e.g., a broken invariant.
+    // They are raised instead of panicking so that a host program is never aborted by a script.
+    InternalError {
+        message: String,
+        line: usize,
+    },
 }
 
-/// Prints the error message for each error in `errors`.
-pub fn report_errors(errors: &[ErrorType]) {
-    println!("An error has occurred.");
-    for error in errors {
-        print_report(error);
+impl ErrorType {
+    /// Returns the message describing the error, including the line it occurred on.
+    pub fn message(&self) -> String {
+        match self {
+            // Lexical analysis errors, i.e., tokenization errors.
+            ErrorType::UnexpectedCharacter { character, line, column } => {
+                format!("Line {}, column {}: unexpected character `{}`.", line, column, character)
+            },
+            ErrorType::UnterminatedString { line, column } => {
+                format!("Line {}, column {}: this string was never closed by the end of the program.", line, column)
+            },
+
+            // Syntax analysis errors, i.e., syntax errors.
+            ErrorType::ExpectedCharacter { expected, line } => {
+                format!("Line {}: expected character `{}`", line, expected)
+            },
+            ErrorType::ExpectedExpression { line } => {
+                format!("Line {}: expected expression.", line)
+            },
+            ErrorType::ExpectedFunctionName { line } => {
+                format!("Line {}: expected function name. Make sure it is not a keyword.", line)
+            },
+            ErrorType::ExpectedParameterName { line } => {
+                format!("Line {}: expected parameter name in function declaration.", line)
+            },
+            ErrorType::ExpectedVariableName { line } => {
+                format!("Line {}: expected variable name. Make sure it is not a keyword.", line)
+            },
+            ErrorType::ExpectedSemicolonAfterInit { line } => {
+                format!("Line {}: expected `;` after initialising statement in `for` loop.", line)
+            },
+            ErrorType::ExpectedSemicolonAfterCondition { line } => {
+                format!("Line {}: expected `;` after condition in `for` loop.", line)
+            },
+            ErrorType::ExpectedParenAfterIncrement { line } => {
+                format!("Line {}: expected `)` after increment statement in `for` loop.", line)
+            },
+            ErrorType::ExpectedColonAfterKey { line } => {
+                format!("Line {}: expected colon after dictionary key.", line)
+            },
+            ErrorType::UnclosedBracket { expected, opened_line, line } => {
+                format!("Line {}: expected `{}` to close the bracket opened on line {}.", line, expected, opened_line)
+            },
+            ErrorType::UnclosedCall { opened_line, line } => {
+                format!("Line {}: expected `)` to close the function call opened on line {}.", line, opened_line)
+            },
+            ErrorType::UnexpectedClosingBracket { bracket, line } => {
+                format!("Line {}: `{}` does not close any bracket.", line, bracket)
+            },
+            ErrorType::AssignmentInCondition { line } => {
+                format!("Line {}: `=` assigns a value, so it cannot be used in a condition.", line)
+            },
+            ErrorType::ElseWithoutIf { line } => {
+                format!("Line {}: `else` without a matching `if`.", line)
+            },
+            ErrorType::UnexpectedSemicolon { line } => {
+                format!("Line {}: unexpected `;`.", line)
+            },
+            ErrorType::KeywordAsName { keyword, line } => {
+                format!("Line {}: `{}` is a keyword, so it cannot be used as a name.", line, keyword)
+            },
+            ErrorType::NestingTooDeep { depth, line } => {
+                format!("Line {}: brackets and blocks are nested more than {} levels deep.", line, depth)
+            },
+
+            // Environment errors.
+            ErrorType::NameError { ref name, ref suggestion, line } => {
+                if let Some(suggestion) = suggestion {
+                    format!("Line {}: `{}` is not defined. Did you mean `{}`?", line, name, suggestion)
+                } else {
+                    format!("Line {}: `{}` is not defined.", line, name)
+                }
+            },
+            ErrorType::NotIndexable { line } => {
+                format!("Line {}: the value is not indexable.", line)
+            },
+            ErrorType::OutOfBoundsIndex { index, line } => {
+                format!("Line {}: index `{}` is out of bounds.", line, index)
+            },
+            ErrorType::InsertNonStringIntoString { line } => {
+                format!("Line {}: attempted to insert a non-string into a string.", line)
+            },
+
+            // Execution errors, i.e., runtime errors.
+            ErrorType::InvalidAssignmentTarget { line } => {
+                format!("Line {}: invalid assignment target. Make sure you are not assigning to a literal.", line)
+            },
+            ErrorType::ExpectedType { ref expected, ref got, line } => {
+                format!("Line {}: expected type {}; instead got type {}.", line, expected, got)
+            },
+            ErrorType::NonNaturalIndex { got, line } => {
+                format!("Line {}: index evaluated to {}, which is not a positive integer.", line, got)
+            },
+            ErrorType::NonNumberIndex { got, line } => {
+                format!("Line {}: index evaluated to a {}, which is not a positive integer.", line, got)
+            },
+            ErrorType::BinaryTypeError { ref expected, ref got_left, ref got_right, line } => {
+                format!("Line {}: this operation requires both sides' types to be {}. Instead, got {} and {} respectively.", line, expected, got_left, got_right)
+            },
+            ErrorType::DivideByZero { line } => {
+                format!("Line {}: divisor is 0.", line)
+            },
+            ErrorType::IfConditionNotBoolean { line } => {
+                format!("Line {}: the `if` condition did not evaluate to a Boolean value.", line)
+            },
+            ErrorType::LoopConditionNotBoolean { line } => {
+                format!("Line {}: the condition of the loop did not evaluate to a Boolean value.", line)
+            },
+            ErrorType::AssertConditionNotBoolean { line } => {
+                format!("Line {}: the condition of the `assert` statement did not evaluate to a Boolean value.", line)
+            },
+            ErrorType::AssertionFailed { condition, message, line } => {
+                if let Some(message) = message {
+                    format!("Line {}: assertion `{}` failed: {}", line, condition, message)
+                } else {
+                    format!("Line {}: assertion `{}` failed.", line, condition)
+                }
+            },
+            ErrorType::CannotCallName { line } => {
+                format!("Line {}: cannot call name as a function.", line)
+            },
+            ErrorType::ArgParamNumberMismatch { arg_number, param_number, line } => {
+                format!("Line {}: attempted to call function with {} argument(s), but function accepts {}.", line, arg_number, param_number)
+            },
+            ErrorType::ArgNumberNotAccepted { arg_number, accepted, line } => {
+                format!("Line {}: attempted to call function with {} argument(s), but function accepts {}.", line, arg_number, accepted)
+            },
+            ErrorType::CannotConvertToNumber { line } => {
+                format!("Line {}: could not convert to a number.", line)
+            },
+            ErrorType::NumberOutOfRange { number, target, line } => {
+                format!("Line {}: could not convert {} to `{}`, as it is not a whole number in its range.", line, number, target)
+            },
+            ErrorType::StackOverflow { line } => {
+                format!("Line {}: function calls or code were nested too deeply, so the program ran out of stack space.", line)
+            },
+            ErrorType::IoError { message, line } => {
+                format!("Line {}: input/output error: {}.", line, message)
+            },
+            ErrorType::EndOfInput { line } => {
+                format!("Line {}: there is no more input to read.", line)
+            },
+
+            // Hash table errors.
+            ErrorType::CannotHashFunction { line } => {
+                format!("Line {}: cannot hash function (functions cannot be used as keys in dictionary entries).", line)
+            },
+            ErrorType::CannotHashDictionary { line } => {
+                format!("Line {}: cannot hash dictionary (dictionaries cannot be used as keys in dictionary entries).", line)
+            },
+            ErrorType::KeyError { key, line } => {
+                format!("Line {}: key `{}` does not exist in the dictionary.", line, key)
+            },
+
+            // Special errors.
+            ErrorType::ThrownBreak { line } => {
+                format!("Line {}: `break` has to be used within a loop.", line)
+            },
+            ErrorType::ThrownReturn { value: _ , line} => {
+                format!("Line {}: `return` has to be used within a function.", line)
+            },
+            ErrorType::ThrownStop { line } => {
+                format!("Line {}: the program was stopped.", line)
+            },
+
+            // Internal errors.
+            ErrorType::InternalError { message, line } => {
+                format!("Line {}: internal error: {}. This is a bug in the interpreter, not in your program.", line, message)
+            },
+        }
+    }
+
+    /// Returns the line the error occurred on.
+    pub fn line(&self) -> usize {
+        match self {
+            Self::UnexpectedCharacter { line, .. } |
+            Self::UnterminatedString { line, .. } |
+            Self::ExpectedCharacter { line, .. } |
+            Self::ExpectedExpression { line, .. } |
+            Self::ExpectedFunctionName { line, .. } |
+            Self::ExpectedParameterName { line, .. } |
+            Self::ExpectedVariableName { line, .. } |
+            Self::ExpectedSemicolonAfterInit { line, .. } |
+            Self::ExpectedSemicolonAfterCondition { line, .. } |
+            Self::ExpectedParenAfterIncrement { line, .. } |
+            Self::ExpectedColonAfterKey { line, .. } |
+            Self::UnclosedBracket { line, .. } |
+            Self::UnclosedCall { line, .. } |
+            Self::UnexpectedClosingBracket { line, .. } |
+            Self::AssignmentInCondition { line, .. } |
+            Self::ElseWithoutIf { line, .. } |
+            Self::UnexpectedSemicolon { line, .. } |
+            Self::KeywordAsName { line, .. } |
+            Self::NestingTooDeep { line, .. } |
+            Self::NameError { line, .. } |
+            Self::NotIndexable { line, .. } |
+            Self::OutOfBoundsIndex { line, .. } |
+            Self::InsertNonStringIntoString { line, .. } |
+            Self::InvalidAssignmentTarget { line, .. } |
+            Self::ExpectedType { line, .. } |
+            Self::NonNaturalIndex { line, .. } |
+            Self::NonNumberIndex { line, .. } |
+            Self::BinaryTypeError { line, .. } |
+            Self::DivideByZero { line, .. } |
+            Self::IfConditionNotBoolean { line, .. } |
+            Self::LoopConditionNotBoolean { line, .. } |
+            Self::AssertConditionNotBoolean { line, .. } |
+            Self::AssertionFailed { line, .. } |
+            Self::CannotCallName { line, .. } |
+            Self::ArgParamNumberMismatch { line, .. } |
+            Self::ArgNumberNotAccepted { line, .. } |
+            Self::CannotConvertToNumber { line, .. } |
+            Self::NumberOutOfRange { line, .. } |
+            Self::StackOverflow { line, .. } |
+            Self::IoError { line, .. } |
+            Self::EndOfInput { line, .. } |
+            Self::CannotHashFunction { line, .. } |
+            Self::CannotHashDictionary { line, .. } |
+            Self::KeyError { line, .. } |
+            Self::ThrownBreak { line, .. } |
+            Self::ThrownReturn { line, .. } |
+            Self::ThrownStop { line, .. } |
+            Self::InternalError { line, .. } => *line,
+        }
+    }
+
+    /// Returns a suggestion on how to fix the error, for errors caused by common mistakes.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Self::UnclosedBracket { expected, .. } => {
+                Some(format!("add `{}` where the bracket should end, or check that every bracket in between is closed.", expected))
+            },
+            Self::UnclosedCall { .. } => {
+                Some(String::from("add `)` after the last argument, and separate the arguments with `,`."))
+            },
+            Self::UnexpectedClosingBracket { .. } => {
+                Some(String::from("remove it, or add the matching opening bracket before it."))
+            },
+            Self::AssignmentInCondition { .. } => {
+                Some(String::from("use `==` to check whether two values are equal."))
+            },
+            Self::ElseWithoutIf { .. } => {
+                Some(String::from("`else` must come directly after the closing `}` of an `if` statement."))
+            },
+            Self::UnexpectedSemicolon { .. } => {
+                Some(String::from("statements do not end with `;`, so it can be removed. `;` is only used inside `for (...)`."))
+            },
+            Self::KeywordAsName { keyword, .. } => {
+                Some(format!("choose a different name, e.g., `{}_`.", keyword))
+            },
+            Self::StackOverflow { .. } => {
+                Some(String::from("check that every recursive function has a base case which stops the recursion."))
+            },
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for ErrorType {}
+
+/// The error returned by `Interpreter::eval_str()`. The source code is only run if it has no syntax errors,
+/// so either all the lexical and syntax errors are returned, or the error which stopped execution.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    Syntax(Vec<ErrorType>),
+    Runtime(ErrorType),
+}
+
+impl Error {
+    /// Returns every error, e.g., to report them with `report_errors()`.
+    pub fn errors(&self) -> &[ErrorType] {
+        match self {
+            Self::Syntax(errors) => errors,
+            Self::Runtime(error) => std::slice::from_ref(error),
+        }
     }
 }
 
-/// Prints the error message for an individual error.
-fn print_report(error: &ErrorType) {
-    match error {
-        // Lexical analysis errors, i.e., tokenization errors.
-        ErrorType::UnexpectedCharacter { character, line } => {
-            println!("Line {}: unexpected character `{}`.", line, character);
-        },
-        ErrorType::UnterminatedString => {
-            println!("A string was never closed by the end of the program.");
-        },
-
-        // Syntax analysis errors, i.e., syntax errors.
-        ErrorType::ExpectedCharacter { expected, line } => {
-            println!("Line {}: expected character `{}`", line, expected);
-        },
-        ErrorType::ExpectedExpression { line } => {
-            println!("Line {}: expected expression.", line);
-        },
-        ErrorType::ExpectedFunctionName { line } => {
-            println!("Line {}: expected function name. Make sure it is not a keyword.", line);
-        },
-        ErrorType::ExpectedParameterName { line } => {
-            println!("Line {}: expected parameter name in function declaration.", line);
-        },
-        ErrorType::ExpectedVariableName { line } => {
-            println!("Line {}: expected variable name. Make sure it is not a keyword.", line);
-        },
-        ErrorType::ExpectedSemicolonAfterInit { line } => {
-            println!("Line {}: expected `;` after initialising statement in `for` loop.", line);
-        },
-        ErrorType::ExpectedSemicolonAfterCondition { line } => {
-            println!("Line {}: expected `;` after condition in `for` loop.", line);
-        },
-        ErrorType::ExpectedParenAfterIncrement { line } => {
-            println!("Line {}: expected `)` after increment statement in `for` loop.", line);
-        },
-        ErrorType::ExpectedColonAfterKey { line } => {
-            println!("Line {}: expected colon after dictionary key.", line);
-        },
-
-        // Environment errors.
-        ErrorType::NameError { ref name, line } => {
-            println!("Line {}: `{}` is not defined.", line, name);
-        },
-        ErrorType::NotIndexable { line } => {
-            println!("Line {}: the value is not indexable.", line);
-        },
-        ErrorType::OutOfBoundsIndex { index, line } => {
-            println!("Line {}: index `{}` is out of bounds.", line, index);
-        },
-        ErrorType::InsertNonStringIntoString { line } => {
-            println!("Line {}: attempted to insert a non-string into a string.", line);
-        },
-
-        // Execution errors, i.e., runtime errors.
-        ErrorType::InvalidAssignmentTarget { line } => {
-            println!("Line {}: invalid assignment target. Make sure you are not assigning to a literal.", line);
-        },
-        ErrorType::ExpectedType { ref expected, ref got, line } => {
-            println!("Line {}: expected type {}; instead got type {}.", line, expected, got);
-        },
-        ErrorType::NonNaturalIndex { got, line } => {
-            println!("Line {}: index evaluated to {}, which is not a positive integer.", line, got);
-        },
-        ErrorType::NonNumberIndex { got, line } => {
-            println!("Line {}: index evaluated to a {}, which is not a positive integer.", line, got);
-        },
-        ErrorType::BinaryTypeError { ref expected, ref got_left, ref got_right, line } => {
-            println!("Line {}: this operation requires both sides' types to be {}. Instead, got {} and {} respectively.", line, expected, got_left, got_right);
-        },
-        ErrorType::DivideByZero { line } => {
-            println!("Line {}: divisor is 0.", line);
-        },
-        ErrorType::IfConditionNotBoolean { line } => {
-            println!("Line {}: the `if` condition did not evaluate to a Boolean value.", line);
-        },
-        ErrorType::LoopConditionNotBoolean { line } => {
-            println!("Line {}: the condition of the loop did not evaluate to a Boolean value.", line);
-        },
-        ErrorType::CannotCallName { line } => {
-            println!("Line {}: cannot call name as a function.", line);
-        },
-        ErrorType::ArgParamNumberMismatch { arg_number, param_number, line } => {
-            println!("Line {}: attempted to call function with {} argument(s), but function accepts {}.", line, arg_number, param_number);
-        },
-        ErrorType::CannotConvertToNumber { line } => {
-            println!("Line {}: could not convert to a number.", line);
-        },
-
-        // Hash table errors.
-        ErrorType::CannotHashFunction { line } => {
-            println!("Line {}: cannot hash function (functions cannot be used as keys in dictionary entries).", line);
-        },
-        ErrorType::CannotHashDictionary { line } => {
-            println!("Line {}: cannot hash dictionary (dictionaries cannot be used as keys in dictionary entries).", line);
-        },
-        ErrorType::KeyError { key, line } => {
-            println!("Line {}: key `{}` does not exist in the dictionary.", line, key);
-        },
-
-        // Special errors.
-        ErrorType::ThrownBreak { line } => {
-            println!("Line {}: `break` has to be used within a loop.", line);
-        },
-        ErrorType::ThrownReturn { value: _ , line} => {
-            println!("Line {}: `return` has to be used within a function.", line);
-        },
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<String> = self.errors().iter().map(ErrorType::message).collect();
+        write!(f, "{}", messages.join("\n"))
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Prints the error message for each error in `errors`, followed by its hint if it has one.
+pub fn report_errors(errors: &[ErrorType]) {
+    write_errors(&mut io::stdout().lock(), errors);
+}
+
+/// Writes the error message for each error in `errors` to `output`, followed by its hint if it has one.
+pub fn write_errors(output: &mut dyn Write, errors: &[ErrorType]) {
+    // Unlike `println!()`, `writeln!()` does not panic if stdout has been closed. There is nowhere left to report to in that case,
+    // so the result is ignored.
+    let _ = writeln!(output, "An error has occurred.");
+    for error in errors {
+        let _ = writeln!(output, "{}", error.message());
+        if let Some(hint) = error.hint() {
+            let _ = writeln!(output, "    Hint: {}", hint);
+        }
     }
+    let _ = output.flush();
 }
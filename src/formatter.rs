@@ -0,0 +1,302 @@
+use crate::error::ErrorType;
+use crate::parser::Parser;
+use crate::token::{Token, TokenType};
+use crate::tokenizer::Tokenizer;
+
+/// The indentation for each level of nesting.
+const INDENT: &str = "    ";
+
+/// Tokens after which an operand is expected, e.g., `-` after these is a unary minus rather than a binary one.
+const EXPECTS_OPERAND: [TokenType; 24] = [
+    TokenType::LeftParen, TokenType::LeftSquare, TokenType::Comma, TokenType::Colon, TokenType::Semicolon,
+    TokenType::Plus, TokenType::Minus, TokenType::Star, TokenType::Slash, TokenType::Percent,
+    TokenType::Bang, TokenType::BangEqual, TokenType::Equal, TokenType::EqualEqual,
+    TokenType::Greater, TokenType::GreaterEqual, TokenType::Less, TokenType::LessEqual,
+    TokenType::And, TokenType::Or, TokenType::Assert, TokenType::Print, TokenType::Return, TokenType::Var,
+];
+
+/// Binary operators. A line ending in one of these continues on the next line, which is indented by an extra level.
+const BINARY_OPERATORS: [TokenType; 15] = [
+    TokenType::Plus, TokenType::Minus, TokenType::Star, TokenType::Slash, TokenType::Percent,
+    TokenType::BangEqual, TokenType::Equal, TokenType::EqualEqual,
+    TokenType::Greater, TokenType::GreaterEqual, TokenType::Less, TokenType::LessEqual,
+    TokenType::And, TokenType::Or, TokenType::Bang,
+];
+
+/// The kinds of brackets, which are formatted differently.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Bracket {
+    Paren,
+    Header,  // The parentheses of an `if`, `while` or `for` statement or of a function's parameters, which are followed by a block.
+    Square,
+    Dictionary,
+    Block,  // The body of a function, `if` or loop, which always spans multiple lines.
+}
+
+/// Formats the source code in the canonical style, keeping its comments.
+/// Only whitespace is changed: e.g., `{` is moved to the end of the line before it, statements in a block are put on their own lines,
+/// and operators are surrounded by single spaces. Source code with errors is not formatted, and the errors are returned instead.
+pub fn format(source: &str) -> Result<String, Vec<ErrorType>> {
+    // Only programs which parse can be formatted reliably, e.g., a `{` could not be matched with its `}` otherwise.
+    let (tokens, mut errors) = Tokenizer::new(source).tokenize();
+    if let Err(parser_errors) = Parser::new(tokens).parse() {
+        errors.extend(parser_errors);
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // The formatter works on the tokens rather than the abstract syntax tree, as the tree does not contain comments,
+    // and `for` loops are turned into `while` loops by the parser.
+    let (tokens, _) = Tokenizer::with_comments(source).tokenize();
+    Ok(Formatter::new().format(&tokens))
+}
+
+/// Lays out a sequence of tokens line by line.
+struct Formatter {
+    lines: Vec<String>,  // The lines formatted so far, not including the current one.
+    line: String,  // The current line.
+    brackets: Vec<(Bracket, usize)>,  // The brackets which are currently open, and the index of the line each was opened on.
+}
+
+impl Formatter {
+    /// Constructs a new `Formatter` with no output.
+    fn new() -> Self {
+        Self {
+            lines: Vec::new(),
+            line: String::new(),
+            brackets: Vec::new(),
+        }
+    }
+
+    /// Returns the formatted source code of the tokens, which must include comments and be balanced.
+    fn format(mut self, tokens: &[Token]) -> String {
+        let mut previous: Option<(&Token, Option<Bracket>)> = None;  // The previous token, and the kind of bracket it is, if it is one.
+        let mut previous_code: Option<(&Token, Option<Bracket>)> = None;  // The same as `previous`, but skipping comments.
+        let mut second_previous_code: Option<&Token> = None;  // The token which is not a comment before `previous_code`.
+        let mut previous_unary = false;  // Whether the previous token was a unary operator.
+        let mut end_line = 0;  // The line of the source code the previous token ended on.
+
+        for token in tokens.iter().take_while(|token| token.type_ != TokenType::Eof) {
+            // Work out what kind of bracket this is, popping closing brackets.
+            let mut popped_line = None;
+            let bracket = match token.type_ {
+                TokenType::LeftParen => match (second_previous_code, previous_code) {
+                    (_, Some((code, _))) if matches!(code.type_, TokenType::If | TokenType::While | TokenType::For) => Some(Bracket::Header),
+                    (Some(func), Some((name, _))) if func.type_ == TokenType::Func && name.type_ == TokenType::Identifier => Some(Bracket::Header),
+                    _ => Some(Bracket::Paren),
+                },
+                TokenType::LeftSquare => Some(Bracket::Square),
+                // Blocks only follow `else` or the parentheses of a header, e.g., `if (x) {`. Any other `{` starts a dictionary.
+                TokenType::LeftCurly => match previous_code {
+                    Some((code, _)) if code.type_ == TokenType::Else => Some(Bracket::Block),
+                    Some((_, Some(Bracket::Header))) => Some(Bracket::Block),
+                    _ => Some(Bracket::Dictionary),
+                },
+                TokenType::RightParen | TokenType::RightSquare | TokenType::RightCurly => {
+                    let popped = self.brackets.pop();
+                    popped_line = popped.map(|(_, line)| line);
+                    popped.map(|(bracket, _)| bracket)
+                },
+                _ => None,
+            };
+            let is_opening = matches!(token.type_, TokenType::LeftParen | TokenType::LeftSquare | TokenType::LeftCurly);
+            let is_closing = matches!(token.type_, TokenType::RightParen | TokenType::RightSquare | TokenType::RightCurly);
+
+            match previous {
+                None => (),
+                Some((previous_token, previous_bracket)) => {
+                    let after_comment = previous_token.type_ == TokenType::Comment;
+                    let after_block_open = previous_bracket == Some(Bracket::Block) && previous_token.type_ == TokenType::LeftCurly;
+                    let after_block_close = previous_bracket == Some(Bracket::Block) && previous_token.type_ == TokenType::RightCurly;
+
+                    let new_line = if after_comment {
+                        // A comment runs until the end of the line.
+                        true
+                    } else if token.type_ == TokenType::Comment {
+                        // Comments stay where they are, e.g., after the code on a line.
+                        token.line > end_line
+                    } else if is_opening && bracket == Some(Bracket::Block) {
+                        // `{` goes at the end of the line before it, e.g., `if (x) {`.
+                        false
+                    } else if is_closing && bracket == Some(Bracket::Block) {
+                        // The statements of a block go on their own lines, but an empty block stays as `{}`.
+                        !after_block_open
+                    } else if after_block_open {
+                        true
+                    } else if after_block_close {
+                        // `else` goes on the same line as the `}` before it.
+                        token.type_ != TokenType::Else
+                    } else {
+                        token.line > end_line
+                    };
+
+                    if new_line {
+                        // Keep at most one blank line between lines, except at the start or end of a bracket.
+                        let blank_line = token.line > end_line + 1 && !is_closing && !previous_token_opens(previous_token);
+                        // A line continuing an expression, e.g., after a trailing `+`, is indented by an extra level.
+                        let continues = previous_code.is_some_and(|(code, _)| BINARY_OPERATORS.contains(&code.type_)) && !after_comment;
+                        // A line starting with a closing bracket is indented like the line the bracket was opened on.
+                        let closing_line = if is_closing { popped_line } else { None };
+                        self.new_line(blank_line, continues, closing_line);
+                    } else {
+                        let space = spacing(previous_token, previous_bracket, previous_unary, token, bracket);
+                        self.line.push_str(space);
+                    }
+                },
+            }
+
+            if token.type_ == TokenType::Comment {
+                self.line.push_str(token.lexeme.trim_end());
+            } else {
+                self.line.push_str(&token.lexeme);
+            }
+            if is_opening {
+                if let Some(bracket) = bracket {
+                    self.brackets.push((bracket, self.lines.len()));
+                }
+            }
+
+            if token.type_ != TokenType::Comment {
+                previous_unary = token.type_ == TokenType::Bang
+                    || (token.type_ == TokenType::Minus && previous_code.is_none_or(|(code, _)| EXPECTS_OPERAND.contains(&code.type_)));
+                second_previous_code = previous_code.map(|(code, _)| code);
+                previous_code = Some((token, bracket));
+            }
+            previous = Some((token, bracket));
+            // A string may span several lines.
+            end_line = token.line + token.lexeme.matches('\n').count();
+        }
+
+        if !self.line.is_empty() {
+            self.lines.push(self.line);
+        }
+        if self.lines.is_empty() {
+            String::new()
+        } else {
+            self.lines.join("\n") + "\n"
+        }
+    }
+
+    /// Finishes the current line and indents the next one, optionally with a blank line in between.
+    /// The indentation is the number of lines with a bracket which is still open, as several brackets opened on one line,
+    /// e.g., `f([`, only add one level. `continues` adds an extra level for a line which continues an expression.
+    /// If the line starts by closing a bracket opened on the line with index `closing_line`, only the brackets opened before that line count.
+    fn new_line(&mut self, blank_line: bool, continues: bool, closing_line: Option<usize>) {
+        self.lines.push(std::mem::take(&mut self.line));
+        if blank_line {
+            self.lines.push(String::new());
+        }
+
+        let mut levels = 0;
+        let mut last_line = None;
+        for &(_, line) in self.brackets.iter().filter(|(_, line)| closing_line.is_none_or(|closing_line| *line < closing_line)) {
+            if last_line != Some(line) {
+                levels += 1;
+                last_line = Some(line);
+            }
+        }
+        if continues {
+            levels += 1;
+        }
+        self.line = INDENT.repeat(levels);
+    }
+}
+
+/// Returns `true` if the token is an opening bracket.
+fn previous_token_opens(token: &Token) -> bool {
+    matches!(token.type_, TokenType::LeftParen | TokenType::LeftSquare | TokenType::LeftCurly)
+}
+
+/// Returns the whitespace to put between two tokens on the same line.
+fn spacing(previous: &Token, previous_bracket: Option<Bracket>, previous_unary: bool, token: &Token, bracket: Option<Bracket>) -> &'static str {
+    match (&previous.type_, &token.type_) {
+        // Comments after code are separated from it by two spaces.
+        (_, TokenType::Comment) => "  ",
+        (_, TokenType::Comma | TokenType::Semicolon | TokenType::Colon | TokenType::RightParen | TokenType::RightSquare) => "",
+        (_, TokenType::RightCurly) if bracket == Some(Bracket::Dictionary) || previous.type_ == TokenType::LeftCurly => "",
+        (TokenType::LeftParen | TokenType::LeftSquare, _) => "",
+        (TokenType::LeftCurly, _) if previous_bracket == Some(Bracket::Dictionary) => "",
+        _ if previous_unary => "",
+        // Calls, e.g., `f(x)`. `print` is a statement rather than a function, so `print (a + b) * 2` keeps its space,
+        // as it prints the whole expression rather than only `(a + b)`.
+        (TokenType::Identifier | TokenType::RightParen | TokenType::RightSquare, TokenType::LeftParen) => "",
+        // Indices, e.g., `a[0]` and `"abc"[1]`.
+        (TokenType::Identifier | TokenType::RightParen | TokenType::RightSquare | TokenType::String_, TokenType::LeftSquare) => "",
+        (TokenType::RightCurly, TokenType::LeftSquare) if previous_bracket == Some(Bracket::Dictionary) => "",
+        _ => " ",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::token::TokenType;
+    use crate::tokenizer::Tokenizer;
+
+    use super::format;
+
+    /// Formats the source code, checking that formatting it again does not change it and that only whitespace was changed.
+    fn check(source: &str) -> String {
+        let formatted = format(source).unwrap();
+        assert_eq!(format(&formatted).unwrap(), formatted, "formatting is not idempotent for:\n{}", source);
+
+        let lexemes = |source: &str| -> Vec<(TokenType, String)> {
+            let (tokens, _) = Tokenizer::with_comments(source).tokenize();
+            tokens.into_iter().map(|token| (token.type_, token.lexeme.trim_end().to_string())).collect()
+        };
+        assert_eq!(lexemes(&formatted), lexemes(source), "formatting changed the tokens of:\n{}", source);
+        formatted
+    }
+
+    #[test]
+    fn spacing() {
+        assert_eq!(check("var a=1+2*-3"), "var a = 1 + 2 * -3\n");
+        assert_eq!(check("print( f ( a [ 0 ] , -b ) )"), "print (f(a[0], -b))\n");
+        assert_eq!(check("print(a+b)*2"), "print (a + b) * 2\n");
+        assert_eq!(check("var b = ! (a and b)or!c"), "var b = !(a and b) or !c\n");
+        assert_eq!(check("var d={ 1 :[ 2 ,3 ] ,'x':{}}"), "var d = {1: [2, 3], 'x': {}}\n");
+        assert_eq!(check("a = \"abc\" [1] - (2) [0]"), "a = \"abc\"[1] - (2)[0]\n");
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(check("func f(a,b)\n{ return a }"), "func f(a, b) {\n    return a\n}\n");
+        assert_eq!(check("if (a) {} else { if (b)\n{\nbreak\n}\nelse\n{\n}\n}"), "\
+if (a) {} else {
+    if (b) {
+        break
+    } else {}
+}
+");
+        assert_eq!(check("while(true){if (x) {var x = 1} print x}"), "while (true) {\n    if (x) {\n        var x = 1\n    }\n    print x\n}\n");
+        // A `{` which does not follow a header starts a dictionary, so it is not moved onto the line before it.
+        assert_eq!(check("f(x)\n{1: 2}"), "f(x)\n{1: 2}\n");
+        assert_eq!(check("for(var i=0;i<3;i=i+1){print(i)}"), "for (var i = 0; i < 3; i = i + 1) {\n    print (i)\n}\n");
+    }
+
+    #[test]
+    fn blank_lines() {
+        assert_eq!(check("\n\nvar a = 1\n\n\n\nvar b = 2\nif (a) {\n\n    b = 3\n\n}\n\n"), "var a = 1\n\nvar b = 2\nif (a) {\n    b = 3\n}\n");
+        assert_eq!(check(""), "");
+    }
+
+    #[test]
+    fn multiple_lines() {
+        assert_eq!(check("var a = [\n1,\n  [2,\n3]]\nvar b = 1 +\n2"), "var a = [\n    1,\n    [2,\n        3]]\nvar b = 1 +\n    2\n");
+        assert_eq!(check("f(a, {\n1: 2\n})"), "f(a, {\n    1: 2\n})\n");
+        assert_eq!(check("print \"a\nb\"\n    print 1"), "print \"a\nb\"\nprint 1\n");
+    }
+
+    #[test]
+    fn comments() {
+        let source = "# header\nvar a = 1 # one\n  # indented\nfunc f() {   # trailing   \n  # inside\nreturn a\n}  # end\n#last";
+        assert_eq!(check(source), "# header\nvar a = 1  # one\n# indented\nfunc f() {  # trailing\n    # inside\n    return a\n}  # end\n#last\n");
+        assert_eq!(check("if (a)  # c\n{\n}"), "if (a)  # c\n{}\n");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(format("var = 1").is_err());
+        assert!(format("'abc").is_err());
+    }
+}
@@ -0,0 +1,293 @@
+// A fuzzing harness which runs randomly generated source code through the tokenizer, parser, linter and interpreter.
+// Whatever the input, the pipeline must report errors rather than panic (or overflow the stack), as a panic would abort a host program.
+//
+// The generators are seeded, so every run of `cargo test` checks the same programs. To fuzz for longer or with other programs, set
+// `NEA_FUZZ_ITERATIONS` and `NEA_FUZZ_SEED`, e.g., `NEA_FUZZ_ITERATIONS=100000 NEA_FUZZ_SEED=7 cargo test --release fuzz`.
+
+use std::env;
+use std::panic;
+use std::thread;
+
+use crate::formatter;
+use crate::interpreter::Interpreter;
+use crate::linter::Linter;
+use crate::parser::Parser;
+use crate::token::TokenType;
+use crate::tokenizer::Tokenizer;
+
+/// The default number of programs checked by each generator.
+const DEFAULT_ITERATIONS: usize = 300;
+
+/// The default size of the stack of a thread spawned by Rust, which the test threads may not have, e.g., if `RUST_MIN_STACK` is set.
+const DEFAULT_STACK_SIZE: usize = 2 * 1024 * 1024;
+
+/// Words which must not appear in generated programs:
+/// loops may never terminate, `input` would wait for stdin, and `print` would write to the terminal running the tests.
+const EXCLUDED_WORDS: [&str; 4] = ["for", "input", "print", "while"];
+
+/// Hand-written programs covering the grammar, which are mutated to produce programs that are 'almost' valid.
+const CORPUS: [&str; 6] = [
+    "var a = [1, 2, 3]\na[1] = \"x\"\nappend(a, {1: true, \"k\": null})\nremove(a, 0)\nvar b = sort([3, 1, 2])",
+    "func f(x, y) {\n    if (x > y) {\n        return x - y\n    } else if (x == y) {\n        return 0\n    }\n    return f(y, x)\n}\nvar r = f(5, 2) * 3 % 4",
+    "var d = {\"a\": [1, {2: \"b\"}], 3.5: -1}\nd[\"a\"][1][2] = \"c\"\nremove(d, 3.5)\nvar n = size(d) + size(\"abc\")",
+    "var s = \"hello\"\ns[0] = \"j\"\nvar t = to_string(to_number(\"12.5\") / 2) + s[1]\nvar u = !(true and false or !false)",
+    "func fact(n) {\n    if (n <= 1) { return 1 }\n    return n * fact(n - 1)\n}\nvar x = fact(10) >= 1000 == true  # a comment",
+    "{\n    var a = 1\n    {\n        var a = \"shadow\"\n        a = a + \"ed\"\n    }\n    a = -a\n}\nbreak\nreturn 5",
+];
+
+/// A small, seedable pseudo-random number generator (xorshift64*), so that no external crate is needed.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Constructs a new generator. The state of xorshift must never be 0.
+    fn new(seed: u64) -> Self {
+        Self { state: (seed ^ 0x9E37_79B9_7F4A_7C15) | 1 }
+    }
+
+    /// Returns the next pseudo-random number.
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a pseudo-random number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Returns a pseudo-randomly chosen element of `options`.
+    fn choose<'a>(&mut self, options: &[&'a str]) -> &'a str {
+        options[self.below(options.len())]
+    }
+}
+
+/// Generates a string of random characters, including ones which are not part of the language.
+fn random_characters(rng: &mut Rng) -> String {
+    const CHARACTERS: &str = "abfgx_019.+-*/%=!<>&|()[]{},:;\"'#\n\t é€";
+    let characters: Vec<char> = CHARACTERS.chars().collect();
+    (0..rng.below(200)).map(|_| characters[rng.below(characters.len())]).collect()
+}
+
+/// Generates a random sequence of valid tokens, which will rarely form a valid program.
+fn random_tokens(rng: &mut Rng) -> String {
+    const LEXEMES: [&str; 46] = [
+        "and", "break", "else", "false", "func", "if", "null", "or", "return", "true", "var",
+        "a", "b", "f", "append", "remove", "size", "sort", "to_number", "to_string",
+        "0", "1", "2.5", "\"\"", "\"ab\"",
+        "+", "-", "*", "/", "%", "=", "==", "!", "!=", "<", "<=", ">", ">=",
+        "(", ")", "[", "]", "{", "}", ",", ":",
+    ];
+    let mut source = String::new();
+    for _ in 0..rng.below(60) {
+        source.push_str(rng.choose(&LEXEMES));
+        source.push_str(if rng.below(5) == 0 { "\n" } else { " " });
+    }
+    source
+}
+
+/// Generates a random program following the grammar, so that it is likely to get past the parser and exercise the interpreter.
+fn random_program(rng: &mut Rng) -> String {
+    let mut source = String::new();
+    for _ in 0..rng.below(8) {
+        source.push_str(&random_statement(rng, 3));
+        source.push('\n');
+    }
+    source
+}
+
+/// Generates a random statement, nested at most `depth` levels deep.
+fn random_statement(rng: &mut Rng, depth: usize) -> String {
+    let choice = if depth == 0 { rng.below(4) } else { rng.below(8) };
+    match choice {
+        0 => format!("var {} = {}", random_name(rng), random_expression(rng, depth)),
+        1 => format!("{} = {}", random_expression(rng, depth), random_expression(rng, depth)),
+        2 => format!("{}({}, {})", rng.choose(&["append", "remove"]), random_name(rng), random_expression(rng, depth)),
+        3 => format!("return {}", random_expression(rng, depth)),
+        4 => format!(
+            "if ({}) {{\n{}\n}} else {{\n{}\n}}",
+            random_expression(rng, depth - 1), random_statement(rng, depth - 1), random_statement(rng, depth - 1),
+        ),
+        5 => format!("func {}(a, b) {{\n{}\n{}\n}}", random_name(rng), random_statement(rng, depth - 1), random_statement(rng, depth - 1)),
+        6 => format!("{{\n{}\nbreak\n}}", random_statement(rng, depth - 1)),
+        _ => random_expression(rng, depth),
+    }
+}
+
+/// Generates a random expression, nested at most `depth` levels deep.
+fn random_expression(rng: &mut Rng, depth: usize) -> String {
+    const LITERALS: [&str; 10] = ["0", "1", "-3", "2.5", "1000000", "\"\"", "\"abc\"", "true", "false", "null"];
+    const OPERATORS: [&str; 13] = ["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "and", "or"];
+
+    let choice = if depth == 0 { rng.below(2) } else { rng.below(9) };
+    match choice {
+        0 => String::from(rng.choose(&LITERALS)),
+        1 => random_name(rng),
+        2 => format!("[{}, {}]", random_expression(rng, depth - 1), random_expression(rng, depth - 1)),
+        3 => format!("{{{}: {}}}", random_expression(rng, depth - 1), random_expression(rng, depth - 1)),
+        4 => format!("{} {} {}", random_expression(rng, depth - 1), rng.choose(&OPERATORS), random_expression(rng, depth - 1)),
+        5 => format!("{}{}", rng.choose(&["!", "-"]), random_expression(rng, depth - 1)),
+        6 => format!("({})", random_expression(rng, depth - 1)),
+        7 => format!("{}[{}]", random_expression(rng, depth - 1), random_expression(rng, depth - 1)),
+        _ => format!("{}({})", random_name(rng), random_expression(rng, depth - 1)),
+    }
+}
+
+/// Returns a random name, which is either a user-defined variable or function, or a built-in function.
+fn random_name(rng: &mut Rng) -> String {
+    String::from(rng.choose(&["a", "b", "f", "g", "size", "sort", "to_number", "to_string"]))
+}
+
+/// Mutates a program from the corpus by inserting, deleting, replacing and duplicating characters.
+fn mutated_corpus(rng: &mut Rng) -> String {
+    let mut source: Vec<char> = CORPUS[rng.below(CORPUS.len())].chars().collect();
+    let extra = random_characters(rng);
+    let extra: Vec<char> = extra.chars().collect();
+
+    for _ in 0..1 + rng.below(5) {
+        let index = rng.below(source.len() + 1);
+        match rng.below(4) {
+            0 if !extra.is_empty() => source.insert(index, extra[rng.below(extra.len())]),
+            1 if index < source.len() => {
+                let end = (index + 1 + rng.below(10)).min(source.len());
+                source.drain(index..end);
+            },
+            2 if index < source.len() && !extra.is_empty() => source[index] = extra[rng.below(extra.len())],
+            _ => {
+                let end = (index + rng.below(20)).min(source.len());
+                let copy: Vec<char> = source[index..end].to_vec();
+                source.splice(index..index, copy);
+            },
+        }
+    }
+    source.into_iter().collect()
+}
+
+/// Runs the source code through every stage of the interpreter, which must not panic.
+fn run_pipeline(source: &str) {
+    let (tokens, errors) = Tokenizer::new(source).tokenize();
+    let ast = Parser::new(tokens).parse();
+
+    // The linter parses `allow` comments from the source code, so it is run even if there were errors.
+    let mut linter = Linter::new(source);
+    if let (true, Ok(ast)) = (errors.is_empty(), ast) {
+        linter.lint(&ast);
+        // Runtime errors are reported to stdout, which the test harness captures.
+        let _ = Interpreter::new().interpret(ast);
+    }
+}
+
+/// Checks that formatting the source code, if it is valid, is idempotent and changes nothing but whitespace.
+fn check_formatting(source: &str) {
+    let Ok(formatted) = formatter::format(source) else {
+        return;
+    };
+    assert_eq!(formatter::format(&formatted), Ok(formatted.clone()), "formatting is not idempotent for this program:\n{}", source);
+
+    let lexemes = |source: &str| -> Vec<(TokenType, String)> {
+        let (tokens, _) = Tokenizer::with_comments(source).tokenize();
+        tokens.into_iter().map(|token| (token.type_, token.lexeme.trim_end().to_string())).collect()
+    };
+    assert_eq!(lexemes(&formatted), lexemes(source), "formatting changed the tokens of this program:\n{}", source);
+}
+
+/// Runs `f` on a thread with the default stack size of 2 MiB, like a thread of a program embedding the interpreter,
+/// whose default limits have to keep it within that stack. A panic in `f` is propagated to the calling test.
+fn on_default_stack<F: FnOnce() + Send + 'static>(f: F) {
+    let handle = thread::Builder::new().stack_size(DEFAULT_STACK_SIZE).spawn(f).expect("failed to spawn a thread");
+    if let Err(e) = handle.join() {
+        panic::resume_unwind(e);
+    }
+}
+
+/// Returns the number of programs to check with each generator.
+fn iterations() -> usize {
+    env::var("NEA_FUZZ_ITERATIONS").ok().and_then(|n| n.parse().ok()).unwrap_or(DEFAULT_ITERATIONS)
+}
+
+/// Runs programs from `generator`, seeded with `seed`, reporting the first program which causes a panic.
+fn fuzz(generator: fn(&mut Rng) -> String, seed: u64) {
+    let iterations = iterations();
+    let seed = env::var("NEA_FUZZ_SEED").ok().and_then(|n| n.parse().ok()).unwrap_or(0) ^ seed;
+    let mut rng = Rng::new(seed);
+
+    on_default_stack(move || {
+        for _ in 0..iterations {
+            let source = generator(&mut rng);
+            if EXCLUDED_WORDS.iter().any(|word| source.contains(word)) {
+                continue;
+            }
+            if panic::catch_unwind(|| run_pipeline(&source)).is_err() {
+                panic!("the interpreter panicked on this program:\n{}", source);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_formatting, fuzz, iterations, mutated_corpus, on_default_stack, random_characters, random_program, random_tokens, run_pipeline, Rng, CORPUS};
+
+    #[test]
+    fn characters() {
+        fuzz(random_characters, 1);
+    }
+
+    #[test]
+    fn tokens() {
+        fuzz(random_tokens, 2);
+    }
+
+    #[test]
+    fn programs() {
+        fuzz(random_program, 3);
+    }
+
+    #[test]
+    fn mutations() {
+        fuzz(mutated_corpus, 4);
+    }
+
+    #[test]
+    fn deep_recursion() {
+        // A runaway recursion has to raise an error rather than overflow the stack.
+        on_default_stack(|| run_pipeline("func f(n) {\n    return f(n + 1) + 1\n}\nf(0)"));
+    }
+
+    #[test]
+    fn regressions() {
+        // Programs which used to panic.
+        for source in [
+            "var s = \"abc\"\ns[100000000000000000000] = \"d\"",  // The end of the index range overflowed.
+            "var d = {\"é€\": 1}\nd[\"é€\"] = 2",  // Hashing non-ASCII strings.
+            "break\nreturn 1",
+        ] {
+            on_default_stack(move || run_pipeline(source));
+        }
+    }
+
+    #[test]
+    fn deep_nesting() {
+        // The parser limits nesting, but programs just within the limit have to be handled by every stage too.
+        fuzz(|rng| {
+            let depth = rng.below(crate::parser::MAX_NESTING_DEPTH + 20);
+            let (open, close) = [("(", ")"), ("[", "]"), ("{1: ", "}"), ("-", ""), ("if (true) {", "}")][rng.below(5)];
+            format!("{}1{}", open.repeat(depth), close.repeat(depth))
+        }, 5);
+    }
+
+    #[test]
+    fn formatting() {
+        for source in CORPUS {
+            check_formatting(source);
+        }
+        let mut rng = Rng::new(6);
+        for _ in 0..iterations() {
+            check_formatting(&random_program(&mut rng));
+            check_formatting(&mutated_corpus(&mut rng));
+        }
+    }
+}
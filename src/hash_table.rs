@@ -11,14 +11,16 @@ const MAX_LOAD_FACTOR_NUMERATOR: usize = 3;  // Numerator of the maximum load fa
 const MAX_LOAD_FACTOR_DENOMINATOR: usize = 4;  // Denominator of the maximum load factor before a rehash is required (3/4).
 const HASH_FIRST_N: usize = 300;  // Number of elements to hash to keep constant time operation.
 
-// A key-value pair in the hash table.
+/// A key-value pair in a hash table, or in a dictionary literal in the syntax tree.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct KeyValue<T> {
     pub key: T,
     pub value: T,
 }
 
-/// A hash table.
+/// A hash table, which holds the key-value pairs of a dictionary (see `Value::Dictionary`).
+/// Any value can be a key except dictionaries and functions, which cannot be hashed.
+/// The methods which can raise errors take the line to report them on, which can be 0 outside a script.
 #[derive(Clone)]
 pub struct HashTable {
     array: Vec<Vec<KeyValue<Value>>>,  // The internal array of the hash table.
@@ -26,6 +28,12 @@ pub struct HashTable {
     current_num_buckets: usize,  // The current number of buckets in the table.
 }
 
+impl Default for HashTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HashTable {
     /// Initialises a new instance of `HashTable`.
     pub fn new() -> Self {
@@ -36,7 +44,7 @@ impl HashTable {
         }
     }
 
-    /// Returns the value associated with `key`.
+    /// Returns the value associated with `key`, or a `KeyError` if there is none.
     pub fn get(&self, key: &Value, line: usize) -> Result<&Value, ErrorType> {
         // Calculate the bucket number of the key.
         let bucket_number = self.get_bucket_number(key, line)?;
@@ -62,21 +70,19 @@ impl HashTable {
     }
 
     /// Inserts a key-value pair to the table if the key does not already exist; otherwise, updates the existing pair with the new value.
-    pub fn insert(&mut self, key: &Value, value: &Value, line: usize) -> Result<(), ErrorType> {
+    /// The key and value are moved into the table rather than cloned, as they may be deeply nested.
+    pub fn insert(&mut self, key: Value, value: Value, line: usize) -> Result<(), ErrorType> {
         // Calculate the bucket number of the key.
-        let bucket_number = self.get_bucket_number(key, line)?;
+        let bucket_number = self.get_bucket_number(&key, line)?;
 
         // Iterate through the bucket.
-        if let Some(key_value) = self.array[bucket_number].iter_mut().find(|key_value| key_value.key == key.clone()) {
+        if let Some(key_value) = self.array[bucket_number].iter_mut().find(|key_value| key_value.key == key) {
             // If a `key_value` is found such that `key_value.key == key`, then update `key_value.value` to `value`.
-            key_value.value = value.clone();
+            key_value.value = value;
         } else {
             // Otherwise, we are adding a new entry.
             self.entries += 1;  // Increment the number of entries in the table.
-            self.array[bucket_number].push(KeyValue {  // Push the new key-value pair into the bucket.
-                key: key.clone(),
-                value: value.clone()
-            });
+            self.array[bucket_number].push(KeyValue { key, value });  // Push the new key-value pair into the bucket.
         }
         
         // Check if the table needs rehashing.
@@ -85,7 +91,7 @@ impl HashTable {
         Ok(())
     }
 
-    /// Removes a key-value pair from the table.
+    /// Removes a key-value pair from the table, or raises a `KeyError` if there is none.
     pub fn remove(&mut self, key: &Value, line: usize) -> Result<(), ErrorType> {
         // Calculate the bucket number of the key.
         let bucket_number = self.get_bucket_number(key, line)?;
@@ -107,23 +113,26 @@ impl HashTable {
         self.entries
     }
 
+    /// Returns an iterator over the keys and values in the table, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Value, &Value)> {
+        self.array.iter().flatten().map(|key_value| (&key_value.key, &key_value.value))
+    }
+
     /// Checks the load factor of the table and performs rehashing if required.
     fn check_load(&mut self, line: usize) -> Result<(), ErrorType> {
         if self.current_num_buckets < MAX_NUM_BUCKETS && self.entries * MAX_LOAD_FACTOR_DENOMINATOR > self.current_num_buckets * MAX_LOAD_FACTOR_NUMERATOR {
             // If `current_capacity` is less than the maximum capacity and greater than the maximum load factor, perform rehashing.
 
-            // Make a copy of the entries in the table.
-            let copy = self.flatten();
-
             // Double the current capacity of the table.
             self.current_num_buckets <<= 1;
 
-            // Repopulate the internal array with `current_capacity` number of empty buckets.
-            self.array = vec![Vec::new(); self.current_num_buckets];
+            // Take the entries out of the table, and repopulate the internal array with `current_capacity` number of empty buckets.
+            let entries = std::mem::replace(&mut self.array, vec![Vec::new(); self.current_num_buckets]);
+            self.entries = 0;
 
-            // For each entry in the saved table, re-insert it in the new table.
-            for entry in copy.iter() {
-                self.insert(&entry.key, &entry.value, line)?;
+            // For each entry taken out, re-insert it in the new table.
+            for entry in entries.into_iter().flatten() {
+                self.insert(entry.key, entry.value, line)?;
             }
         }
         Ok(())
@@ -209,7 +218,7 @@ fn hash(key: &Value, mut elements_left: usize, line: usize) -> Result<(usize, us
             // Hashing dictionaries in constant time will involve more sophisticated techniques.
             Err(ErrorType::CannotHashDictionary { line })
         },
-        Value::Function {..} | Value::BuiltinFunction(..) => {
+        Value::Function {..} | Value::NativeFunction(..) => {
             // It is tricky to hash functions as the comparison of two functions is not set in stone.
             // So we raise a descriptive error instead.
             Err(ErrorType::CannotHashFunction { line })
@@ -217,22 +226,23 @@ fn hash(key: &Value, mut elements_left: usize, line: usize) -> Result<(usize, us
         Value::Null => Ok((3, elements_left - 1)),
         Value::Number(x) => {
             // We will discard the 12 least significant bits to mask floating point inaccuracy.
-            let mut binary: usize = (x.to_bits() >> 12).try_into().unwrap();
-            binary %= MAX_CALC;
+            // The remainder is taken before converting so that the conversion is lossless on every platform.
+            let mut binary = ((x.to_bits() >> 12) % MAX_CALC as u64) as usize;
 
             // The 'Knuth Variant on Division' (https://www.cs.hmc.edu/~geoff/classes/hmc.cs070.200101/homework10/hashfuncs.html)
             binary = (binary * (binary + 3)) % MAX_CALC;
-            Ok((binary as usize, elements_left - 1))
+            Ok((binary, elements_left - 1))
         },
         Value::String_(s) => {
             // Similar to arrays, we use the `djb2` algorithm.
             let mut hash_value = 5381;
-            let mut index = 0;
 
-            while elements_left > 0 && index < s.chars().count() {
-                hash_value = (((hash_value << 5) + hash_value) + s.chars().nth(index).unwrap() as usize) % MAX_CALC;
+            for c in s.chars() {
+                if elements_left == 0 {
+                    break;
+                }
+                hash_value = (((hash_value << 5) + hash_value) + c as usize) % MAX_CALC;
                 elements_left -= 1;
-                index += 1;
             }
 
             Ok((hash_value, elements_left))
@@ -249,28 +259,41 @@ mod tests {
     #[test]
     fn insert_and_get() {
         let mut dict = HashTable::new();
-        assert!(dict.insert(&Value::Number(5.0), &Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict.insert(Value::Number(5.0), Value::String_("hello".to_string()), 1).is_ok());
         assert_eq!(dict.get(&Value::Number(5.0), 1), Ok(&Value::String_("hello".to_string())));
     }
 
     #[test]
     fn insert_duplicate_and_get() {
         let mut dict = HashTable::new();
-        assert!(dict.insert(&Value::Number(5.0), &Value::String_("hello".to_string()), 1).is_ok());
-        assert!(dict.insert(&Value::Number(5.0), &Value::String_("hi".to_string()), 1).is_ok());
+        assert!(dict.insert(Value::Number(5.0), Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict.insert(Value::Number(5.0), Value::String_("hi".to_string()), 1).is_ok());
         assert_eq!(dict.get(&Value::Number(5.0), 1), Ok(&Value::String_("hi".to_string())));
     }
 
     #[test]
     fn insert_remove_size() {
         let mut dict = HashTable::new();
-        assert!(dict.insert(&Value::Number(5.0), &Value::String_("hello".to_string()), 1).is_ok());
-        assert!(dict.insert(&Value::String_("key1".to_string()), &Value::String_("hi".to_string()), 1).is_ok());
+        assert!(dict.insert(Value::Number(5.0), Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict.insert(Value::String_("key1".to_string()), Value::String_("hi".to_string()), 1).is_ok());
         assert_eq!(dict.size(), 2);
 
         assert!(dict.remove(&Value::Number(5.0), 1).is_ok());
         assert_eq!(dict.size(), 1)
     }
+
+    #[test]
+    fn rehash_keeps_entries() {
+        // Enough entries to make the table rehash more than once.
+        let mut dict = HashTable::new();
+        for i in 0..100 {
+            assert!(dict.insert(Value::Number(i as f64), Value::Number((i * 2) as f64), 1).is_ok());
+        }
+        assert_eq!(dict.size(), 100);
+        assert_eq!(dict.iter().count(), 100);
+        assert!(dict.iter().all(|(key, value)| matches!((key, value), (Value::Number(k), Value::Number(v)) if *v == k * 2.0)));
+        assert_eq!(dict.get(&Value::Number(42.0), 1), Ok(&Value::Number(84.0)));
+    }
     
     #[test]
     fn key_error() {
@@ -289,13 +312,13 @@ mod tests {
     fn equality() {
         let mut dict1 = HashTable::new();
         let mut dict2 = HashTable::new();
-        assert!(dict1.insert(&Value::Number(5.0), &Value::String_("hello".to_string()), 1).is_ok());
-        assert!(dict1.insert(&Value::Number(6.0), &Value::String_("hello".to_string()), 1).is_ok());
-        assert!(dict1.insert(&Value::Number(7.0), &Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict1.insert(Value::Number(5.0), Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict1.insert(Value::Number(6.0), Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict1.insert(Value::Number(7.0), Value::String_("hello".to_string()), 1).is_ok());
 
-        assert!(dict2.insert(&Value::Number(7.0), &Value::String_("hello".to_string()), 1).is_ok());
-        assert!(dict2.insert(&Value::Number(6.0), &Value::String_("hello".to_string()), 1).is_ok());
-        assert!(dict2.insert(&Value::Number(5.0), &Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict2.insert(Value::Number(7.0), Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict2.insert(Value::Number(6.0), Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict2.insert(Value::Number(5.0), Value::String_("hello".to_string()), 1).is_ok());
 
         assert_eq!(dict1, dict2);
     }
@@ -304,13 +327,13 @@ mod tests {
     fn inequality() {
         let mut dict1 = HashTable::new();
         let mut dict2 = HashTable::new();
-        assert!(dict1.insert(&Value::Number(5.0), &Value::String_("hello".to_string()), 1).is_ok());
-        assert!(dict1.insert(&Value::Number(6.0), &Value::String_("hello".to_string()), 1).is_ok());
-        assert!(dict1.insert(&Value::Number(7.0), &Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict1.insert(Value::Number(5.0), Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict1.insert(Value::Number(6.0), Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict1.insert(Value::Number(7.0), Value::String_("hello".to_string()), 1).is_ok());
 
-        assert!(dict2.insert(&Value::Number(8.0), &Value::String_("hello".to_string()), 1).is_ok());
-        assert!(dict2.insert(&Value::Number(6.0), &Value::String_("hello".to_string()), 1).is_ok());
-        assert!(dict2.insert(&Value::Number(5.0), &Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict2.insert(Value::Number(8.0), Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict2.insert(Value::Number(6.0), Value::String_("hello".to_string()), 1).is_ok());
+        assert!(dict2.insert(Value::Number(5.0), Value::String_("hello".to_string()), 1).is_ok());
 
         assert_ne!(dict1, dict2);
     }
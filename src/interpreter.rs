@@ -1,16 +1,90 @@
-use std::io::{Write, self};
+use std::io::{BufRead, Write, self};
 
 use crate::environment::{Environment, Pointer, self};
 use crate::expr::{Expr, ExprType};
-use crate::token::{TokenType, Literal};
-use crate::error::{ErrorType, self};
+use crate::token::{Token, TokenType, Literal};
+use crate::error::{self, Error, ErrorType};
+use crate::parser::Parser;
 use crate::stmt::{Stmt, StmtType};
-use crate::value::{Value, BuiltinFunction};
+use crate::stream;
+use crate::tokenizer::Tokenizer;
+use crate::value::{Arity, NativeFunction, Value};
 use crate::hash_table::HashTable;
 
+/// The maximum number of nested function calls, so that a runaway recursion in a script fails quickly.
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+/// The default maximum number of bytes of extra stack which the interpreter may allocate (see `set_max_stack_usage()`).
+/// The interpreter is recursive, so when the stack of the thread runs low, it continues on a new segment of stack instead of overflowing it.
+/// This is far more than `MAX_CALL_DEPTH` calls need, so it is only a safety net for scripts which also nest deeply within each call.
+pub const DEFAULT_MAX_STACK_USAGE: usize = 256 * 1024 * 1024;
+
+/// The number of bytes of stack left below which the interpreter continues on a new segment of stack.
+const STACK_RED_ZONE: usize = 128 * 1024;
+
+/// The size of each new segment of stack the interpreter continues on, in bytes.
+const STACK_SEGMENT_SIZE: usize = 2 * 1024 * 1024;
+
+/// The name of the frame of the top level of a script, which is not in any function.
+pub const SCRIPT_FRAME: &str = "<script>";
+
+/// Receives events from the interpreter as it executes a script, e.g., so that the debugger can pause it.
+/// It must be `Send`, like the interpreter holding it.
+pub trait Hook: Send {
+    /// Called before each statement is executed, except blocks, which only group other statements.
+    /// The hook can inspect and use the interpreter, e.g., to evaluate expressions, but it is not called again until it returns.
+    /// Returning an error stops execution with that error.
+    fn before_statement(&mut self, interpreter: &mut Interpreter, stmt: &Stmt) -> Result<(), ErrorType>;
+
+    /// Called after each statement which `before_statement()` was called for has been executed, with its result.
+    /// Errors include the ones used for control flow, e.g., by `return`.
+    fn after_statement(&mut self, _interpreter: &Interpreter, _stmt: &Stmt, _result: &Result<(), ErrorType>) {}
+
+    /// Called when the condition of an `if` statement or a `while` loop has been evaluated to a Boolean, with its value.
+    fn condition_evaluated(&mut self, _interpreter: &Interpreter, _stmt: &Stmt, _value: bool) {}
+
+    /// Called when a user-defined function is called, once its frame has been pushed and its parameters declared.
+    fn function_entered(&mut self, _interpreter: &Interpreter, _name: &str, _parameters: &[String], _arguments: &[Value]) {}
+
+    /// Called when a call to a user-defined function ends, before its frame is popped, with its return value or the error which ended it.
+    fn function_exited(&mut self, _interpreter: &Interpreter, _name: &str, _result: &Result<Value, ErrorType>) {}
+
+    /// Called when a variable is declared by a `var` statement. `old` is the value it was already declared with in the same scope, if any.
+    fn variable_declared(&mut self, _interpreter: &Interpreter, _name: &str, _old: Option<&Value>, _value: &Value) {}
+
+    /// Called when the value of a variable is changed by an assignment, `append()` or `remove()`, with the whole value before and after,
+    /// e.g., the whole array when one of its elements is assigned to.
+    fn variable_updated(&mut self, _interpreter: &Interpreter, _name: &str, _old: &Value, _new: &Value) {}
+}
+
+/// A function call being executed, or the top level of the script. Together, these make up the call stack.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub function: String,  // The name the function was called by, or `SCRIPT_FRAME` for the top level.
+    pub line: usize,  // The line of the statement being executed in the frame.
+    pub scope: usize,  // The index of the first scope of the frame in the environment.
+}
+
 /// Recursively traverses the abstract syntax tree, executes statements, and evaluates expressions.
 pub struct Interpreter {
     environment: Environment,
+    call_depth: usize,  // The number of function calls currently being executed, including native functions.
+    stack_segments: usize,  // The number of extra segments of stack currently in use (see `nested()`).
+    max_stack_usage: usize,  // The number of bytes of extra stack which execution may allocate before raising an error.
+    echo: bool,  // Whether to print the values of top-level expression statements.
+    args: Vec<String>,  // The command-line arguments given to the script, returned by `args()`.
+    frames: Vec<Frame>,  // The call stack, starting with the top level of the script.
+    hook: Option<Box<dyn Hook>>,  // Called before each statement, if set. It is taken out while it runs.
+    output: Box<dyn Write + Send>,  // Where `print` statements, echoed values and the prompts of `input()` are written.
+    input: Box<dyn BufRead + Send>,  // Where `input()` reads lines from.
+    error_output: Box<dyn Write + Send>,  // Where `report_errors()` writes error reports.
+    native_lines: Vec<usize>,  // The line of the native function call being executed, followed by the lines of its arguments.
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
@@ -18,24 +92,366 @@ impl Interpreter {
     pub fn new() -> Self {
         Self {
             environment: Environment::new(),
+            call_depth: 0,
+            stack_segments: 0,
+            max_stack_usage: DEFAULT_MAX_STACK_USAGE,
+            echo: false,
+            args: Vec::new(),
+            frames: vec![Frame { function: String::from(SCRIPT_FRAME), line: 0, scope: 0 }],
+            hook: None,
+            output: stream::stdout(),
+            input: stream::stdin(),
+            error_output: stream::stdout(),
+            native_lines: Vec::new(),
         }
     }
 
+    /// Sets whether to print the value of each top-level expression statement. This is used by the REPL,
+    /// so that, e.g., `1 + 2` prints `3` without needing a `print` statement.
+    pub fn set_echo(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    /// Sets the command-line arguments which the script can access with `args()`.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    /// Sets the maximum number of bytes of extra stack which the interpreter may allocate once the stack of the thread runs low,
+    /// beyond which scripts raise a `StackOverflow` error. The default is `DEFAULT_MAX_STACK_USAGE`; scripts normally hit `MAX_CALL_DEPTH` first.
+    pub fn set_max_stack_usage(&mut self, max_stack_usage: usize) {
+        self.max_stack_usage = max_stack_usage;
+    }
+
+    /// Sets the hook which is called before each statement is executed (see `Hook`).
+    pub fn set_hook(&mut self, hook: Box<dyn Hook>) {
+        self.hook = Some(hook);
+    }
+
+    /// Sets where the output of the script is written instead of stdout, e.g., so that it does not mix with the messages of the DAP server.
+    /// See `stream` for an in-memory buffer and a stream which passes the output to a function.
+    pub fn set_output(&mut self, output: Box<dyn Write + Send>) {
+        self.output = output;
+    }
+
+    /// Sets where `input()` reads lines from instead of stdin. See `stream` for in-memory input and a stream which gets lines from a function.
+    pub fn set_input(&mut self, input: Box<dyn BufRead + Send>) {
+        self.input = input;
+    }
+
+    /// Sets where `report_errors()` writes error reports instead of stdout.
+    pub fn set_error_output(&mut self, error_output: Box<dyn Write + Send>) {
+        self.error_output = error_output;
+    }
+
+    /// Returns the stream error reports are written to, e.g., to report errors in another format.
+    pub fn error_output(&mut self) -> &mut dyn Write {
+        self.error_output.as_mut()
+    }
+
+    /// Writes a report of the errors to the error output, with a hint for each error which has one.
+    pub fn report_errors(&mut self, errors: &[ErrorType]) {
+        error::write_errors(self.error_output.as_mut(), errors);
+    }
+
     /// Executes statements in the given abstract syntax tree.
-    pub fn interpret(&mut self, ast: Vec<Stmt>) {
+    /// Execution stops at the first error, which is returned so that the caller can report it.
+    pub fn interpret(&mut self, ast: Vec<Stmt>) -> Result<(), ErrorType> {
         for stmt in &ast {
             // Iterate through each statement.
-            if let Err(e) = self.execute(stmt) {
-                // If an error occurred in the execution of the statement, report the error and terminate execution.
-                error::report_errors(&[e]);
-                return;
+            let result = match &stmt.stmt_type {
+                StmtType::Expression { expression } if self.echo => self.echo(expression),
+                _ => self.execute(stmt),
+            };
+            // If an error occurred in the execution of the statement, terminate execution.
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Declares a global native function, which scripts call like any other function, replacing any global already declared under `name`.
+    /// `code` is given the evaluated arguments, of which there are as many as `arity` accepts; otherwise the call raises an error
+    /// without calling it. Errors it returns are raised in the script, with `call_line()` and `argument_line()` giving lines to report.
+    pub fn register_function(&mut self, name: &str, arity: Arity, code: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, ErrorType> + Send + Sync + 'static) {
+        self.environment.declare_global(name, Value::NativeFunction(NativeFunction::new(name, arity, code)));
+    }
+
+    /// Returns the line of the native function call being executed, or 0 if it was not called from a script (see `call()`).
+    pub fn call_line(&self) -> usize {
+        self.native_lines.first().copied().unwrap_or(0)
+    }
+
+    /// Returns the line of the argument at `index` of the native function call being executed, or the line of the call
+    /// if there is no such argument.
+    pub fn argument_line(&self, index: usize) -> usize {
+        self.native_lines.get(index + 1).copied().unwrap_or(self.call_line())
+    }
+
+    /// Returns the command-line arguments given to the script (see `set_args()`).
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Writes text to the output of the script without a newline, e.g., for native functions which print.
+    /// The output is flushed, so that, e.g., a prompt is shown before reading input.
+    pub fn write_output(&mut self, text: &str) -> io::Result<()> {
+        write!(self.output, "{}", text)?;
+        self.output.flush()
+    }
+
+    /// Reads a line from the input of the script (see `set_input()`), including its line ending.
+    /// Returns `None` if there is no more input to read.
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        let read = self.input.read_line(&mut line)?;
+        Ok(if read == 0 { None } else { Some(line) })
+    }
+
+    /// Tokenizes, parses and runs the source code, and returns the value of its last statement if it is an expression,
+    /// e.g., `3` for `var x = 1 x + 2`, or `null` otherwise. Declarations are kept, so later calls can use them.
+    /// Nothing is run if the source code has syntax errors.
+    pub fn eval_str(&mut self, source: &str) -> Result<Value, Error> {
+        let (tokens, mut errors) = Tokenizer::new(source).tokenize();
+        match Parser::new(tokens).parse() {
+            Ok(..) if !errors.is_empty() => Err(Error::Syntax(errors)),
+            Ok(ast) => {
+                let mut value = Value::Null;
+                for stmt in &ast {
+                    // As with echoing, the value of an expression statement is kept rather than discarded.
+                    value = match &stmt.stmt_type {
+                        StmtType::Expression { expression } => self.evaluate(expression).map_err(Error::Runtime)?,
+                        _ => {
+                            self.execute(stmt).map_err(Error::Runtime)?;
+                            Value::Null
+                        },
+                    };
+                }
+                Ok(value)
+            },
+            Err(parser_errors) => {
+                errors.extend(parser_errors);
+                Err(Error::Syntax(errors))
+            },
+        }
+    }
+
+    /// Returns the value of the global variable or function declared under `name`, if there is one.
+    /// Built-in functions are global too.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.environment.get_global(name)
+    }
+
+    /// Declares a global variable, replacing any global already declared under `name`, e.g., to give a script its inputs.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.environment.declare_global(name, value);
+    }
+
+    /// Returns every name declared in the environment, including the built-in functions.
+    pub fn names(&self) -> Vec<String> {
+        self.environment.names()
+    }
+
+    /// Returns every name declared in the environment with its value, including the built-in functions.
+    pub fn bindings(&self) -> Vec<(String, Value)> {
+        self.environment.bindings()
+    }
+
+    /// Returns the call stack, starting with the top level of the script and ending with the function call being executed.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Returns the number of scopes in the environment, including the base scope.
+    pub fn scope_count(&self) -> usize {
+        self.environment.depth()
+    }
+
+    /// Returns the names declared in the scope at `index` with their values, sorted by name. The base scope has index 0.
+    pub fn scope_bindings(&self, index: usize) -> Vec<(String, Value)> {
+        self.environment.scope(index)
+    }
+
+    /// Calls the function declared under `name` with the given arguments, and returns its return value.
+    /// Native functions which change their first argument, e.g., `append()`, return the changed value, as there is no variable to assign it to.
+    /// This can also be used by native functions and hooks while a script is running.
+    pub fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Value, ErrorType> {
+        // There is no line in the source code to report errors on, so line 0 is used.
+        match self.environment.get(name.to_string(), 0)? {
+            Value::Function { parameters, body } => {
+                if arguments.len() != parameters.len() {
+                    return Err(ErrorType::ArgParamNumberMismatch { arg_number: arguments.len(), param_number: parameters.len(), line: 0 });
+                }
+                self.call_function(name, &parameters, &body, arguments, 0)
+            },
+            Value::NativeFunction(function) => {
+                if !function.arity.accepts(arguments.len()) {
+                    return Err(arity_mismatch(function.arity, arguments.len(), 0));
+                }
+                self.run_native(&function, arguments, Vec::new())
+            },
+            _ => Err(ErrorType::CannotCallName { line: 0 }),
+        }
+    }
+
+    /// Executes the body of a user-defined function with the parameters set to the evaluated arguments, and returns its return value.
+    /// `name` is the name the function was called by, which is shown in the call stack.
+    /// The numbers of parameters and arguments must already have been checked to match.
+    fn call_function(&mut self, name: &str, parameters: &[String], body: &Stmt, arguments: Vec<Value>, line: usize) -> Result<Value, ErrorType> {
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(ErrorType::StackOverflow { line });
+        }
+
+        // Create a new variable scope for the arguments and function execution, and a new frame on the call stack.
+        self.frames.push(Frame { function: name.to_string(), line, scope: self.environment.depth() });
+        self.environment.new_scope();
+
+        // Declare the arguments in the new scope.
+        for (parameter, arg) in parameters.iter().zip(arguments.iter()) {
+            self.environment.declare(parameter.clone(), arg);
+        }
+
+        self.notify(|hook, interpreter| hook.function_entered(interpreter, name, parameters, &arguments));
+
+        // Execute function body.
+        self.call_depth += 1;
+        let exec_result = self.execute(body);
+        self.call_depth -= 1;
+
+        let result = match exec_result {
+            // If the function execution did not raise any error, evaluate the call to `Null` (no return statement used in function).
+            Ok(()) => Ok(Value::Null),
+            // If the execution ended because of a raised `ThrownReturn` error, then evaluate the call to the given return vale.
+            Err(ErrorType::ThrownReturn { value, line: _ }) => Ok(value),
+            // If another error occurred, continue to bubble up the error.
+            Err(e) => Err(e),
+        };
+        self.notify(|hook, interpreter| hook.function_exited(interpreter, name, &result));
+
+        // Exit scope and frame.
+        self.frames.pop();
+        self.environment.exit_scope(line)?;
+        result
+    }
+
+    /// Evaluates the arguments of a call to a native function, calls it, and returns its return value.
+    /// If the function changes its first argument, the return value is also assigned to the variable or element given as that argument.
+    fn call_native(&mut self, function: &NativeFunction, arguments: &[Expr], line: usize) -> Result<Value, ErrorType> {
+        if !function.arity.accepts(arguments.len()) {
+            return Err(arity_mismatch(function.arity, arguments.len(), line));
+        }
+
+        // Iterate through the arguments and evaluate each. The pointer to the first argument is constructed straight after it is evaluated,
+        // so that the indices in it are evaluated before the other arguments.
+        let mut args_eval = Vec::new();
+        let mut pointer = None;
+        for (i, arg) in arguments.iter().enumerate() {
+            args_eval.push(self.evaluate(arg)?);
+            if i == 0 && function.updates_argument {
+                pointer = Some(self.construct_pointer(arg, arg.line)?);
             }
         }
+
+        let lines = std::iter::once(line).chain(arguments.iter().map(|arg| arg.line)).collect();
+        let value = self.run_native(function, args_eval, lines)?;
+        if let Some(pointer) = pointer {
+            self.update_variable(&pointer, &value, line)?;
+        }
+        Ok(value)
+    }
+
+    /// Runs the code of a native function with the evaluated arguments. `lines` are returned by `call_line()` and `argument_line()` while it runs,
+    /// and the previous lines are restored afterwards, as the function may call other native functions through the interpreter.
+    /// Native functions count towards `MAX_CALL_DEPTH`, as they can call back into the interpreter (see `call()`).
+    fn run_native(&mut self, function: &NativeFunction, arguments: Vec<Value>, lines: Vec<usize>) -> Result<Value, ErrorType> {
+        let line = lines.first().copied().unwrap_or(0);
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(ErrorType::StackOverflow { line });
+        }
+        let previous_lines = std::mem::replace(&mut self.native_lines, lines);
+        self.call_depth += 1;
+        // Native functions which only call other native functions never execute a statement, so the stack is grown here too.
+        let result = self.nested(line, |interpreter| (function.code)(interpreter, arguments));
+        self.call_depth -= 1;
+        self.native_lines = previous_lines;
+        result
+    }
+
+    /// Passes an event to the hook, if there is one. As in `execute()`, it is taken out while it runs.
+    fn notify(&mut self, event: impl FnOnce(&mut dyn Hook, &Interpreter)) {
+        if let Some(mut hook) = self.hook.take() {
+            event(hook.as_mut(), self);
+            self.hook = Some(hook);
+        }
+    }
+
+    /// Declares a variable for a `var` statement, and tells the hook.
+    fn declare_variable(&mut self, name: &str, value: &Value) {
+        let old = self.environment.declare(name.to_string(), value);
+        self.notify(|hook, interpreter| hook.variable_declared(interpreter, name, old.as_ref(), value));
+    }
+
+    /// Updates the value at the pointer, and tells the hook how the value of the whole variable changed.
+    fn update_variable(&mut self, pointer: &Pointer, value: &Value, line: usize) -> Result<(), ErrorType> {
+        if self.hook.is_none() {
+            // The values before and after are only needed by the hook, so they are not cloned without one.
+            return self.environment.update(pointer, value, line);
+        }
+        let old = self.environment.get(pointer.name.clone(), line);
+        self.environment.update(pointer, value, line)?;
+        if let (Ok(old), Ok(new)) = (old, self.environment.get(pointer.name.clone(), line)) {
+            self.notify(|hook, interpreter| hook.variable_updated(interpreter, &pointer.name, &old, &new));
+        }
+        Ok(())
+    }
+
+    /// Evaluates a top-level expression statement and prints its value in a debug-style representation (see `Value::repr()`).
+    /// Assignments and `null` values are not printed, as, e.g., `a = 5` or calling a function with no `return` would just be noise.
+    fn echo(&mut self, expression: &Expr) -> Result<(), ErrorType> {
+        let value = self.evaluate(expression)?;
+        if matches!(expression.expr_type, ExprType::Assignment {..}) || value == Value::Null {
+            return Ok(());
+        }
+        writeln!(self.output, "{}", value.repr()).map_err(|e| ErrorType::IoError { message: e.to_string(), line: expression.line })
     }
 
-    /// Executes the given statement.
+    /// Executes the given statement, calling the hook before and after it unless it is a block.
     fn execute(&mut self, stmt: &Stmt) -> Result<(), ErrorType> {
+        if matches!(stmt.stmt_type, StmtType::Block {..}) {
+            return self.nested(stmt.line, |interpreter| interpreter.execute_statement(stmt));
+        }
+
+        if let Some(frame) = self.frames.last_mut() {
+            frame.line = stmt.line;
+        }
+        if let Some(mut hook) = self.hook.take() {
+            // The hook is taken out while it runs, so that statements it executes itself, e.g., in functions it calls, do not call it again.
+            let result = hook.before_statement(self, stmt);
+            self.hook = Some(hook);
+            result?;
+        }
+        let result = self.nested(stmt.line, |interpreter| interpreter.execute_statement(stmt));
+        self.notify(|hook, interpreter| hook.after_statement(interpreter, stmt, &result));
+        result
+    }
+
+    /// Executes the statement itself.
+    fn execute_statement(&mut self, stmt: &Stmt) -> Result<(), ErrorType> {
         match &stmt.stmt_type {
+            StmtType::Assert { condition, message, condition_text } => {
+                match self.evaluate(condition)? {
+                    Value::Bool(true) => Ok(()),
+                    Value::Bool(false) => {
+                        // The message is only evaluated if the assertion fails, so it can be expensive to compute.
+                        let message = match message {
+                            Some(message) => Some(self.evaluate(message)?.to_string()),
+                            None => None,
+                        };
+                        Err(ErrorType::AssertionFailed { condition: condition_text.clone(), message, line: stmt.line })
+                    },
+                    _ => Err(ErrorType::AssertConditionNotBoolean { line: condition.line }),
+                }
+            },
+
             StmtType::Block { body } => {
                 // Create a new variable scope.
                 self.environment.new_scope();
@@ -44,14 +460,13 @@ impl Interpreter {
                 for block_stmt in body {
                     // We cannot just use `?` here as it will exit this function call right away and not call `exit_scope()`.
                     if let Err(e) = self.execute(block_stmt) {
-                        self.environment.exit_scope();
+                        self.environment.exit_scope(stmt.line)?;
                         return Err(e);
                     }
                 }
                 
                 // Exit and remove the scope.
-                self.environment.exit_scope();
-                Ok(())
+                self.environment.exit_scope(stmt.line)
             },
 
             StmtType::Break => {
@@ -67,7 +482,7 @@ impl Interpreter {
                 Ok(())
             },
 
-            StmtType::Function { name, parameters, body } => {
+            StmtType::Function { name, parameters, body, .. } => {
                 // Declare the function as a new `Value` in the environment.
                 self.environment.declare(name.clone(), &Value::Function {
                     parameters: parameters.clone(),
@@ -76,10 +491,11 @@ impl Interpreter {
                 Ok(())
             },
 
-            StmtType::If { condition, then_body, else_body } => {
+            StmtType::If { condition, then_body, else_body, .. } => {
                 match self.evaluate(condition)? {
                     Value::Bool(condition_bool) => {
                         // If the condition evaluated to a Boolean value...
+                        self.notify(|hook, interpreter| hook.condition_evaluated(interpreter, stmt, condition_bool));
                         if condition_bool {
                             // and the condition is `true`, execute the `then` body.
                             self.execute(then_body.as_ref())?;
@@ -98,8 +514,9 @@ impl Interpreter {
 
             StmtType::Print { expression } => {
                 // Print the evaluated expression.
-                println!("{}", self.evaluate(expression)?);
-                Ok(())
+                // `println!()` would panic if stdout was closed, so the error is raised instead.
+                let value = self.evaluate(expression)?;
+                writeln!(self.output, "{}", value).map_err(|e| ErrorType::IoError { message: e.to_string(), line: stmt.line })
             },
 
             StmtType::Return { expression } => {
@@ -111,16 +528,16 @@ impl Interpreter {
                 })
             },
 
-            StmtType::VarDecl { name, value } => {
+            StmtType::VarDecl { name, value, .. } => {
                 // Evaluate the value.
                 let value_eval = &self.evaluate(value)?;
 
                 // Declare the new variable in the environment.
-                self.environment.declare(name.clone(), value_eval);
+                self.declare_variable(name, value_eval);
                 Ok(())
             },
             
-            StmtType::While { condition, body } => {
+            StmtType::While { condition, body, .. } => {
                 loop {
                     let continue_ = match self.evaluate(condition)? {
                         // If `condition` evaluated to a Boolean value, set `continue_` to the result of that.
@@ -128,6 +545,7 @@ impl Interpreter {
                         // Otherwise, it cannot be used as the condition for a loop, so raise a specific error.
                         _ => return Err(ErrorType::LoopConditionNotBoolean { line: stmt.line }),
                     };
+                    self.notify(|hook, interpreter| hook.condition_evaluated(interpreter, stmt, continue_));
 
                     // If the `condition` evaluated to `false`, stop the loop.
                     if !continue_ {
@@ -148,8 +566,13 @@ impl Interpreter {
         }
     }
 
-    /// Evaluates the given expression.
-    fn evaluate(&mut self, expr: &Expr) -> Result<Value, ErrorType> {
+    /// Evaluates the given expression, e.g., for a debugger to show the value of an expression while a script is paused.
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, ErrorType> {
+        self.nested(expr.line, |interpreter| interpreter.evaluate_expression(expr))
+    }
+
+    /// Evaluates the expression itself.
+    fn evaluate_expression(&mut self, expr: &Expr) -> Result<Value, ErrorType> {
         match &expr.expr_type {
             ExprType::Array { elements } => {
                 // Evaluate each expression in the array to a `Value`, and collect those in an array.
@@ -164,7 +587,7 @@ impl Interpreter {
                 // Construct the pointer to the target.
                 match self.construct_pointer(target, expr.line) {
                     // Use the pointer to update the value in the environment.
-                    Ok(pointer) => self.environment.update(&pointer, &value_eval, expr.line)?,
+                    Ok(pointer) => self.update_variable(&pointer, &value_eval, expr.line)?,
                     // If an error occurred (invalid assignment target), continue to bubble it up.
                     Err(e) => return Err(e),
                 };
@@ -187,7 +610,7 @@ impl Interpreter {
                                 match operator.type_ {
                                     TokenType::Or => Ok(Value::Bool(*left_bool || *right_bool)),
                                     TokenType::And => Ok(Value::Bool(*left_bool && *right_bool)),
-                                    _ => unreachable!(),
+                                    _ => Err(invalid_operator(operator)),
                                 }
                             },
                             (_, _) => {
@@ -217,7 +640,7 @@ impl Interpreter {
                                     TokenType::Less => Ok(Value::Bool(left_num < right_num)),
                                     TokenType::GreaterEqual => Ok(Value::Bool(left_num >= right_num)),
                                     TokenType::LessEqual => Ok(Value::Bool(left_num <= right_num)),
-                                    _ => unreachable!(),
+                                    _ => Err(invalid_operator(operator)),
                                 }
                             },
                             (Value::String_(left_str), Value::String_(right_str)) => {
@@ -226,7 +649,7 @@ impl Interpreter {
                                     TokenType::Less => Ok(Value::Bool(left_str < right_str)),
                                     TokenType::GreaterEqual => Ok(Value::Bool(left_str >= right_str)),
                                     TokenType::LessEqual => Ok(Value::Bool(left_str <= right_str)),
-                                    _ => unreachable!(),
+                                    _ => Err(invalid_operator(operator)),
                                 }
                             },
                             (_, _) => {
@@ -271,7 +694,7 @@ impl Interpreter {
                                         }
                                     },
                                     TokenType::Percent => Ok(Value::Number(left_num % right_num)),
-                                    _ => unreachable!(),
+                                    _ => Err(invalid_operator(operator)),
                                 }
                             },
                             (_, _) => {
@@ -284,8 +707,8 @@ impl Interpreter {
                             }
                         }
                     },
-                    // This is unreachable because the parser only builds Binary expressions with certain tokens.
-                    _ => unreachable!(),
+                    // This should be unreachable because the parser only builds Binary expressions with certain tokens.
+                    _ => Err(invalid_operator(operator)),
                 }
             },
 
@@ -311,210 +734,17 @@ impl Interpreter {
                             args_eval.push(self.evaluate(arg)?);
                         }
 
-                        // Create a new variable scope for the arguments and function execution.
-                        self.environment.new_scope();
-
-                        // Declare the arguments in the new scope.
-                        for i in 0..arguments.len() {
-                            self.environment.declare(parameters[i].clone(), &args_eval[i]);
-                        }
-
-                        // Execute function body.
-                        let exec_result = self.execute(&body);
-
-                        // Exit scope.
-                        self.environment.exit_scope();
-
-                        match exec_result {
-                            // If the function execution did not raise any error, evaluate the call to `Null` (no return statement used in function).
-                            Ok(()) => Ok(Value::Null),
-                            // If the execution ended because of a raised `ThrownReturn` error, then evaluate the call to the given return vale.
-                            Err(ErrorType::ThrownReturn { value, line: _ }) => Ok(value),
-                            // If another error occurred, continue to bubble up the error.
-                            Err(e) => Err(e),
-                        }
+                        // Functions are values, so they do not have names of their own. The call stack shows the name they were called by, if any.
+                        let name = match &callee.expr_type {
+                            ExprType::Variable { name } => name.as_str(),
+                            _ => "<function>",
+                        };
+                        self.call_function(name, &parameters, &body, args_eval, expr.line)
                     },
 
-                    Value::BuiltinFunction(function) => {
-                        // Built-in functions.
-                        match function {
-                            BuiltinFunction::Append => {
-                                // We want two arguments: the target array, and the value to append.
-                                if arguments.len() != 2 {
-                                    // If the number of given arguments was not 2, raise an error, providing the number of arguments received.
-                                    return Err(ErrorType::ArgParamNumberMismatch { arg_number: arguments.len(), param_number: 2, line: expr.line });
-                                }
+                    Value::NativeFunction(function) => self.call_native(&function, arguments, expr.line),
 
-                                let target = &arguments[0];
-                                let target_eval = self.evaluate(target)?;
-                                let pointer = self.construct_pointer(target, target.line)?;
-
-                                let value_eval = self.evaluate(&arguments[1])?;
-
-                                if let Value::Array(mut array) = target_eval {
-                                    // If `target` is an Array variant of Value, append and update the environment using the pointer.
-                                    array.push(value_eval);
-                                    self.environment.update(&pointer, &Value::Array(array.clone()), expr.line)?;
-
-                                    // Evaluate to changed array.
-                                    Ok(Value::Array(array))
-                                } else {
-                                    // We can only append to arrays.
-                                    // If `target` is not an Array variant, raise an `ExpectedTypeError` and provide the received type.
-                                    Err(ErrorType::ExpectedType { expected: String::from("Array"), got: target_eval.type_to_string(), line: target.line })
-                                }
-                            },
-                            BuiltinFunction::Input => {
-                                // We want one argument: the input prompt.
-                                if arguments.len() != 1 {
-                                    return Err(ErrorType::ArgParamNumberMismatch { arg_number: arguments.len(), param_number: 1, line: expr.line });
-                                }
-
-                                // Print the input prompt.
-                                let prompt = self.evaluate(&arguments[0])?;
-                                print!("{}", prompt);
-                                io::stdout().flush().expect("Error: flush failed");
-
-                                // Read input.
-                                let mut input = String::new();
-                                io::stdin().read_line(&mut input).expect("Error: something went wrong while reading input");
-                                input = input.trim().to_string();
-
-                                // Evaluate to input string.
-                                Ok(Value::String_(input))
-                            },
-                            BuiltinFunction::Remove => {
-                                // We want two arguments: the target array/dictionary, and the index/key to remove.
-                                if arguments.len() != 2 {
-                                    return Err(ErrorType::ArgParamNumberMismatch { arg_number: arguments.len(), param_number: 2, line: expr.line });
-                                }
-
-                                let target = &arguments[0];
-                                let target_eval = self.evaluate(target)?;
-                                let pointer = self.construct_pointer(target, target.line)?;
-                                
-                                let key_eval = self.evaluate(&arguments[1])?;
-
-                                match target_eval {
-                                    Value::Array(mut array) => {
-                                        // If `target` is an Array variant...
-
-                                        // Convert `key` into a `usize` index.
-                                        let index = environment::index_value_to_usize(&key_eval, arguments[1].line)?;
-
-                                        if index < array.len() {
-                                            // If `index` is not out-of-bounds, perform the removal.
-                                            // Note `usize` is guaranteed to be non-negative.
-                                            array.remove(index);
-                                        } else {
-                                            // Otherwise, raise an out-of-bounds error.
-                                            return Err(ErrorType::OutOfBoundsIndex { index, line: arguments[1].line });
-                                        }
-                                        
-                                        // Update the environment with the new array.
-                                        self.environment.update(&pointer, &Value::Array(array.clone()), expr.line)?;
-
-                                        // Evaluate to the changed array.
-                                        Ok(Value::Array(array))
-                                    },
-                                    Value::Dictionary(mut dict) => {
-                                        // If `target` is a Dictionary variant, we can let `HashTable` take care of the removal.
-                                        dict.remove(&key_eval, expr.line)?;
-
-                                        // Update the environment with the new dictionary.
-                                        self.environment.update(&pointer, &Value::Dictionary(dict.clone()), expr.line)?;
-                                        
-                                        // Evaluate to the changed dictionary.
-                                        Ok(Value::Dictionary(dict))
-                                    },
-                                    // If it is not an Array or a Dictionary variant, then raise an `ExpectedTypeError`, providing the received type.
-                                    _ => Err(ErrorType::ExpectedType { expected: String::from("Array or Dictionary"), got: target_eval.type_to_string(), line: target.line }),
-                                }
-                            },
-                            BuiltinFunction::Size => {
-                                // We want one argument: the target array/dictionary/string.
-                                if arguments.len() != 1 {
-                                    return Err(ErrorType::ArgParamNumberMismatch { arg_number: arguments.len(), param_number: 1, line: expr.line });
-                                }
-
-                                let value = self.evaluate(&arguments[0])?;
-                                match value {
-                                    Value::Array(array) => Ok(Value::Number(array.len() as f64)),
-                                    Value::Dictionary(dict) => Ok(Value::Number(dict.size() as f64)),
-                                    Value::String_(s) => Ok(Value::Number(s.len() as f64)),
-                                    // If `value` did not evaluate to an Array, a Dictionary, or a String, raise an error.
-                                    _ => Err(ErrorType::ExpectedType { expected: String::from("Array, Dictionary, or String"), got: value.type_to_string(), line: expr.line }),
-                                }
-                            },
-                            BuiltinFunction::Sort => {
-                                // We want one argument: the array to be sorted.
-                                if arguments.len() != 1 {
-                                    return Err(ErrorType::ArgParamNumberMismatch { arg_number: arguments.len(), param_number: 1, line: expr.line });
-                                }
-
-                                let value = self.evaluate(&arguments[0])?; 
-                                match value {
-                                    // If given argument is an array, sort using the `merge_sort` function defined below.
-                                    Value::Array(array) => Ok(Value::Array(merge_sort(&array, arguments[0].line)?)),
-
-                                    // We cannot sort objects which are not arrays, so raise an error.
-                                    _ => Err(ErrorType::ExpectedType { expected: String::from("Array"), got: value.type_to_string(), line: expr.line }),
-                                }
-                            },
-                            BuiltinFunction::ToNumber => {
-                                // We want one argument: the Boolean/number/string to be converted.
-                                if arguments.len() != 1 {
-                                    return Err(ErrorType::ArgParamNumberMismatch { arg_number: arguments.len(), param_number: 1, line: expr.line });
-                                }
-
-                                let value = self.evaluate(&arguments[0])?;
-                                match value {
-                                    Value::Bool(b) => {
-                                        match b {
-                                            true => Ok(Value::Number(1.0)),
-                                            false => Ok(Value::Number(0.0)),
-                                        }
-                                    },
-                                    Value::Number(..) => Ok(value),
-                                    Value::String_(s) => {
-                                        match s.parse::<f64>() {
-                                            Ok(x) => Ok(Value::Number(x)),
-                                            // If something went wrong during Rust's conversion, raise an error.
-                                            Err(..) => Err(ErrorType::CannotConvertToNumber { line: expr.line }),
-                                        }
-                                    },
-
-                                    // We can only construct numeric representations of Booleans, numbers, and strings.
-                                    // If not given one of these, raise an error.
-                                    _ => Err(ErrorType::ExpectedType { expected: String::from("Boolean, Number or String"), got: value.type_to_string(), line: expr.line }),
-                                }
-                            },
-                            BuiltinFunction::ToString => {
-                                // We want one argument: the Boolean/number/string to be converted.
-                                if arguments.len() != 1 {
-                                    return Err(ErrorType::ArgParamNumberMismatch { arg_number: arguments.len(), param_number: 1, line: expr.line });
-                                }
-
-                                let value = self.evaluate(&arguments[0])?;
-                                match value {
-                                    Value::Bool(b) => {
-                                        match b {
-                                            true => Ok(Value::String_(String::from("true"))),
-                                            false => Ok(Value::String_(String::from("false"))),
-                                        }
-                                    },
-                                    Value::Number(x) => Ok(Value::String_(x.to_string())),
-                                    Value::String_(..) => Ok(value),
-
-                                    // We can only construct string representations of Booleans, numbers, and strings.
-                                    // If not given one of these, raise an error.
-                                    _ => Err(ErrorType::ExpectedType { expected: String::from("Boolean, Number or String"), got: value.type_to_string(), line: expr.line }),
-                                }
-                            },
-                        }
-                    },
-
-                    // If the evaluated `function` was not a `Function` or a `BuiltinFunction` variant, then we cannot 'call' it.
+                    // If the evaluated `function` was not a `Function` or a `NativeFunction` variant, then we cannot 'call' it.
                     // So raise an error.
                     _ => Err(ErrorType::CannotCallName { line: callee.line })
                 }
@@ -531,7 +761,7 @@ impl Interpreter {
                     let value_eval = self.evaluate(&key_value.value)?;
 
                     // Insert the evaluated key and value into the table.
-                    hash_table.insert(&key_eval, &value_eval, expr.line)?;
+                    hash_table.insert(key_eval, value_eval, expr.line)?;
                 }
                 Ok(Value::Dictionary(hash_table))
             },
@@ -622,8 +852,8 @@ impl Interpreter {
                             })
                         }
                     },
-                    // The parser only builds `Unary` expressions with `Bang` or `Minus`, so this is unreachable.
-                    _ => unreachable!(),
+                    // The parser only builds `Unary` expressions with `Bang` or `Minus`, so this should be unreachable.
+                    _ => Err(invalid_operator(operator)),
                 }
             },
 
@@ -634,6 +864,22 @@ impl Interpreter {
         }
     }
 
+    /// Runs `run`, which recurses into the interpreter, on a new segment of stack if the current one is running low.
+    /// Raises a `StackOverflow` error instead if that would allocate more extra stack than the interpreter may (see `set_max_stack_usage()`).
+    fn nested<T>(&mut self, line: usize, run: impl FnOnce(&mut Self) -> Result<T, ErrorType>) -> Result<T, ErrorType> {
+        // If the size of the stack is unknown, e.g., on some platforms, it cannot be grown either.
+        if stacker::remaining_stack().is_none_or(|remaining| remaining >= STACK_RED_ZONE) {
+            return run(self);
+        }
+        if (self.stack_segments + 1) * STACK_SEGMENT_SIZE > self.max_stack_usage {
+            return Err(ErrorType::StackOverflow { line });
+        }
+        self.stack_segments += 1;
+        let result = stacker::grow(STACK_SEGMENT_SIZE, || run(self));
+        self.stack_segments -= 1;
+        result
+    }
+
     /// Constructs a Pointer object given an expression.
     fn construct_pointer(&mut self, element: &Expr, line: usize) -> Result<Pointer, ErrorType> {
         match &element.expr_type {
@@ -661,75 +907,16 @@ impl Interpreter {
     }
 }
 
-/// Sorts the given array using merge sort.
-fn merge_sort(array_to_sort: &Vec<Value>, line: usize) -> Result<Vec<Value>, ErrorType> {
-    let n = array_to_sort.len();
-
-    // Base case.
-    if n <= 1 {
-        return Ok(array_to_sort.to_vec());
-    }
-
-    // Recursive case.
-
-    // Recursively sort the left and right halves of the array.
-    let left = merge_sort(&array_to_sort[0..n/2].to_vec(), line)?;
-    let right = merge_sort(&array_to_sort[n/2..].to_vec(), line)?;
-
-    // Merge the two sorted arrays using two pointers.
-    let mut left_index = 0;
-    let mut right_index = 0;
-    let mut merged = Vec::new();
-
-    while left_index < left.len() && right_index < right.len() {
-        match (&left[left_index], &right[right_index]) {
-            // Append the 'lower' of the two to the merged array, and advance the respective pointer.
-            (Value::Number(left_num), Value::Number(right_num)) => {
-                if left_num < right_num {
-                    merged.push(left[left_index].clone());
-                    left_index += 1;
-                } else {
-                    merged.push(right[right_index].clone());
-                    right_index += 1;
-                }
-            },
-            (Value::String_(left_str), Value::String_(right_str)) => {
-                if left_str < right_str {
-                    merged.push(left[left_index].clone());
-                    left_index += 1;
-                } else {
-                    merged.push(right[right_index].clone());
-                    right_index += 1;
-                }
-            },
-
-            // We only support comparisons between numbers and between strings.
-            (_, _) => {
-                return Err(ErrorType::BinaryTypeError {
-                    expected: String::from("Number or String"),
-                    got_left: left[left_index].type_to_string(),
-                    got_right: right[right_index].type_to_string(),
-                    line,
-                });
-            }
-        }
-    }
-
-    // Only one of `left` and `right` will have any elements left.
-    // Append the remainder to the merged array.
-    if left_index < left.len() {
-        while left_index < left.len() {
-            merged.push(left[left_index].clone());
-            left_index += 1;
-        }
-    }
-
-    if right_index < right.len() {
-        while right_index < right.len() {
-            merged.push(right[right_index].clone());
-            right_index += 1;
-        }
+/// Constructs the error raised when a native function is called with a number of arguments its arity does not accept.
+fn arity_mismatch(arity: Arity, arg_number: usize, line: usize) -> ErrorType {
+    match arity {
+        Arity::Exact(param_number) => ErrorType::ArgParamNumberMismatch { arg_number, param_number, line },
+        accepted => ErrorType::ArgNumberNotAccepted { arg_number, accepted, line },
     }
+}
 
-    Ok(merged)
+/// Constructs the error raised when an expression contains an operator the interpreter does not expect.
+/// The parser never builds such expressions, so this indicates a bug rather than a mistake in the script.
+fn invalid_operator(operator: &Token) -> ErrorType {
+    ErrorType::InternalError { message: format!("unexpected operator `{}`", operator.lexeme), line: operator.line }
 }
@@ -0,0 +1,469 @@
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// The maximum depth of nested arrays and objects when parsing, so that malicious input cannot overflow the stack.
+const MAX_PARSE_DEPTH: usize = 128;
+
+/// A JSON value, used for machine-readable input and output, e.g., diagnostics and the messages of the DAP server for editors and other tools.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),  // The members are kept in the order they were added, so that the output is predictable.
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self {
+        Self::Number(n as f64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Self::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Self::String(s)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(option: Option<T>) -> Self {
+        option.map_or(Self::Null, Into::into)
+    }
+}
+
+impl Json {
+    /// Parses a JSON value which makes up the whole of `text`, returning a message describing the problem if it is not valid JSON.
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = JsonParser { chars: text.chars().peekable(), depth: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("unexpected `{}` after the value", c)),
+        }
+    }
+
+    /// Returns the value of the member called `key`, if this is an object with such a member.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Self::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Returns the string, if this is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as a `usize`, if this is a non-negative integer.
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Self::Number(x) if *x >= 0.0 && x.fract() == 0.0 && *x <= usize::MAX as f64 => Some(*x as usize),
+            _ => None,
+        }
+    }
+
+    /// Returns the Boolean, if this is a Boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements, if this is an array.
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Self::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as JSON indented by two spaces per level, with one element or member per line.
+    /// Unlike the compact form (see `Display`), this is easy to read and to compare with `diff`.
+    pub fn pretty(&self) -> String {
+        let mut output = String::new();
+        // Writing to a `String` cannot fail.
+        let _ = self.write_pretty(&mut output, 0);
+        output
+    }
+
+    /// Writes the value as indented JSON, where `depth` is the indentation level of the line the value starts on.
+    fn write_pretty<W: fmt::Write>(&self, f: &mut W, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth + 1);
+        match self {
+            Self::Array(elements) if !elements.is_empty() => {
+                writeln!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    write!(f, "{}", indent)?;
+                    element.write_pretty(f, depth + 1)?;
+                    writeln!(f, "{}", if i + 1 < elements.len() { "," } else { "" })?;
+                }
+                write!(f, "{}]", "  ".repeat(depth))
+            },
+            Self::Object(members) if !members.is_empty() => {
+                writeln!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    write!(f, "{}{}: ", indent, Json::from(key.as_str()))?;
+                    value.write_pretty(f, depth + 1)?;
+                    writeln!(f, "{}", if i + 1 < members.len() { "," } else { "" })?;
+                }
+                write!(f, "{}}}", "  ".repeat(depth))
+            },
+            // Scalars and empty arrays and objects fit on one line.
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    /// Writes the value as compact JSON, i.e., on a single line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(b) => write!(f, "{}", b),
+            // JSON has no representation of infinity or NaN.
+            Self::Number(x) if !x.is_finite() => write!(f, "null"),
+            Self::Number(x) => write!(f, "{}", x),
+            Self::String(s) => write_string(f, s),
+            Self::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            },
+            Self::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+/// Parses JSON text character by character, with recursive descent for arrays and objects.
+struct JsonParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    depth: usize,  // The number of arrays and objects the parser is currently in.
+}
+
+impl JsonParser<'_> {
+    /// Skips any whitespace before the next token.
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| matches!(c, ' ' | '\t' | '\n' | '\r')).is_some() {}
+    }
+
+    /// Consumes the next character if it is `expected`, after any whitespace, or returns an error otherwise.
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("expected `{}` but found `{}`", expected, c)),
+            None => Err(format!("expected `{}` but the text ended", expected)),
+        }
+    }
+
+    /// Parses any value.
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut word = String::new();
+                while let Some(c) = self.chars.next_if(char::is_ascii_alphabetic) {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "null" => Ok(Json::Null),
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    _ => Err(format!("unexpected `{}`", word)),
+                }
+            },
+            Some(c) => Err(format!("unexpected `{}`", c)),
+            None => Err(String::from("expected a value but the text ended")),
+        }
+    }
+
+    /// Parses an array or object with `parse`, limiting how deeply they can be nested.
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth >= MAX_PARSE_DEPTH {
+            return Err(format!("arrays and objects are nested more than {} levels deep", MAX_PARSE_DEPTH));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    /// Parses an array, starting at its `[`.
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&']').is_some() {
+            return Ok(Json::Array(elements));
+        }
+        loop {
+            elements.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => (),
+                Some(']') => return Ok(Json::Array(elements)),
+                _ => return Err(String::from("expected `,` or `]` after an element of an array")),
+            }
+        }
+    }
+
+    /// Parses an object, starting at its `{`.
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'}').is_some() {
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => (),
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(String::from("expected `,` or `}` after a member of an object")),
+            }
+        }
+    }
+
+    /// Parses a string literal, starting at its opening quote, and returns its value.
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.chars.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(String::from("invalid escape sequence in a string")),
+                    };
+                    s.push(c);
+                },
+                Some(c) if (c as u32) < 0x20 => return Err(String::from("control characters in strings have to be escaped")),
+                Some(c) => s.push(c),
+                None => return Err(String::from("a string was never closed")),
+            }
+        }
+    }
+
+    /// Parses the code units after `\u`, including the second `\uXXXX` of a surrogate pair, and returns the character.
+    /// Unpaired surrogates cannot be represented in Rust strings, so they are replaced with U+FFFD.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let first = self.hex_code_unit()?;
+        if !(0xD800..0xDC00).contains(&first) {
+            return Ok(char::from_u32(first).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+        if self.chars.next_if_eq(&'\\').is_none() || self.chars.next_if_eq(&'u').is_none() {
+            return Ok(char::REPLACEMENT_CHARACTER);
+        }
+        let second = self.hex_code_unit()?;
+        if !(0xDC00..0xE000).contains(&second) {
+            return Ok(char::REPLACEMENT_CHARACTER);
+        }
+        let code_point = 0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00);
+        Ok(char::from_u32(code_point).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    /// Parses four hexadecimal digits.
+    fn hex_code_unit(&mut self) -> Result<u32, String> {
+        let mut code_unit = 0;
+        for _ in 0..4 {
+            let digit = self.chars.next().and_then(|c| c.to_digit(16)).ok_or("expected four hexadecimal digits after `\\u`")?;
+            code_unit = code_unit * 16 + digit;
+        }
+        Ok(code_unit)
+    }
+
+    /// Parses a number. Rust's parsing of floats is more lenient than JSON, e.g., it accepts `1.`, which is allowed here too.
+    fn number(&mut self) -> Result<Json, String> {
+        let mut text = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+            text.push(c);
+        }
+        text.parse().map(Json::Number).map_err(|_| format!("invalid number `{}`", text))
+    }
+}
+
+/// Writes `s` as a JSON string literal, escaping quotes, backslashes and control characters.
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Reads the next message framed by a `Content-Length` header, as used by the DAP and LSP servers, or returns `None`
+/// if there are no more, e.g., the editor has closed stdin. Messages which are not valid JSON are skipped.
+pub fn read_message(input: &mut dyn BufRead) -> Option<Json> {
+    loop {
+        // The headers end with an empty line. `Content-Length` is the only one which matters.
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if input.read_line(&mut header).ok()? == 0 {
+                return None;
+            }
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("Content-Length") {
+                    length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+        // Without a length, the end of the message cannot be found, so nothing more can be read.
+        let mut body = vec![0; length?];
+        input.read_exact(&mut body).ok()?;
+
+        if let Some(message) = String::from_utf8(body).ok().and_then(|body| Json::parse(&body).ok()) {
+            return Some(message);
+        }
+    }
+}
+
+/// Writes a message framed by a `Content-Length` header. If the editor has gone, there is nobody to report the error to, so it is ignored.
+pub fn write_message(output: &mut dyn Write, message: &Json) {
+    let body = message.to_string();
+    let _ = write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let _ = output.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{read_message, write_message, Json};
+
+    #[test]
+    fn display() {
+        let json = Json::Object(vec![
+            (String::from("line"), Json::from(3)),
+            (String::from("hint"), Json::from(None::<String>)),
+            (String::from("ok"), Json::from(false)),
+            (String::from("x"), Json::Number(1.5)),
+            (String::from("y"), Json::Number(f64::NAN)),
+            (String::from("empty"), Json::Object(vec![])),
+        ]);
+        assert_eq!(json.to_string(), r#"{"line":3,"hint":null,"ok":false,"x":1.5,"y":null,"empty":{}}"#);
+    }
+
+    #[test]
+    fn pretty() {
+        let json = Json::Object(vec![
+            (String::from("a"), Json::Array(vec![Json::from(1), Json::Object(vec![(String::from("b"), Json::Null)])])),
+            (String::from("c"), Json::Array(vec![])),
+        ]);
+        assert_eq!(json.pretty(), "{\n  \"a\": [\n    1,\n    {\n      \"b\": null\n    }\n  ],\n  \"c\": []\n}");
+    }
+
+    #[test]
+    fn escape_strings() {
+        assert_eq!(Json::from("a \"b\"\\\n\t\u{1}é").to_string(), r#""a \"b\"\\\n\t\u0001é""#);
+    }
+
+    #[test]
+    fn parse() {
+        let text = r#" {"seq": 1, "args": ["a", -2.5e1, true, null, {}], "s": "\"\u00e9\ud83d\ude00\n"} "#;
+        let json = Json::parse(text).unwrap();
+        assert_eq!(json, Json::Object(vec![
+            (String::from("seq"), Json::from(1)),
+            (String::from("args"), Json::Array(vec![Json::from("a"), Json::Number(-25.0), Json::from(true), Json::Null, Json::Object(vec![])])),
+            (String::from("s"), Json::from("\"é😀\n")),
+        ]));
+        assert_eq!(json.get("seq").and_then(Json::as_usize), Some(1));
+        assert_eq!(json.get("args").and_then(Json::as_array).map(<[Json]>::len), Some(5));
+        assert_eq!(json.get("s").and_then(Json::as_str), Some("\"é😀\n"));
+        assert_eq!(json.get("missing"), None);
+        // Parsing the compact form gives back the same value.
+        assert_eq!(Json::parse(&json.to_string()), Ok(json));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Json::parse("").is_err());
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("\"abc").is_err());
+        assert!(Json::parse("nul").is_err());
+        assert!(Json::parse("1 2").is_err());
+        assert!(Json::parse(&"[".repeat(1000)).is_err());
+    }
+
+    #[test]
+    fn messages() {
+        let mut output = Vec::new();
+        write_message(&mut output, &Json::from("é"));
+        assert_eq!(String::from_utf8(output.clone()).unwrap(), "Content-Length: 4\r\n\r\n\"é\"");
+
+        // Invalid messages are skipped, and the input ends with an incomplete one.
+        output.extend_from_slice(b"Content-Length: 3\r\n\r\n[1,Content-Length: 2\r\n\r\n{}Content-Length: 9\r\n\r\n[");
+        let mut input = Cursor::new(output);
+        assert_eq!(read_message(&mut input), Some(Json::from("é")));
+        assert_eq!(read_message(&mut input), Some(Json::Object(vec![])));
+        assert_eq!(read_message(&mut input), None);
+    }
+}
@@ -0,0 +1,80 @@
+//! The Nea language as a library, for embedding the interpreter in Rust programs.
+//!
+//! The types re-exported here are the stable API: [`Tokenizer`] and [`Parser`] turn source code into an abstract syntax tree,
+//! and an [`Interpreter`] runs it, producing [`Value`]s or reporting an [`ErrorType`]. Most programs only need
+//! [`Interpreter::eval_str()`], [`Interpreter::get_global()`], [`Interpreter::set_global()`] and [`Interpreter::call()`]:
+//!
+//! ```
+//! use nea::{Interpreter, Value};
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.set_global("limit", Value::Number(3.0));
+//! interpreter.eval_str("func triple(x) {\n    return x * limit\n}").unwrap();
+//! assert_eq!(interpreter.call("triple", vec![Value::Number(2.0)]), Ok(Value::Number(6.0)));
+//! assert_eq!(interpreter.eval_str("triple(5) + 1"), Ok(Value::Number(16.0)));
+//! ```
+//!
+//! Rust functions can be made callable from scripts with [`Interpreter::register_function()`]; the built-in functions work the same way.
+//! The output, input and error reports of scripts can be captured or provided with the streams in [`stream`].
+//! An [`Interpreter`] can be moved to another thread to run scripts there, so the functions, streams and hooks given to it must be `Send`.
+//! [`Value`] converts to and from Rust types with `From` and `TryFrom`, e.g., `Vec::<f64>::try_from(value)`,
+//! and with the `serde` feature it implements `Serialize` and `Deserialize`. Dictionaries are [`HashTable`]s, which can also be built and read directly.
+//!
+//! Programs which inspect scripts or follow their execution, e.g., debuggers, can use the lower-level API too:
+//! the [`Token`]s and syntax tree ([`Stmt`] and [`Expr`]) which [`Interpreter::interpret()`] and [`Interpreter::evaluate()`] run,
+//! and a [`Hook`], which is told about each statement as it runs and can inspect the call stack ([`Frame`]).
+//! [`ErrorType`] is `#[non_exhaustive]`, as new kinds of errors may be added.
+//!
+//! The interpreter is recursive, so it continues on a new segment of stack when the stack of the thread runs low, and scripts can be run
+//! on any thread. Runaway recursion raises an error rather than overflowing the stack (see [`Interpreter::set_max_stack_usage()`]).
+//!
+//! The binary, with its REPL and other tools, needs the `cli` feature, which is on by default.
+//! Programs which only embed the interpreter can turn it off with `default-features = false` to avoid its dependencies.
+
+// `ErrorType` carries `Value`s (e.g., the value of a `return` statement), so it is large by design.
+#![allow(clippy::result_large_err)]
+
+// The modules hidden from the documentation are public only for the `nea` binary and its tools; the items of them which are part of the API are re-exported below.
+// They are not part of the API, so their contents may change between versions.
+#[doc(hidden)]
+pub mod builtins;
+mod convert;
+mod environment;
+#[doc(hidden)]
+pub mod error;
+#[doc(hidden)]
+pub mod expr;
+#[doc(hidden)]
+pub mod formatter;
+mod hash_table;
+#[doc(hidden)]
+pub mod interpreter;
+#[doc(hidden)]
+pub mod linter;
+#[doc(hidden)]
+pub mod parser;
+#[cfg(feature = "serde")]
+mod serialize;
+#[doc(hidden)]
+pub mod stmt;
+pub mod stream;
+#[doc(hidden)]
+pub mod suggestion;
+#[doc(hidden)]
+pub mod token;
+#[doc(hidden)]
+pub mod tokenizer;
+#[doc(hidden)]
+pub mod value;
+#[doc(hidden)]
+pub mod warning;
+
+pub use error::{Error, ErrorType};
+pub use expr::{Expr, ExprType};
+pub use hash_table::{HashTable, KeyValue};
+pub use interpreter::{Frame, Hook, Interpreter};
+pub use parser::Parser;
+pub use stmt::{Doc, Stmt, StmtType};
+pub use token::{Literal, Token, TokenType};
+pub use tokenizer::Tokenizer;
+pub use value::{Arity, NativeFunction, Value};
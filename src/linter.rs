@@ -0,0 +1,394 @@
+use crate::builtins;
+use crate::expr::{Expr, ExprType};
+use crate::stmt::{Stmt, StmtType};
+use crate::token::Literal;
+use crate::warning::WarningType;
+
+/// The kinds of names which can be declared.
+enum DeclarationKind {
+    Variable,
+    Parameter {
+        function: String,  // The name of the function the parameter belongs to.
+    },
+    Function,
+}
+
+/// A name declared in the source code, and whether it has been used so far.
+struct Declaration {
+    name: String,
+    kind: DeclarationKind,
+    line: usize,
+    used: bool,
+}
+
+/// Statically checks the abstract syntax tree for likely mistakes, without executing it.
+pub struct Linter {
+    scopes: Vec<Vec<Declaration>>,  // Mirrors the scopes the interpreter creates; each contains the names declared so far.
+    warnings: Vec<WarningType>,  // The warnings found so far.
+    allowed_lines: Vec<(usize, String)>,  // The kinds of warnings suppressed on specific lines by `# allow(...)` comments.
+}
+
+impl Linter {
+    /// Initialises a new instance of `Linter` for the given source code string, reading any `# allow(...)` comments in it.
+    /// A comment on its own line suppresses the warnings on the line below it; a comment after code suppresses them on the same line.
+    /// For example, `# allow(unused_variable, shadowed_variable)`.
+    pub fn new(source: &str) -> Self {
+        let mut allowed_lines = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let Some((code, comment)) = split_comment(line) else {
+                continue;
+            };
+            let Some(names) = comment.trim().strip_prefix("allow(").and_then(|rest| rest.strip_suffix(')')) else {
+                continue;
+            };
+
+            // Line numbers start at 1, so the current line is `index + 1` and the line below is `index + 2`.
+            let target_line = if code.trim().is_empty() { index + 2 } else { index + 1 };
+            for name in names.split(',') {
+                allowed_lines.push((target_line, name.trim().to_string()));
+            }
+        }
+
+        Self {
+            scopes: Vec::new(),
+            warnings: Vec::new(),
+            allowed_lines,
+        }
+    }
+
+    /// The interface method which returns the warnings found in the abstract syntax tree.
+    pub fn lint(&mut self, ast: &[Stmt]) -> Vec<WarningType> {
+        self.warnings.clear();
+        self.block(ast);
+
+        // Sort the warnings in order of line so they can be read from top to bottom.
+        let mut warnings: Vec<WarningType> = self.warnings
+            .drain(..)
+            .filter(|warning| !self.allowed_lines.iter().any(|(line, name)| *line == warning.line() && name == warning.name()))
+            .collect();
+        warnings.sort_by_key(|warning| warning.line());
+        warnings
+    }
+
+    /// Checks a sequence of statements in a new scope.
+    fn block(&mut self, body: &[Stmt]) {
+        self.scopes.push(Vec::new());
+        self.statements(body);
+        self.exit_scope();
+    }
+
+    /// Checks a sequence of statements in the current scope.
+    fn statements(&mut self, body: &[Stmt]) {
+        // Function bodies are checked after the rest of the sequence, as they may use names declared after the function.
+        let mut functions: Vec<&Stmt> = Vec::new();
+
+        let mut terminated = false;  // Whether a `return` or `break` has been passed.
+        let mut reported_unreachable = false;
+        for stmt in body {
+            if terminated && !reported_unreachable {
+                // Only report the first unreachable statement, as the rest follow from it.
+                self.warnings.push(WarningType::UnreachableCode { line: stmt.line });
+                reported_unreachable = true;
+            }
+
+            self.statement(stmt, &mut functions);
+
+            if matches!(stmt.stmt_type, StmtType::Break | StmtType::Return {..}) {
+                terminated = true;
+            }
+        }
+
+        for function in functions {
+            if let StmtType::Function { name, parameters, body, .. } = &function.stmt_type {
+                // The parameters are declared in their own scope, as in the interpreter.
+                self.scopes.push(parameters.iter().map(|parameter| Declaration {
+                    name: parameter.clone(),
+                    kind: DeclarationKind::Parameter { function: name.clone() },
+                    line: function.line,
+                    used: false,
+                }).collect());
+                self.statement(body, &mut Vec::new());
+                self.exit_scope();
+            }
+        }
+    }
+
+    /// Checks an individual statement.
+    /// Function declarations are added to `functions` to have their bodies checked later.
+    fn statement<'a>(&mut self, stmt: &'a Stmt, functions: &mut Vec<&'a Stmt>) {
+        match &stmt.stmt_type {
+            StmtType::Assert { condition, message, .. } => {
+                self.condition(condition);
+                if let Some(message) = message {
+                    self.expression(message);
+                }
+            },
+            StmtType::Block { body } => self.block(body),
+            StmtType::Break => (),
+            StmtType::Expression { expression } => self.expression(expression),
+            StmtType::Function { name, .. } => {
+                self.declare(name, DeclarationKind::Function, stmt.line);
+                functions.push(stmt);
+            },
+            StmtType::If { condition, then_body, else_body, .. } => {
+                self.condition(condition);
+                self.statement(then_body, functions);
+                if let Some(else_) = else_body {
+                    self.statement(else_, functions);
+                }
+            },
+            StmtType::Print { expression } |
+            StmtType::Return { expression } => self.expression(expression),
+            StmtType::VarDecl { name, value, .. } => {
+                // The value is evaluated before the name is declared, so `var a = a` uses the outer `a`.
+                self.expression(value);
+
+                let declared_in_outer_scope = self.scopes
+                    .iter()
+                    .rev()
+                    .skip(1)
+                    .any(|scope| scope.iter().any(|declaration| &declaration.name == name));
+                let declared_in_current_scope = self.scopes
+                    .last()
+                    .is_some_and(|scope| scope.iter().any(|declaration| &declaration.name == name));
+                if declared_in_outer_scope && !declared_in_current_scope {
+                    self.warnings.push(WarningType::ShadowedVariable { name: name.clone(), line: stmt.line });
+                }
+
+                self.declare(name, DeclarationKind::Variable, stmt.line);
+            },
+            StmtType::While { condition, body, .. } => {
+                self.condition(condition);
+                self.statement(body, functions);
+            },
+        }
+    }
+
+    /// Checks the condition of an `if` or `assert` statement or a loop.
+    fn condition(&mut self, condition: &Expr) {
+        // Literals, arrays, and dictionaries always evaluate to the same type, so we know whether they are Boolean in advance.
+        let got = match &condition.expr_type {
+            ExprType::Literal { value: Literal::Number(..) } => Some("Number"),
+            ExprType::Literal { value: Literal::String_(..) } => Some("String"),
+            ExprType::Literal { value: Literal::Null } => Some("Null"),
+            ExprType::Array {..} => Some("Array"),
+            ExprType::Dictionary {..} => Some("Dictionary"),
+            _ => None,
+        };
+        if let Some(got) = got {
+            self.warnings.push(WarningType::NonBooleanCondition { got: String::from(got), line: condition.line });
+        }
+
+        self.expression(condition);
+    }
+
+    /// Checks an expression, marking the names it uses.
+    fn expression(&mut self, expr: &Expr) {
+        match &expr.expr_type {
+            ExprType::Array { elements } => {
+                for element in elements {
+                    self.expression(element);
+                }
+            },
+            ExprType::Assignment { target, value } => {
+                self.expression(value);
+                // Assigning to a variable does not count as using it, but assigning to one of its elements does.
+                if !matches!(target.expr_type, ExprType::Variable {..}) {
+                    self.expression(target);
+                }
+            },
+            ExprType::Binary { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            },
+            ExprType::Call { callee, arguments } => {
+                if let ExprType::Variable { name } = &callee.expr_type {
+                    self.check_builtin_call(name, arguments.len(), expr.line);
+                }
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            },
+            ExprType::Dictionary { elements } => {
+                for key_value in elements {
+                    self.expression(&key_value.key);
+                    self.expression(&key_value.value);
+                }
+            },
+            ExprType::Element { array, index } => {
+                self.expression(array);
+                self.expression(index);
+            },
+            ExprType::Grouping { expression } => self.expression(expression),
+            ExprType::Literal {..} => (),
+            ExprType::Unary { right, .. } => self.expression(right),
+            ExprType::Variable { name } => self.mark_used(name),
+        }
+    }
+
+    /// Checks the number of arguments given to a built-in function, unless `name` has been declared in the source code.
+    fn check_builtin_call(&mut self, name: &str, arg_number: usize, line: usize) {
+        if self.is_declared(name) {
+            return;
+        }
+        if let Some(builtin) = builtins::find(name) {
+            if !builtin.arity.accepts(arg_number) {
+                self.warnings.push(WarningType::BuiltinArgNumberMismatch {
+                    name: name.to_string(),
+                    arg_number,
+                    arity: builtin.arity,
+                    line,
+                });
+            }
+        }
+    }
+
+    /// Declares a name in the current scope.
+    fn declare(&mut self, name: &str, kind: DeclarationKind, line: usize) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Declaration { name: name.to_string(), kind, line, used: false });
+        }
+    }
+
+    /// Returns `true` if `name` has been declared in any of the current scopes.
+    fn is_declared(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.iter().any(|declaration| declaration.name == name))
+    }
+
+    /// Marks the declaration `name` refers to as used, i.e., the most recent one in the right-most scope.
+    fn mark_used(&mut self, name: &str) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(declaration) = scope.iter_mut().rev().find(|declaration| declaration.name == name) {
+                declaration.used = true;
+                return;
+            }
+        }
+    }
+
+    /// Exits the right-most scope, reporting any variables and parameters declared in it that were never used.
+    fn exit_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        for declaration in scope {
+            // Names starting with `_` are deliberately unused.
+            if declaration.used || declaration.name.starts_with('_') {
+                continue;
+            }
+            match declaration.kind {
+                DeclarationKind::Variable => self.warnings.push(WarningType::UnusedVariable {
+                    name: declaration.name,
+                    line: declaration.line,
+                }),
+                DeclarationKind::Parameter { function } => self.warnings.push(WarningType::UnusedParameter {
+                    name: declaration.name,
+                    function,
+                    line: declaration.line,
+                }),
+                DeclarationKind::Function => (),
+            }
+        }
+    }
+}
+
+/// Splits a line of source code into the code and the text of its comment, if it has one.
+/// A `#` inside a string does not start a comment.
+fn split_comment(line: &str) -> Option<(&str, &str)> {
+    let mut quote: Option<char> = None;  // The quote character of the string we are in, if any.
+    for (index, c) in line.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return Some((&line[..index], &line[index + 1..])),
+            None => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tokenizer::Tokenizer, parser::Parser, value::Arity, warning::WarningType};
+
+    use super::Linter;
+
+    fn lint(source: &str) -> Vec<WarningType> {
+        let mut tokenizer = Tokenizer::new(source);
+        let (tokens, _) = tokenizer.tokenize();
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse().expect("Parser returned error.");
+        Linter::new(source).lint(&ast)
+    }
+
+    #[test]
+    fn no_warnings() {
+        let source = "var a = 1\nfunc f(x) {\nreturn x + a + b\n}\nvar b = 2\nprint f(3)";
+        assert_eq!(lint(source), vec![]);
+    }
+
+    #[test]
+    fn unused_variables_and_parameters() {
+        let source = "var a = 1\nvar _b = 2\nfunc f(x, y) {\nvar z = x\n}\nf(1, 2)";
+        assert_eq!(lint(source), vec![
+            WarningType::UnusedVariable { name: String::from("a"), line: 1 },
+            WarningType::UnusedParameter { name: String::from("y"), function: String::from("f"), line: 3 },
+            WarningType::UnusedVariable { name: String::from("z"), line: 4 },
+        ]);
+    }
+
+    #[test]
+    fn assignment_is_not_use() {
+        let source = "var a = 1\na = 2\nvar b = [1]\nb[0] = 2";
+        assert_eq!(lint(source), vec![WarningType::UnusedVariable { name: String::from("a"), line: 1 }]);
+    }
+
+    #[test]
+    fn shadowing() {
+        let source = "var a = 1\nvar a = 2\nif (a == 2) {\nvar a = 3\nprint a\n}\nfor (var i = 0; i < 1; i = i + 1) {\nvar i = 5\nprint i\n}";
+        assert_eq!(lint(source), vec![
+            WarningType::UnusedVariable { name: String::from("a"), line: 1 },
+            WarningType::ShadowedVariable { name: String::from("a"), line: 4 },
+            WarningType::ShadowedVariable { name: String::from("i"), line: 8 },
+        ]);
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let source = "func f() {\nreturn 1\nprint 2\nprint 3\n}\nwhile (true) {\nbreak\nprint 4\n}\nprint f()";
+        assert_eq!(lint(source), vec![
+            WarningType::UnreachableCode { line: 3 },
+            WarningType::UnreachableCode { line: 8 },
+        ]);
+    }
+
+    #[test]
+    fn non_boolean_conditions() {
+        let source = "if (1) {}\nwhile ('a') {}\nif ([]) {} else if (null) {}\nif (true) {}\nwhile (1 == 1) {}";
+        assert_eq!(lint(source), vec![
+            WarningType::NonBooleanCondition { got: String::from("Number"), line: 1 },
+            WarningType::NonBooleanCondition { got: String::from("String"), line: 2 },
+            WarningType::NonBooleanCondition { got: String::from("Array"), line: 3 },
+            WarningType::NonBooleanCondition { got: String::from("Null"), line: 3 },
+        ]);
+    }
+
+    #[test]
+    fn builtin_arity() {
+        let source = "var a = []\nappend(a)\nprint size(a, 1)\nfunc sort(x, y) {\nreturn x + y\n}\nprint sort(1, 2)";
+        assert_eq!(lint(source), vec![
+            WarningType::BuiltinArgNumberMismatch { name: String::from("append"), arg_number: 1, arity: Arity::Exact(2), line: 2 },
+            WarningType::BuiltinArgNumberMismatch { name: String::from("size"), arg_number: 2, arity: Arity::Exact(1), line: 3 },
+        ]);
+    }
+
+    #[test]
+    fn suppression() {
+        let source = "# allow(unused_variable)\nvar a = 1\nvar b = 2  # allow(unused_variable, shadowed_variable)\nvar c = '# allow(unused_variable)'\nif (1) {}";
+        assert_eq!(lint(source), vec![
+            WarningType::UnusedVariable { name: String::from("c"), line: 4 },
+            WarningType::NonBooleanCondition { got: String::from("Number"), line: 5 },
+        ]);
+    }
+}
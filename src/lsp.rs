@@ -0,0 +1,577 @@
+// The Language Server Protocol (LSP) server used by `nea lsp`, so that editors can show errors, navigate and complete scripts as they are edited.
+// See https://microsoft.github.io/language-server-protocol/specification for the messages.
+//
+// Messages are JSON-RPC objects with the same `Content-Length` framing as the DAP server, read from stdin and written to stdout.
+// The server keeps the text of each open document, and works everything out from its tokens again for each request,
+// which is plenty fast for scripts. As scoping is dynamic, a name cannot be resolved to a single declaration without running the script,
+// so every `var`, `func` and parameter declaring the name counts as a definition of it, and every use of the name as a reference.
+//
+// A diagnostic covers the token its error was raised at: the `Error` token the tokenizer left in place of the characters it could not
+// tokenize, or the token the parser was at (see `Parser::error_tokens()`). Errors without one cover the code on their line instead.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+
+use crate::builtins::{self, BUILTINS};
+use crate::error::ErrorType;
+use crate::json::{self, Json};
+use crate::parser::Parser;
+use crate::stmt::{Stmt, StmtType};
+use crate::token::{Token, TokenType, KEYWORDS};
+use crate::tokenizer::Tokenizer;
+
+/// The JSON-RPC error code for requests with a method the server does not have.
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+/// The kinds of symbols and completion items in the protocol which are used.
+const SYMBOL_FUNCTION: usize = 12;
+const COMPLETION_FUNCTION: usize = 3;
+const COMPLETION_VARIABLE: usize = 6;
+const COMPLETION_KEYWORD: usize = 14;
+
+/// What a name is declared as.
+#[derive(Clone, Debug, PartialEq)]
+enum Kind {
+    Variable,
+    Function { parameters: Vec<String> },
+    Parameter { function: String },
+}
+
+/// A declaration of a name: by `var`, by `func`, or as a parameter of a function.
+#[derive(Debug, PartialEq)]
+struct Declaration {
+    kind: Kind,
+    index: usize,  // The index of the name's token.
+}
+
+/// An open document, and what is known about it.
+struct Document {
+    text: String,
+    tokens: Vec<Token>,
+    declarations: Vec<Declaration>,
+}
+
+impl Document {
+    /// Tokenizes the text and finds the declarations in it. Tokenizer errors are returned to be reported as diagnostics.
+    fn new(text: String) -> (Self, Vec<ErrorType>) {
+        let (tokens, errors) = Tokenizer::new(&text).tokenize();
+        let declarations = find_declarations(&tokens);
+        (Self { text, tokens, declarations }, errors)
+    }
+
+    /// Returns the text of a line, counting from 1, without the line break.
+    fn line(&self, line: usize) -> &str {
+        self.text.split('\n').nth(line.saturating_sub(1)).unwrap_or_default()
+    }
+
+    /// Returns the LSP range of a token. Columns count characters, whereas LSP counts UTF-16 code units.
+    fn range(&self, token: &Token) -> Json {
+        let text = self.line(token.line);
+        let start = token.column.saturating_sub(1);
+        let end = start + token.lexeme.chars().count();
+        range(token.line - 1, utf16_offset(text, start), token.line - 1, utf16_offset(text, end))
+    }
+
+    /// Returns the LSP range of the code on a line, counting from 1, i.e., the line without its indentation.
+    fn line_range(&self, line: usize) -> Json {
+        let text = self.line(line);
+        let indentation = text.chars().take_while(|c| c.is_whitespace()).count();
+        range(line.saturating_sub(1), utf16_offset(text, indentation), line.saturating_sub(1), utf16_offset(text, text.chars().count()))
+    }
+
+    /// Returns the index of the identifier at an LSP position, including just after its last character, where the cursor is after typing it.
+    fn identifier_at(&self, position: &Json) -> Option<usize> {
+        let line = position.get("line").and_then(Json::as_usize)? + 1;
+        let character = position.get("character").and_then(Json::as_usize)?;
+        let column = char_offset(self.line(line), character) + 1;
+        self.tokens.iter().position(|token| {
+            token.type_ == TokenType::Identifier
+                && token.line == line
+                && token.column <= column
+                && column <= token.column + token.lexeme.chars().count()
+        })
+    }
+
+    /// Returns the declarations of a name.
+    fn declarations_of<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Declaration> {
+        self.declarations.iter().filter(move |declaration| self.tokens[declaration.index].lexeme == name)
+    }
+}
+
+/// Finds the names declared by `var`, by `func`, and as parameters, in the order they appear.
+fn find_declarations(tokens: &[Token]) -> Vec<Declaration> {
+    let mut declarations = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let Some(name) = tokens.get(index + 1).filter(|name| name.type_ == TokenType::Identifier) else { continue };
+        match token.type_ {
+            TokenType::Var => declarations.push(Declaration { kind: Kind::Variable, index: index + 1 }),
+            TokenType::Func => {
+                // The parameters are the identifiers between the brackets, which may not be closed yet.
+                let mut parameters = Vec::new();
+                if tokens.get(index + 2).is_some_and(|token| token.type_ == TokenType::LeftParen) {
+                    for (offset, parameter) in tokens[index + 3..].iter().enumerate() {
+                        match parameter.type_ {
+                            TokenType::Identifier => parameters.push((index + 3 + offset, parameter.lexeme.clone())),
+                            TokenType::Comma => {},
+                            _ => break,
+                        }
+                    }
+                }
+                declarations.push(Declaration {
+                    kind: Kind::Function { parameters: parameters.iter().map(|(_, parameter)| parameter.clone()).collect() },
+                    index: index + 1,
+                });
+                declarations.extend(parameters.into_iter().map(|(index, _)| Declaration {
+                    kind: Kind::Parameter { function: name.lexeme.clone() },
+                    index,
+                }));
+            },
+            _ => {},
+        }
+    }
+    declarations
+}
+
+/// Returns the number of UTF-16 code units in the first `chars` characters of `text`.
+fn utf16_offset(text: &str, chars: usize) -> usize {
+    text.chars().take(chars).map(char::len_utf16).sum()
+}
+
+/// Returns the number of characters in the first `units` UTF-16 code units of `text`.
+fn char_offset(text: &str, units: usize) -> usize {
+    let mut count = 0;
+    let mut chars = 0;
+    for c in text.chars() {
+        count += c.len_utf16();
+        if count > units {
+            break;
+        }
+        chars += 1;
+    }
+    chars
+}
+
+/// Returns an LSP range, where lines and characters count from 0.
+fn range(start_line: usize, start_character: usize, end_line: usize, end_character: usize) -> Json {
+    object(vec![
+        ("start", object(vec![("line", Json::from(start_line)), ("character", Json::from(start_character))])),
+        ("end", object(vec![("line", Json::from(end_line)), ("character", Json::from(end_character))])),
+    ])
+}
+
+/// Returns a JSON object with the given members.
+fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(members.into_iter().map(|(name, value)| (name.to_string(), value)).collect())
+}
+
+/// Returns the message of an error without the line at its start, as the editor shows where it is, followed by its hint.
+fn diagnostic_message(error: &ErrorType) -> String {
+    let message = error.message();
+    let mut message = match message.split_once(": ") {
+        Some((location, rest)) if location.starts_with("Line ") => rest.to_string(),
+        _ => message,
+    };
+    if let Some(hint) = error.hint() {
+        message.push_str(&format!("\nHint: {}", hint));
+    }
+    message
+}
+
+/// Answers the editor's requests, and keeps the open documents up to date.
+struct Server {
+    output: Box<dyn Write>,
+    documents: HashMap<String, Document>,  // The open documents, by their URIs.
+}
+
+/// Runs the server on stdin and stdout until the editor tells it to exit, or closes stdin.
+pub fn run_server() {
+    serve(&mut BufReader::new(io::stdin()), Box::new(io::stdout()));
+}
+
+/// Handles the messages from the editor until it tells the server to exit.
+fn serve(input: &mut dyn BufRead, output: Box<dyn Write>) {
+    let mut server = Server { output, documents: HashMap::new() };
+    while let Some(message) = json::read_message(input) {
+        let Some(method) = message.get("method").and_then(Json::as_str) else { continue };
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+        match message.get("id") {
+            // Requests have an ID, which the response is sent with.
+            Some(id) => {
+                let result = server.request(method, &params);
+                let mut response = vec![(String::from("jsonrpc"), Json::from("2.0")), (String::from("id"), id.clone())];
+                response.push(match result {
+                    Some(result) => (String::from("result"), result),
+                    None => (String::from("error"), object(vec![
+                        ("code", Json::Number(METHOD_NOT_FOUND)),
+                        ("message", Json::from(format!("`{}` is not supported", method))),
+                    ])),
+                });
+                json::write_message(&mut server.output, &Json::Object(response));
+            },
+            None if method == "exit" => return,
+            None => server.notification(method, &params),
+        }
+    }
+}
+
+impl Server {
+    /// Returns the result of a request, or `None` if the method is not supported.
+    fn request(&mut self, method: &str, params: &Json) -> Option<Json> {
+        if method == "initialize" {
+            return Some(object(vec![
+                ("capabilities", object(vec![
+                    // The whole text of a document is sent when it changes.
+                    ("textDocumentSync", Json::from(1)),
+                    ("definitionProvider", Json::from(true)),
+                    ("referencesProvider", Json::from(true)),
+                    ("hoverProvider", Json::from(true)),
+                    ("documentSymbolProvider", Json::from(true)),
+                    ("completionProvider", object(vec![])),
+                ])),
+                ("serverInfo", object(vec![("name", Json::from("nea")), ("version", Json::from(env!("CARGO_PKG_VERSION")))])),
+            ]));
+        }
+        if method == "shutdown" {
+            return Some(Json::Null);
+        }
+
+        let uri = params.get("textDocument").and_then(|document| document.get("uri")).and_then(Json::as_str).unwrap_or_default();
+        let position = params.get("position").unwrap_or(&Json::Null);
+        // Requests for documents which are not open have no results.
+        let document = self.documents.get(uri);
+        match method {
+            "textDocument/definition" => Some(document.map_or(Json::Null, |document| definition(document, uri, position))),
+            "textDocument/references" => {
+                let include_declaration = params.get("context")
+                    .and_then(|context| context.get("includeDeclaration"))
+                    .and_then(Json::as_bool)
+                    .unwrap_or(true);
+                Some(document.map_or(Json::Null, |document| references(document, uri, position, include_declaration)))
+            },
+            "textDocument/hover" => Some(document.map_or(Json::Null, |document| hover(document, position))),
+            "textDocument/documentSymbol" => Some(document.map_or(Json::Null, document_symbols)),
+            "textDocument/completion" => Some(document.map_or(Json::Null, completion)),
+            _ => None,
+        }
+    }
+
+    /// Handles a notification. Ones which are not supported, e.g., `initialized`, are ignored, as the protocol requires.
+    fn notification(&mut self, method: &str, params: &Json) {
+        let Some(uri) = params.get("textDocument").and_then(|document| document.get("uri")).and_then(Json::as_str) else { return };
+        let text = match method {
+            "textDocument/didOpen" => params.get("textDocument").and_then(|document| document.get("text")).and_then(Json::as_str),
+            // As the whole text is sent, only the last change matters.
+            "textDocument/didChange" => params.get("contentChanges")
+                .and_then(Json::as_array)
+                .and_then(<[Json]>::last)
+                .and_then(|change| change.get("text"))
+                .and_then(Json::as_str),
+            "textDocument/didClose" => {
+                // The diagnostics of a closed document are cleared, as they can no longer be kept up to date.
+                self.documents.remove(uri);
+                self.publish_diagnostics(uri, Json::Array(Vec::new()));
+                return;
+            },
+            _ => return,
+        };
+        let Some(text) = text else { return };
+
+        let (document, mut errors) = Document::new(text.to_string());
+        // Each tokenizer error left an `Error` token in place of the characters at fault.
+        let mut error_tokens: Vec<Option<Token>> = document.tokens.iter().filter(|token| token.type_ == TokenType::Error).cloned().map(Some).collect();
+        error_tokens.resize(errors.len(), None);
+        let mut parser = Parser::new(document.tokens.clone());
+        let (_, parser_errors) = parser.parse_partial();
+        errors.extend(parser_errors);
+        error_tokens.extend(parser.error_tokens().iter().cloned());
+        let diagnostics = errors.iter().zip(error_tokens)
+            .map(|(error, token)| object(vec![
+                ("range", token.map_or_else(|| document.line_range(error.line()), |token| document.range(&token))),
+                ("severity", Json::from(1)),
+                ("source", Json::from("nea")),
+                ("message", Json::from(diagnostic_message(error))),
+            ]))
+            .collect();
+        self.documents.insert(uri.to_string(), document);
+        self.publish_diagnostics(uri, Json::Array(diagnostics));
+    }
+
+    /// Sends the diagnostics of a document, replacing the ones sent before.
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: Json) {
+        json::write_message(&mut self.output, &object(vec![
+            ("jsonrpc", Json::from("2.0")),
+            ("method", Json::from("textDocument/publishDiagnostics")),
+            ("params", object(vec![("uri", Json::from(uri)), ("diagnostics", diagnostics)])),
+        ]));
+    }
+}
+
+/// Returns the locations of the declarations of the name at `position`.
+fn definition(document: &Document, uri: &str, position: &Json) -> Json {
+    let Some(index) = document.identifier_at(position) else { return Json::Null };
+    let locations: Vec<Json> = document.declarations_of(&document.tokens[index].lexeme)
+        .map(|declaration| location(document, uri, declaration.index))
+        .collect();
+    if locations.is_empty() { Json::Null } else { Json::Array(locations) }
+}
+
+/// Returns the locations of every use of the name at `position`, leaving out its declarations unless `include_declaration` is set.
+fn references(document: &Document, uri: &str, position: &Json, include_declaration: bool) -> Json {
+    let Some(index) = document.identifier_at(position) else { return Json::Null };
+    let name = &document.tokens[index].lexeme;
+    let locations = document.tokens.iter()
+        .enumerate()
+        .filter(|(_, token)| token.type_ == TokenType::Identifier && token.lexeme == *name)
+        .filter(|(index, _)| include_declaration || !document.declarations.iter().any(|declaration| declaration.index == *index))
+        .map(|(index, _)| location(document, uri, index))
+        .collect();
+    Json::Array(locations)
+}
+
+/// Returns an LSP location of the token with the given index.
+fn location(document: &Document, uri: &str, index: usize) -> Json {
+    object(vec![("uri", Json::from(uri)), ("range", document.range(&document.tokens[index]))])
+}
+
+/// Returns how the name at `position` is declared in the document, or the signature and description of a built-in function.
+fn hover(document: &Document, position: &Json) -> Json {
+    let Some(index) = document.identifier_at(position) else { return Json::Null };
+    let name = &document.tokens[index].lexeme;
+
+    let mut declarations: Vec<String> = Vec::new();
+    for declaration in document.declarations_of(name) {
+        let description = match &declaration.kind {
+            Kind::Variable => format!("var {}", name),
+            Kind::Function { parameters } => format!("func {}({})", name, parameters.join(", ")),
+            Kind::Parameter { function } => format!("{}  # a parameter of `{}`", name, function),
+        };
+        if !declarations.contains(&description) {
+            declarations.push(description);
+        }
+    }
+    let contents = if !declarations.is_empty() {
+        format!("```nea\n{}\n```", declarations.join("\n"))
+    } else if let Some(builtin) = builtins::find(name) {
+        format!("```nea\n{}\n```\n{}", builtin.signature, builtin.description)
+    } else {
+        return Json::Null;
+    };
+    object(vec![
+        ("contents", object(vec![("kind", Json::from("markdown")), ("value", Json::from(contents))])),
+        ("range", document.range(&document.tokens[index])),
+    ])
+}
+
+/// Returns the functions in the document, with the ones declared inside them as their children.
+/// Only functions which parse are included, as the extent of the others is unknown.
+fn document_symbols(document: &Document) -> Json {
+    let (ast, _) = Parser::new(document.tokens.clone()).parse_partial();
+    let mut symbols = Vec::new();
+    for statement in &ast {
+        function_symbols(document, statement, &mut symbols);
+    }
+    Json::Array(symbols)
+}
+
+/// Adds the symbols of the functions declared in a statement, at the outermost level, to `symbols`.
+fn function_symbols(document: &Document, statement: &Stmt, symbols: &mut Vec<Json>) {
+    match &statement.stmt_type {
+        StmtType::Function { name, parameters, body, .. } => {
+            let mut children = Vec::new();
+            function_symbols(document, body, &mut children);
+
+            // A function goes from `func` to the `}` closing its body, which is the line of the body.
+            let full_range = range(statement.line - 1, 0, body.line - 1, utf16_offset(document.line(body.line), document.line(body.line).chars().count()));
+            let selection_range = document.declarations.iter()
+                .map(|declaration| &document.tokens[declaration.index])
+                .find(|token| token.line == statement.line && token.lexeme == *name)
+                .map_or_else(|| full_range.clone(), |token| document.range(token));
+            symbols.push(object(vec![
+                ("name", Json::from(name.as_str())),
+                ("detail", Json::from(format!("({})", parameters.join(", ")))),
+                ("kind", Json::from(SYMBOL_FUNCTION)),
+                ("range", full_range),
+                ("selectionRange", selection_range),
+                ("children", Json::Array(children)),
+            ]));
+        },
+        StmtType::Block { body } => {
+            for statement in body {
+                function_symbols(document, statement, symbols);
+            }
+        },
+        StmtType::If { then_body, else_body, .. } => {
+            function_symbols(document, then_body, symbols);
+            if let Some(else_body) = else_body {
+                function_symbols(document, else_body, symbols);
+            }
+        },
+        StmtType::While { body, .. } => function_symbols(document, body, symbols),
+        _ => {},
+    }
+}
+
+/// Returns the completion items: the keywords, the built-in functions, and the names declared in the document.
+/// The editor filters them by what has been typed.
+fn completion(document: &Document) -> Json {
+    let mut items: Vec<Json> = KEYWORDS.iter()
+        .map(|keyword| object(vec![("label", Json::from(*keyword)), ("kind", Json::from(COMPLETION_KEYWORD))]))
+        .collect();
+    items.extend(BUILTINS.iter().map(|builtin| object(vec![
+        ("label", Json::from(builtin.name)),
+        ("kind", Json::from(COMPLETION_FUNCTION)),
+        ("detail", Json::from(builtin.signature)),
+        ("documentation", Json::from(builtin.description)),
+    ])));
+
+    let mut names: Vec<&str> = BUILTINS.iter().map(|builtin| builtin.name).collect();
+    for declaration in &document.declarations {
+        let name = document.tokens[declaration.index].lexeme.as_str();
+        if names.contains(&name) {
+            continue;
+        }
+        names.push(name);
+        let (kind, detail) = match &declaration.kind {
+            Kind::Function { parameters } => (COMPLETION_FUNCTION, format!("func {}({})", name, parameters.join(", "))),
+            Kind::Variable => (COMPLETION_VARIABLE, format!("var {}", name)),
+            Kind::Parameter { function } => (COMPLETION_VARIABLE, format!("a parameter of `{}`", function)),
+        };
+        items.push(object(vec![("label", Json::from(name)), ("kind", Json::from(kind)), ("detail", Json::from(detail))]));
+    }
+    Json::Array(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use nea::stream::Buffer;
+
+    use crate::json::Json;
+
+    use super::{char_offset, serve, utf16_offset};
+
+    /// Runs a session where the editor sends the given messages, each a method and its params, and returns the messages sent back.
+    /// Methods starting with `textDocument/did` are sent as notifications, and the others as requests with the ID of their index.
+    fn session(messages: &[(&str, &str)]) -> Vec<Json> {
+        let mut input = String::new();
+        for (id, (method, params)) in messages.iter().enumerate() {
+            let id = if method.starts_with("textDocument/did") { String::new() } else { format!(r#""id":{},"#, id) };
+            let body = format!(r#"{{"jsonrpc":"2.0",{}"method":"{}","params":{}}}"#, id, method, params);
+            input.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+        }
+        let output = Buffer::new();
+        serve(&mut Cursor::new(input), Box::new(output.clone()));
+
+        let output = output.contents();
+        output.split("Content-Length: ")
+            .skip(1)
+            .map(|message| Json::parse(message.split_once("\r\n\r\n").unwrap().1).unwrap())
+            .collect()
+    }
+
+    /// Returns the params of a `didOpen` notification for `file:///a.nea` with the given text.
+    fn open(text: &str) -> String {
+        format!(r#"{{"textDocument":{{"uri":"file:///a.nea","languageId":"nea","version":1,"text":{}}}}}"#, Json::from(text))
+    }
+
+    /// Returns the params of a request at a position in `file:///a.nea`.
+    fn at(line: usize, character: usize) -> String {
+        format!(r#"{{"textDocument":{{"uri":"file:///a.nea"}},"position":{{"line":{},"character":{}}}}}"#, line, character)
+    }
+
+    /// Returns the result of the response with the given ID, as compact JSON.
+    fn result(messages: &[Json], id: usize) -> String {
+        messages.iter()
+            .find(|message| message.get("id").and_then(Json::as_usize) == Some(id))
+            .and_then(|message| message.get("result"))
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn diagnostics() {
+        let change = r#"{"textDocument":{"uri":"file:///a.nea","version":2},"contentChanges":[{"text":"var a = 1\n"}]}"#;
+        let messages = session(&[
+            ("initialize", r#"{"capabilities":{}}"#),
+            ("textDocument/didOpen", &open("var a = 1\nvar = 2\n    print $")),
+            ("textDocument/didChange", change),
+            ("textDocument/didClose", r#"{"textDocument":{"uri":"file:///a.nea"}}"#),
+            ("shutdown", "null"),
+        ]);
+        assert_eq!(messages.len(), 5);
+        assert!(messages[0].get("result").and_then(|result| result.get("capabilities")).is_some());
+
+        let diagnostics = messages[1].get("params").and_then(|params| params.get("diagnostics")).and_then(Json::as_array).unwrap();
+        let summaries: Vec<(String, String)> = diagnostics.iter()
+            .map(|diagnostic| (diagnostic.get("range").unwrap().to_string(), diagnostic.get("message").and_then(Json::as_str).unwrap().to_string()))
+            .collect();
+        assert_eq!(summaries, vec![
+            (String::from(r#"{"start":{"line":2,"character":10},"end":{"line":2,"character":11}}"#), String::from("unexpected character `$`.")),
+            (String::from(r#"{"start":{"line":1,"character":4},"end":{"line":1,"character":5}}"#), String::from("expected variable name. Make sure it is not a keyword.")),
+        ]);
+
+        // Fixing the errors, and closing the document, clears the diagnostics.
+        assert_eq!(messages[2].get("params").unwrap().to_string(), r#"{"uri":"file:///a.nea","diagnostics":[]}"#);
+        assert_eq!(messages[3].get("params").unwrap().to_string(), r#"{"uri":"file:///a.nea","diagnostics":[]}"#);
+        assert_eq!(result(&messages, 4), "null");
+    }
+
+    #[test]
+    fn navigation() {
+        let text = "func add(a, b) {\n    var total = a + b\n    return total\n}\nvar total = add(1, 2)\nprint(size([\"😀\", total]))\nwhile (false) {\n    func inner() {}\n}\n";
+        let messages = session(&[
+            ("textDocument/didOpen", &open(text)),
+            ("textDocument/definition", &at(4, 8)),
+            ("textDocument/references", r#"{"textDocument":{"uri":"file:///a.nea"},"position":{"line":2,"character":16},"context":{"includeDeclaration":false}}"#),
+            ("textDocument/hover", &at(1, 21)),
+            ("textDocument/hover", &at(5, 7)),
+            ("textDocument/hover", &at(4, 14)),
+            ("textDocument/documentSymbol", r#"{"textDocument":{"uri":"file:///a.nea"}}"#),
+            ("textDocument/completion", &at(0, 0)),
+            ("textDocument/hover", &at(0, 0)),
+            ("textDocument/formatting", &at(0, 0)),
+        ]);
+
+        // Both declarations of `total` are definitions of it, as which one is used depends on how the code runs.
+        assert_eq!(result(&messages, 1), concat!(
+            r#"[{"uri":"file:///a.nea","range":{"start":{"line":1,"character":8},"end":{"line":1,"character":13}}},"#,
+            r#"{"uri":"file:///a.nea","range":{"start":{"line":4,"character":4},"end":{"line":4,"character":9}}}]"#,
+        ));
+        assert_eq!(result(&messages, 2), concat!(
+            r#"[{"uri":"file:///a.nea","range":{"start":{"line":2,"character":11},"end":{"line":2,"character":16}}},"#,
+            r#"{"uri":"file:///a.nea","range":{"start":{"line":5,"character":18},"end":{"line":5,"character":23}}}]"#,
+        ));
+        assert_eq!(result(&messages, 3), concat!(
+            r#"{"contents":{"kind":"markdown","value":"```nea\nb  # a parameter of `add`\n```"},"#,
+            r#""range":{"start":{"line":1,"character":20},"end":{"line":1,"character":21}}}"#,
+        ));
+        assert!(result(&messages, 4).contains(r#""value":"```nea\nsize(collection)\n```\nReturns the number of elements"#));
+        assert!(result(&messages, 5).contains(r#""value":"```nea\nfunc add(a, b)\n```""#));
+
+        assert_eq!(result(&messages, 6), concat!(
+            r#"[{"name":"add","detail":"(a, b)","kind":12,"range":{"start":{"line":0,"character":0},"end":{"line":3,"character":1}},"#,
+            r#""selectionRange":{"start":{"line":0,"character":5},"end":{"line":0,"character":8}},"children":[]},"#,
+            r#"{"name":"inner","detail":"()","kind":12,"range":{"start":{"line":7,"character":0},"end":{"line":7,"character":19}},"#,
+            r#""selectionRange":{"start":{"line":7,"character":9},"end":{"line":7,"character":14}},"children":[]}]"#,
+        ));
+
+        let completion = Json::parse(&result(&messages, 7)).unwrap();
+        let labels: Vec<&str> = completion.as_array().unwrap().iter().filter_map(|item| item.get("label").and_then(Json::as_str)).collect();
+        assert!(labels.starts_with(&["and", "assert"]));
+        assert!(labels.ends_with(&["to_string", "add", "a", "b", "total", "inner"]));
+
+        // There is nothing at the start of a keyword, and the server has no formatting.
+        assert_eq!(result(&messages, 8), "null");
+        assert!(messages.iter().any(|message| message.get("id").and_then(Json::as_usize) == Some(9) && message.get("error").is_some()));
+    }
+
+    #[test]
+    fn utf16() {
+        assert_eq!(utf16_offset("a😀b", 2), 3);
+        assert_eq!(char_offset("a😀b", 3), 2);
+        // A position in the middle of a character is in that character.
+        assert_eq!(char_offset("a😀b", 2), 1);
+        assert_eq!(char_offset("ab", 10), 2);
+    }
+}
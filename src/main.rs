@@ -1,86 +1,354 @@
-mod environment;
-mod error;
-mod expr;
-mod hash_table;
-mod interpreter;
-mod parser;
-mod stmt;
-mod token;
-mod tokenizer;
-mod value;
-
-use std::{env, io, io::Write, fs};
+// `ErrorType` carries `Value`s (e.g., the value of a `return` statement), so it is large by design.
+#![allow(clippy::result_large_err)]
 
+mod cli;
+mod coverage;
+mod dap;
+mod debugger;
+mod doc;
+mod dump;
+#[cfg(test)]
+mod fuzz;
+mod json;
+mod lsp;
+mod profile;
+mod repl;
+mod test_runner;
+mod trace;
+
+// The language itself is the library crate. Its modules are imported here so that the tools in this crate can use them as
+// `crate::interpreter` and so on, as if they were declared here.
+use nea::{builtins, error, expr, formatter, interpreter, linter, parser, stmt, suggestion, token, tokenizer, value, warning};
+
+use std::{env, fs, io, thread};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use cli::{Command, Options, Source, WarningMode};
+use debugger::Debugger;
+use error::ErrorType;
 use parser::Parser;
 use tokenizer::Tokenizer;
+use profile::{Profile, ProfileOptions, Profiler};
+use trace::{TraceOptions, Tracer};
 use interpreter::Interpreter;
+use linter::Linter;
+
+/// The size of the stack of the thread which runs the commands.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// The exit code when the command-line arguments are invalid. Errors in the script itself exit with 1.
+const EXIT_USAGE: u8 = 2;
 
 /// Driver code.
-fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    // Note that `args[0]` will be the name of the binary.
-    // So to check whether one argument has been passed, we check if `args.len() == 2`.
-    if args.len() > 2 {
-        // Only one given argument is expected.
-        eprintln!("Usage: nea.exe [script]");
-    } else if args.len() == 2 {
-        // `args[1]` will be the given argument, i.e., the file path of the source code.
-        run_file(&args[1]);
-    } else {
-        // No arguments were given. In this case, we run the REPL interface.
-        run_repl();
+/// The commands are run on a separate thread, as the linter and formatter are recursive, and the main thread's stack is too small for deeply nested scripts.
+fn main() -> ExitCode {
+    let spawned = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run_main);
+    match spawned {
+        // The thread only panics if there is a bug, in which case the panic message has already been printed.
+        Ok(handle) => handle.join().unwrap_or(ExitCode::FAILURE),
+        Err(e) => {
+            eprintln!("Failed to start the interpreter: {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+/// Runs the command given by the command-line arguments (see `cli::HELP`).
+fn run_main() -> ExitCode {
+    // Note that the first argument is the name of the binary, so it is skipped.
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (command, options) = match cli::parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("error: {}\nRun `nea --help` for usage.", message);
+            return ExitCode::from(EXIT_USAGE);
+        },
+    };
+
+    match command {
+        Command::Repl => {
+            // No script was given. In this case, we run the REPL interface.
+            repl::run_repl(&options);
+            ExitCode::SUCCESS
+        },
+        Command::Run { source, args, trace, profile } => run_source(&source, args, trace, profile, &options),
+        Command::Debug { source, args } => debug_source(&source, args, &options),
+        Command::Dap => {
+            dap::run_server();
+            ExitCode::SUCCESS
+        },
+        Command::Lsp => {
+            lsp::run_server();
+            ExitCode::SUCCESS
+        },
+        Command::Check { sources } => check_sources(&sources, &options),
+        Command::Tokens { source, format } => dump_source(&source, &options, |code| {
+            let (tokens, errors) = Tokenizer::new(code).tokenize();
+            // The tokens are printed even if there are errors, as they show where the tokenizer went wrong.
+            print!("{}", dump::dump_tokens(&tokens, format));
+            errors
+        }),
+        Command::Fmt { sources, check } => format_sources(&sources, check, &options),
+        Command::Test { paths, coverage } => test_runner::run_tests(&paths, coverage.as_ref()),
+        Command::Doc { paths, format } => doc::run_doc(&paths, format, &options),
+        Command::Ast { source, format } => dump_source(&source, &options, |code| {
+            let (tokens, mut errors) = Tokenizer::new(code).tokenize();
+            match Parser::new(tokens).parse() {
+                Ok(ast) if errors.is_empty() => print!("{}", dump::dump_ast(&ast, format)),
+                Ok(..) => (),
+                Err(parser_errors) => errors.extend(parser_errors),
+            }
+            errors
+        }),
+        Command::Help => {
+            print!("{}", cli::HELP);
+            ExitCode::SUCCESS
+        },
+        Command::Version => {
+            println!("nea {}", env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        },
     }
 }
 
-/// Runs the source code given at the file path.
-fn run_file(file_path: &str) {
-    // Reading from the file path. If an error occurs, the `expect()` method will print "Failed to read file." and terminate execution.
-    let source = fs::read_to_string(file_path).expect("Failed to read file.");
+/// Runs the given source code, passing `args` to the script. It is traced to stderr if `trace` is given,
+/// and profiled if `profile` is given, in which case the report is printed to stderr once it has finished.
+fn run_source(source: &Source, args: Vec<String>, trace: Option<TraceOptions>, profile: Option<ProfileOptions>, options: &Options) -> ExitCode {
+    // Reading the source code. If an error occurs, report it and terminate execution.
+    let code = match source.read() {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        },
+    };
 
     // An Interpreter object has to be provided to `run()`, as explained below.
     let mut interpreter = Interpreter::new();
-    
-    run(&source, &mut interpreter);
+    interpreter.set_args(args);
+    if let Some(trace) = trace {
+        interpreter.set_hook(Box::new(Tracer::new(&code, trace, Box::new(io::stderr()))));
+    }
+    let measurements = Arc::new(Mutex::new(Profile::default()));
+    if profile.is_some() {
+        interpreter.set_hook(Box::new(Profiler::new(Arc::clone(&measurements))));
+    }
+
+    let mut success = run(&code, source.name(), &mut interpreter, options);
+
+    if let Some(profile) = profile {
+        // The script may have failed part of the way through, but what did run is still reported.
+        let measurements = measurements.lock().unwrap();
+        eprint!("{}", measurements.report(&code));
+        if let Some(path) = profile.folded {
+            if let Err(e) = fs::write(&path, measurements.folded()) {
+                eprintln!("Failed to write the folded stacks to `{}`: {}", path, e);
+                success = false;
+            }
+        }
+    }
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
 }
 
-/// Runs the interactive REPL interface in the console.
-fn run_repl() {
-    // We need the same `Interpreter` instance across all REPL source code inputs to preserve the variables and functions stored in the environment.
-    let mut interpreter = Interpreter::new();
-    loop {
-        print!("> ");
-        io::stdout().flush().expect("Error: flush failed");  // to flush out "> "
+/// Runs the given source code in the debugger, passing `args` to the script.
+/// Commands are read from stdin line by line, so that the script can still read the lines after them with `input()`.
+fn debug_source(source: &Source, args: Vec<String>, options: &Options) -> ExitCode {
+    let code = match source.read() {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        },
+    };
 
-        // Read user input into `line`.
+    let commands = std::iter::from_fn(|| {
         let mut line = String::new();
-        io::stdin()
-            .read_line(&mut line)
-            .expect("Failed to read line");
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(..) => None,
+            Ok(..) => Some(line),
+        }
+    });
+    let mut interpreter = Interpreter::new();
+    interpreter.set_args(args);
+    interpreter.set_hook(Box::new(Debugger::new(&code, Box::new(commands), Box::new(io::stdout()))));
+
+    if run(&code, source.name(), &mut interpreter, options) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
 
-        run(&line, &mut interpreter);
+/// Tokenizes, parses and lints each source without running it, reporting any errors and warnings as set by `options`.
+/// Fails if any source could not be read or has errors, or has warnings which are denied.
+fn check_sources(sources: &[Source], options: &Options) -> ExitCode {
+    let mut success = true;
+    for source in sources {
+        let code = match source.read() {
+            Ok(code) => code,
+            Err(message) => {
+                eprintln!("{}", message);
+                success = false;
+                continue;
+            },
+        };
+
+        // The linter needs a syntax tree, so scripts with errors are not linted, as when they are run.
+        let (errors, warnings) = match cli::parse_script(&code) {
+            Ok(ast) if options.warnings != WarningMode::Hide => (Vec::new(), Linter::new(&code).lint(&ast)),
+            Ok(..) => (Vec::new(), Vec::new()),
+            Err(errors) => (errors, Vec::new()),
+        };
+        if errors.is_empty() && warnings.is_empty() {
+            continue;
+        }
+
+        if options.error_format == cli::ErrorFormat::Human {
+            // Several files may be checked, so say which one the errors are in. JSON reports include the file anyway.
+            println!("In `{}`:", source.name());
+        }
+        if !errors.is_empty() {
+            cli::report_errors(&errors, source.name(), options);
+            success = false;
+        }
+        cli::report_warnings(&warnings, source.name(), options);
+        if options.warnings == WarningMode::Deny && !warnings.is_empty() {
+            success = false;
+        }
+    }
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Formats each source. Files are formatted in place, while code from stdin or `-e` is printed.
+/// If `check` is `true`, the sources which are not formatted are listed instead, and the command fails if there are any.
+fn format_sources(sources: &[Source], check: bool, options: &Options) -> ExitCode {
+    let mut success = true;
+    for source in sources {
+        let code = match source.read() {
+            Ok(code) => code,
+            Err(message) => {
+                eprintln!("{}", message);
+                success = false;
+                continue;
+            },
+        };
+
+        let formatted = match formatter::format(&code) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                // Code with errors cannot be formatted.
+                if options.error_format == cli::ErrorFormat::Human {
+                    println!("In `{}`:", source.name());
+                }
+                cli::report_errors(&errors, source.name(), options);
+                success = false;
+                continue;
+            },
+        };
+
+        match (check, source) {
+            (true, _) => {
+                if formatted != code {
+                    println!("`{}` is not formatted.", source.name());
+                    success = false;
+                }
+            },
+            (false, Source::File(path)) => {
+                // Only write to files which change, so that their modification times are kept otherwise.
+                if formatted != code {
+                    if let Err(e) = fs::write(path, formatted) {
+                        eprintln!("Failed to write file `{}`: {}", path, e);
+                        success = false;
+                    }
+                }
+            },
+            (false, _) => print!("{}", formatted),
+        }
+    }
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
     }
 }
 
-/// Executes the source code string with the given interpreter instance.
-fn run(source: &str, interpreter: &mut Interpreter) {
+/// Reads the source and prints a dump of it with `dump`, which returns any errors found.
+/// Fails if the source could not be read or has errors.
+fn dump_source<F: FnOnce(&str) -> Vec<ErrorType>>(source: &Source, options: &Options, dump: F) -> ExitCode {
+    let code = match source.read() {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let errors = dump(&code);
+    if errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        cli::report_errors(&errors, source.name(), options);
+        ExitCode::FAILURE
+    }
+}
+
+/// Executes the source code string with the given interpreter instance, reporting errors and warnings as set by `options`.
+/// `name` identifies the source code in the reports, e.g., its file path.
+/// Returns `true` if the source code was executed without errors.
+fn run(source: &str, name: &str, interpreter: &mut Interpreter, options: &Options) -> bool {
     // Lexical analysis.
+    // The tokenizer does not stop at the first error, so the token sequence can still be parsed to find any syntax errors.
     let mut tokenizer = Tokenizer::new(source);
-    // If the source code was tokenized without errors, assign the token sequence to `tokens`.
-    let Ok(tokens) = tokenizer.tokenize() else {
-        // If an error occurred, stop trying to execute the current source code string.
-        // If the user is using a REPL interface, this does not then end the session but simply prompts the user for a new source code input, as expected.
-        return;
-    };
+    let (tokens, mut errors) = tokenizer.tokenize();
 
     // Syntax analysis.
     let mut parser = Parser::new(tokens);
-    // Similarly, if the token sequence was parsed without errors, assign the abstract syntax tree to `ast`.
-    let Ok(ast) = parser.parse() else {
-        // If an error occurred, stop trying to execute the current source code string.
-        return;
+    let ast = match parser.parse() {
+        Ok(ast) => ast,
+        Err(parser_errors) => {
+            errors.extend(parser_errors);
+            Vec::new()
+        },
     };
 
+    if !errors.is_empty() {
+        // If any lexical or syntax errors occurred, report them all at once and stop trying to execute the current source code string.
+        // If the user is using a REPL interface, this does not then end the session but simply prompts the user for a new source code input, as expected.
+        cli::write_errors(interpreter.error_output(), &errors, name, options);
+        return false;
+    }
+
+    // Static analysis. Warnings are only reported; they do not stop execution unless they are denied.
+    if options.warnings != WarningMode::Hide {
+        let warnings = Linter::new(source).lint(&ast);
+        cli::report_warnings(&warnings, name, options);
+        if options.warnings == WarningMode::Deny && !warnings.is_empty() {
+            return false;
+        }
+    }
+
     // Evaluation and execution.
-    interpreter.interpret(ast);
+    match interpreter.interpret(ast) {
+        Ok(()) => true,
+        // The debugger stopped the script because the user quit, which is not an error.
+        Err(ErrorType::ThrownStop {..}) => true,
+        Err(e) => {
+            cli::write_errors(interpreter.error_output(), &[e], name, options);
+            false
+        },
+    }
 }
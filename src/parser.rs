@@ -1,28 +1,96 @@
-use crate::error::{ErrorType, self};
+use std::collections::HashMap;
+
+use crate::error::ErrorType;
 use crate::expr::{Expr, ExprType};
 use crate::hash_table::KeyValue;
-use crate::stmt::{Stmt, StmtType};
-use crate::token::{Token, TokenType, Literal};
+use crate::stmt::{Doc, Stmt, StmtType};
+use crate::token::{self, Token, TokenType, Literal};
+
+/// The maximum depth of nested blocks and expressions.
+/// The parser, linter and interpreter are all recursive, so this prevents deeply nested code from overflowing the Rust stack.
+/// Each level of nesting takes many recursive calls of the parser, so it grows the stack as it goes deeper (see `nested()`).
+pub const MAX_NESTING_DEPTH: usize = 1000;
+
+/// How close to the end of the stack the parser may get before it continues on a new segment of the stack, in bytes.
+/// This is more than one level of nesting takes, even in debug builds.
+const STACK_RED_ZONE: usize = 128 * 1024;
+
+/// The size of each new segment of the stack the parser continues on, in bytes.
+const STACK_SEGMENT_SIZE: usize = 2 * 1024 * 1024;
 
 /// Performs syntax analysis.
 pub struct Parser {
     tokens: Vec<Token>,  // The input sequence of tokens.
     current_index: usize,  // An index pointing to the current token.
     current_line: usize,  // The current line number.
+    depth: usize,  // The current depth of nested blocks and expressions.
+    incomplete: bool,  // Whether the tokens ended before the last statement was complete.
+    docs: HashMap<usize, String>,  // The text of the `##` comments directly before a token, by the index of the token.
+    branches: usize,  // The number of `if` and `while` statements parsed so far.
+    error_tokens: Vec<Option<Token>>,  // The token each error of the last parse was raised at (see `error_tokens()`).
 }
 
 impl Parser {
     /// Constructs a new `Parser` object given the sequence of tokens.
     pub fn new(tokens: Vec<Token>) -> Self {
+        // Documentation comments can appear anywhere, so they are taken out of the sequence of tokens
+        // and only looked up when a declaration is parsed.
+        let mut code_tokens: Vec<Token> = Vec::new();
+        let mut docs: HashMap<usize, String> = HashMap::new();
+        let mut doc: Option<(String, usize)> = None;  // The text of the comments so far, and the line of the last one.
+        for token in tokens {
+            if token.type_ != TokenType::DocComment {
+                // The comments document the token if it is on the line after them.
+                if let Some((text, line)) = doc.take() {
+                    if token.line == line + 1 {
+                        docs.insert(code_tokens.len(), text);
+                    }
+                }
+                code_tokens.push(token);
+                continue;
+            }
+
+            // Comments after code on the same line document nothing.
+            if code_tokens.last().is_some_and(|previous| previous.line == token.line) {
+                doc = None;
+                continue;
+            }
+            // A single space after the `##` is not part of the text.
+            let line_text = token.lexeme[2..].strip_prefix(' ').unwrap_or(&token.lexeme[2..]).trim_end().to_string();
+            doc = match doc {
+                // Comments on consecutive lines are one block of documentation.
+                Some((text, line)) if line + 1 == token.line => Some((text + "\n" + &line_text, token.line)),
+                _ => Some((line_text, token.line)),
+            };
+        }
+
         Self {
-            tokens,
+            tokens: code_tokens,
             current_index: 0,
             current_line: 1,
+            depth: 0,
+            incomplete: false,
+            docs,
+            branches: 0,
+            error_tokens: Vec::new(),
         }
     }
 
     /// The interface method which returns the abstract syntax tree of the source code as a sequence of statements.
     pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ErrorType>> {
+        let (statements, errors) = self.parse_partial();
+        if errors.is_empty() {
+            // If no error occurred, return the sequence of statements.
+            Ok(statements)
+        } else {
+            // If errors occurred, return an `Err` variant so that the driver code reports them and terminates execution.
+            Err(errors)
+        }
+    }
+
+    /// Parses as much of the source code as possible, returning the statements which could be parsed alongside the errors.
+    /// Statements with errors are left out, so tools such as the language server can still work with code which is being typed.
+    pub fn parse_partial(&mut self) -> (Vec<Stmt>, Vec<ErrorType>) {
         let mut statements: Vec<Stmt> = Vec::new();  // The abstract syntax tree.
 
         // We aim to collect as many errors as possible in one run into a vector and report them all at once.
@@ -33,21 +101,30 @@ impl Parser {
             match self.statement() {
                 Ok(statement) => statements.push(statement),
                 Err(error) => {
-                    // If an error occurred during the parse, collect the error, synchronise, and continue.
+                    // If the only error is that more tokens were expected at the end, the source code may just be unfinished.
+                    self.incomplete = errors.is_empty() && self.check_next(&[TokenType::Eof]) && expects_more(&error);
+
+                    // If an error occurred during the parse, collect the error and the token it was raised at, synchronise, and continue.
+                    self.error_tokens.push(self.error_token(&error).cloned());
                     errors.push(error);
                     self.sync();
                 },
             }
         }
-        
-        if errors.is_empty() {
-            // If no error occurred, return the sequence of statements.
-            Ok(statements)
-        } else {
-            // If errors occurred, report all the errors and return an `Err` variant so that the driver code terminates execution.
-            error::report_errors(&errors[..]);
-            Err(errors)
-        }
+
+        (statements, errors)
+    }
+
+    /// Returns the token at which each error of the last call to `parse()` was raised, in the same order as the errors,
+    /// e.g., so that the language server can point at it. `None` means there was no token to point at.
+    pub fn error_tokens(&self) -> &[Option<Token>] {
+        &self.error_tokens
+    }
+
+    /// Returns `true` if the last call to `parse()` failed only because the tokens ended in the middle of a statement,
+    /// e.g., an unclosed block, or a dangling `else` or operator. More source code could then make it valid.
+    pub fn is_incomplete(&self) -> bool {
+        self.incomplete
     }
 
     /// Synchronises the parser to the next possible start of a new statement.
@@ -55,6 +132,7 @@ impl Parser {
         while !self.check_next(&[
             // These are considered tokens that are 'safe' to synchronise to.
             TokenType::Eof,
+            TokenType::Assert,
             TokenType::For,
             TokenType::Func,
             TokenType::If,
@@ -69,52 +147,69 @@ impl Parser {
     }
     
     /// Parses a statement.
-    /// <statement> ::= Break | For <for> | Func <function> | If <if> | Print <print> | Return <return> | Var <var> | While <while> | <expression>
+    /// <statement> ::= Assert <assert> | Break | For <for> | Func <function> | If <if> | Print <print> | Return <return> | Var <var> | While <while> | <expression>
     fn statement(&mut self) -> Result<Stmt, ErrorType> {
+        // A statement is reported as being on the line where it starts.
+        let line = self.next_line();
+        // Documentation before a statement other than a declaration is ignored.
+        let doc = self.docs.remove(&self.current_index).map(String::into_boxed_str);
+
         // If the next token is one of these, consume it and call the relevant function, which will parse the rest of the statement.
-        if self.check_and_consume(&[TokenType::Break]).is_some() {
-            Ok(Stmt {
+        let mut stmt = if self.check_and_consume(&[TokenType::Assert]).is_some() {
+            self.assert()?
+        } else if self.check_and_consume(&[TokenType::Break]).is_some() {
+            Stmt {
                 line: self.current_line,
                 stmt_type: StmtType::Break
-            })
+            }
         } else if self.check_and_consume(&[TokenType::For]).is_some() {
-            self.for_()
+            self.for_()?
         } else if self.check_and_consume(&[TokenType::Func]).is_some() {
-            self.function()
+            self.function(doc)?
         } else if self.check_and_consume(&[TokenType::If]).is_some() {
-            self.if_()
+            self.if_()?
         } else if self.check_and_consume(&[TokenType::Print]).is_some() {
-            self.print()
+            self.print()?
         } else if self.check_and_consume(&[TokenType::Return]).is_some() {
-            self.return_()
+            self.return_()?
         } else if self.check_and_consume(&[TokenType::Var]).is_some() {
-            self.var()
+            self.var(doc)?
         } else if self.check_and_consume(&[TokenType::While]).is_some() {
-            self.while_()
+            self.while_()?
+        } else if self.check_and_consume(&[TokenType::Else]).is_some() {
+            // An `else` can only be parsed as part of an `if` statement (see `if_()`), so there must be no `if` before it.
+            return Err(ErrorType::ElseWithoutIf { line: self.current_line });
+        } else if self.check_and_consume(&[TokenType::Semicolon]).is_some() {
+            // Statements are not terminated by semicolons, but this is a common habit from other languages.
+            return Err(ErrorType::UnexpectedSemicolon { line: self.current_line });
         } else {
-            Ok(Stmt {
+            Stmt {
                 line: self.current_line,
                 stmt_type: StmtType::Expression {
                     expression: self.expression()?
                 }
-            })
-        }
+            }
+        };
+
+        stmt.line = line;
+        Ok(stmt)
     }
 
     /// <block> ::= LeftCurly <statement>* RightCurly
     fn block(&mut self) -> Result<Stmt, ErrorType> {
         // Consume LeftCurly if it follows; otherwise, raise an error.
         self.expect(TokenType::LeftCurly, '{')?;
+        let opened_line = self.current_line;
         
         // Parse <statement>*.
         let mut statements: Vec<Stmt> = Vec::new();
         while !self.check_next(&[TokenType::RightCurly, TokenType::Eof]) {
             // Keep parsing statements until the next token is a RightCurly or we have reached the end of the sequence of tokens.
-            statements.push(self.statement()?);
+            statements.push(self.nested(Self::statement)?);
         }
 
         // Consume RightCurly.
-        self.expect(TokenType::RightCurly, '}')?;
+        self.expect_closing(TokenType::RightCurly, '}', opened_line)?;
         Ok(Stmt {
             line: self.current_line,
             stmt_type: StmtType::Block {
@@ -125,6 +220,7 @@ impl Parser {
 
     /// <for> ::= LeftParen <statement>? Semicolon <expression>? Semicolon <statement>? RightParen <block>
     fn for_(&mut self) -> Result<Stmt, ErrorType> {
+        let branch = self.next_branch();
         // Consume LeftParen.
         self.expect(TokenType::LeftParen, '(')?;
 
@@ -150,7 +246,7 @@ impl Parser {
         };
         if !self.check_next(&[TokenType::Semicolon]) {
             // If the next token is not a Semicolon, we parse it as the <expression>.
-            condition = self.expression()?;
+            condition = self.condition()?;
         }
 
         // Consume Semicolon if it follows.
@@ -204,7 +300,8 @@ impl Parser {
             line: self.current_line,
             stmt_type: StmtType::While {
                 condition,
-                body: Box::new(while_body)
+                body: Box::new(while_body),
+                branch,
             }
         };
         
@@ -221,12 +318,14 @@ impl Parser {
     }
 
     /// <function> ::= Identifier LeftParen (Identifier (Comma Identifier)*)? RightParen <block>
-    fn function(&mut self) -> Result<Stmt, ErrorType> {
+    /// `doc` is the documentation comment before the function, if any.
+    fn function(&mut self, doc: Doc) -> Result<Stmt, ErrorType> {
         if let Some(function_name_token) = self.check_and_consume(&[TokenType::Identifier]) {
             // If an Identifier was given (the name of the function), consume it.
 
             // Consume LeftParen.
             self.expect(TokenType::LeftParen, '(')?;
+            let opened_line = self.current_line;
 
             // Parse (Identifier (Comma Identifier)*)?, i.e., collect an array of strings for the parameters.
             let mut parameters: Vec<String> = Vec::new();
@@ -238,7 +337,7 @@ impl Parser {
                         parameters.push(parameter.lexeme);
                     } else {
                         // Otherwise, raise a specific error, as a parameter must be given after a comma.
-                        return Err(ErrorType::ExpectedParameterName { line: self.current_line });
+                        return Err(self.keyword_as_name().unwrap_or(ErrorType::ExpectedParameterName { line: self.current_line }));
                     }
 
                     // If a Comma does not follow a parameter, then there should be no more parameters.
@@ -250,7 +349,7 @@ impl Parser {
             }
 
             // Consume RightParen.
-            self.expect(TokenType::RightParen, ')')?;
+            self.expect_closing(TokenType::RightParen, ')', opened_line)?;
 
             // Parse <block>, the body of the function.
             let body = self.block()?;
@@ -261,24 +360,27 @@ impl Parser {
                     name: function_name_token.lexeme,
                     parameters,
                     body: Box::new(body),
+                    doc,
                 }
             })
         } else {
             // If an Identifier was not given, raise a specific error.
-            Err(ErrorType::ExpectedFunctionName { line: self.current_line })
+            Err(self.keyword_as_name().unwrap_or(ErrorType::ExpectedFunctionName { line: self.current_line }))
         }
     }
 
     /// <if> ::= LeftParen <expression> RightParen <block> (Else <else>)?
     fn if_(&mut self) -> Result<Stmt, ErrorType> {
+        let branch = self.next_branch();
         // Consume LeftParen.
         self.expect(TokenType::LeftParen, '(')?;
+        let opened_line = self.current_line;
 
         // Parse <expression>, the condition of the `if` statement.
-        let condition = self.expression()?;
+        let condition = self.condition()?;
 
         // Consume RightParen.
-        self.expect(TokenType::RightParen, ')')?;
+        self.expect_closing(TokenType::RightParen, ')', opened_line)?;
 
         // Parse <block>, the `then` body of the `if` statement.
         let then_body = self.block()?;
@@ -292,6 +394,7 @@ impl Parser {
                     condition,
                     then_body: Box::new(then_body),
                     else_body: Some(Box::new(else_body)),
+                    branch,
                 }
             })
         } else {
@@ -302,6 +405,7 @@ impl Parser {
                     condition,
                     then_body: Box::new(then_body),
                     else_body: None,
+                    branch,
                 }
             })
         }
@@ -312,13 +416,36 @@ impl Parser {
         // After an `else`, there can either be another block, which ends the `if` statement and creates the `else` body, or an `if` to make an `else if`.
         if self.check_and_consume(&[TokenType::If]).is_some() {
             // If there is an If token, consume it, then parse <if> to create an `else if`.
-            Ok(self.if_()?)
+            self.nested(Self::if_)
         } else {
             // Otherwise, just parse the `else` block.
-            Ok(self.block()?)
+            self.block()
         }
     }
 
+    /// <assert> ::= <expression> (Comma <expression>)?
+    fn assert(&mut self) -> Result<Stmt, ErrorType> {
+        let start_index = self.current_index;
+        let condition = self.condition()?;
+        // The source code of the condition is kept to be quoted if the assertion fails.
+        let condition_text = source_text(&self.tokens[start_index..self.current_index]);
+
+        let message = if self.check_and_consume(&[TokenType::Comma]).is_some() {
+            Some(Box::new(self.expression()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt {
+            line: self.current_line,
+            stmt_type: StmtType::Assert {
+                condition,
+                message,
+                condition_text,
+            }
+        })
+    }
+
     /// <print> ::= <expression>
     fn print(&mut self) -> Result<Stmt, ErrorType> {
         Ok(Stmt {
@@ -340,7 +467,8 @@ impl Parser {
     }
 
     /// <var> ::= Identifier Equal <expression>
-    fn var(&mut self) -> Result<Stmt, ErrorType> {
+    /// `doc` is the documentation comment before the declaration, if any.
+    fn var(&mut self, doc: Doc) -> Result<Stmt, ErrorType> {
         if let Some(target_variable_token) = self.check_and_consume(&[TokenType::Identifier]) {
             // If an Identifier was given (the target variable name), consume it.
 
@@ -354,24 +482,27 @@ impl Parser {
                 stmt_type: StmtType::VarDecl {
                     name: target_variable_token.lexeme,
                     value,
+                    doc,
                 }
             })
         } else {
             // If an Identifier was not given, raise a specific error.
-            Err(ErrorType::ExpectedVariableName { line: self.current_line })
+            Err(self.keyword_as_name().unwrap_or(ErrorType::ExpectedVariableName { line: self.current_line }))
         }
     }
 
     /// <while> ::= LeftParen <expression> RightParen <block>
     fn while_(&mut self) -> Result<Stmt, ErrorType> {
+        let branch = self.next_branch();
         // Consume LeftParen.
         self.expect(TokenType::LeftParen, '(')?;
+        let opened_line = self.current_line;
         
         // Parse <expression>, the condition of the `while` loop.
-        let condition = self.expression()?;
+        let condition = self.condition()?;
 
         // Consume RightParen.
-        self.expect(TokenType::RightParen, ')')?;
+        self.expect_closing(TokenType::RightParen, ')', opened_line)?;
 
         // Parse <block>, the body of the `while` loop.
         let body = self.block()?;
@@ -381,14 +512,25 @@ impl Parser {
             stmt_type: StmtType::While {
                 condition,
                 body: Box::new(body),
+                branch,
             }
         })
     }
 
+    /// Parses the condition of an `if` or `assert` statement or a loop, which is an <expression>.
+    fn condition(&mut self) -> Result<Expr, ErrorType> {
+        let condition = self.expression()?;
+        if let ExprType::Assignment {..} = condition.expr_type {
+            // An assignment in a condition is almost certainly a mistyped `==`.
+            return Err(ErrorType::AssignmentInCondition { line: condition.line });
+        }
+        Ok(condition)
+    }
+
     /// Parses an expression.
     /// <expression> ::= <assignment>
     fn expression(&mut self) -> Result<Expr, ErrorType> {
-        self.assignment()
+        self.nested(Self::assignment)
     }
 
     /// <assignment> ::= <or> (Equal <assignment>)?
@@ -404,7 +546,7 @@ impl Parser {
             // If an Equal was given, consume it.
 
             // Recursively parse <assignment>.
-            let value = self.assignment()?;
+            let value = self.nested(Self::assignment)?;
             
             Ok(Expr {
                 line: self.current_line,
@@ -551,7 +693,7 @@ impl Parser {
             // If the current token is either Bang or Minus, consume it.
 
             // Recursively parse <unary>.
-            let right = self.unary()?;
+            let right = self.nested(Self::unary)?;
             Ok(Expr {
                 line: self.current_line,
                 expr_type: ExprType::Unary {
@@ -572,6 +714,7 @@ impl Parser {
         
         while self.check_and_consume(&[TokenType::LeftSquare]).is_some() {
             // While the following token is LeftSquare, consume it.
+            let opened_line = self.current_line;
 
             // Parse <expression>, i.e., the 'index' part of an element (`1+2` in `a[1+2]`).
             let index = self.expression()?;
@@ -584,7 +727,7 @@ impl Parser {
             };
 
             // Consume the closing RightSquare of an index.
-            self.expect(TokenType::RightSquare, ']')?;
+            self.expect_closing(TokenType::RightSquare, ']', opened_line)?;
         }
         Ok(expr)
     }
@@ -596,6 +739,7 @@ impl Parser {
 
         while self.check_and_consume(&[TokenType::LeftParen]).is_some() {
             // While the following token is LeftParen, consume it.
+            let opened_line = self.current_line;
 
             // Collect the arguments of the function call into an array.
             let mut arguments: Vec<Expr> = Vec::new();
@@ -613,7 +757,10 @@ impl Parser {
             }
 
             // Consume the closing RightParen.
-            self.expect(TokenType::RightParen, ')')?;
+            // If it is missing, raise a specific error pointing to where the call was opened, as the arguments may span many lines.
+            if self.check_and_consume(&[TokenType::RightParen]).is_none() {
+                return Err(ErrorType::UnclosedCall { opened_line, line: self.current_line });
+            }
 
             expr = Expr {
                 line: self.current_line,
@@ -652,12 +799,13 @@ impl Parser {
 
         } else if self.check_and_consume(&[TokenType::LeftParen]).is_some() {
             // Grouping.
+            let opened_line = self.current_line;
 
             // Parse <expression>.
             let expr = self.expression()?;
 
             // Consume the closing RightParen.
-            self.expect(TokenType::RightParen, ')')?;
+            self.expect_closing(TokenType::RightParen, ')', opened_line)?;
 
             Ok(Expr {
                 line: self.current_line,
@@ -668,6 +816,7 @@ impl Parser {
 
         } else if self.check_and_consume(&[TokenType::LeftSquare]).is_some() {
             // Array.
+            let opened_line = self.current_line;
 
             // Collect the expressions of the array elements into an array.
             let mut elements: Vec<Expr> = Vec::new();
@@ -685,7 +834,7 @@ impl Parser {
             }
             
             // Consume the closing RightSquare.
-            self.expect(TokenType::RightSquare, ']')?;
+            self.expect_closing(TokenType::RightSquare, ']', opened_line)?;
             Ok(Expr {
                 line: self.current_line,
                 expr_type: ExprType::Array { 
@@ -695,6 +844,7 @@ impl Parser {
 
         } else if self.check_and_consume(&[TokenType::LeftCurly]).is_some() {
             // Dictionary.
+            let opened_line = self.current_line;
 
             // Collect the expressions for the key-value pairs of the dictionary into an array.
             let mut elements: Vec<KeyValue<Expr>> = Vec::new();
@@ -727,7 +877,7 @@ impl Parser {
             }
 
             // Consume the closing RightCurly.
-            self.expect(TokenType::RightCurly, '}')?;
+            self.expect_closing(TokenType::RightCurly, '}', opened_line)?;
             Ok(Expr {
                 line: self.current_line,
                 expr_type: ExprType::Dictionary {
@@ -746,6 +896,24 @@ impl Parser {
                 }
             })
 
+        } else if self.check_and_consume(&[TokenType::Error]).is_some() {
+            // The tokenizer could not make sense of these characters and has already recorded the error.
+            // Treat them as a placeholder expression so that the rest of the source code can still be checked
+            // without raising further errors caused by the same mistake.
+            Ok(Expr {
+                line: self.current_line,
+                expr_type: ExprType::Literal {
+                    value: Literal::Null
+                }
+            })
+
+        } else if let Some(bracket) = self.check_and_consume(&[TokenType::RightParen, TokenType::RightSquare, TokenType::RightCurly]) {
+            // A closing bracket cannot start an expression, so there must be no opening bracket before it.
+            Err(ErrorType::UnexpectedClosingBracket {
+                bracket: bracket.lexeme.chars().next().unwrap_or(')'),
+                line: self.current_line,
+            })
+
         } else {
             // If no rule matches the token, then we expected an expression but was not given one.
             // So, raise an ExpectedExpression error.
@@ -753,6 +921,29 @@ impl Parser {
         }
     }
 
+    /// Returns the token at fault for an error which was just raised, before synchronising.
+    fn error_token(&self, error: &ErrorType) -> Option<&Token> {
+        let previous = self.current_index.checked_sub(1).and_then(|index| self.tokens.get(index));
+        let next = self.tokens.get(self.current_index);
+        match error {
+            // These errors are raised once the token at fault has been consumed.
+            ErrorType::ElseWithoutIf {..} |
+            ErrorType::UnexpectedSemicolon {..} |
+            ErrorType::UnexpectedClosingBracket {..} |
+            ErrorType::KeywordAsName {..} => previous,
+            // The condition has been consumed, so the `=` of the assignment is found by looking back through it.
+            ErrorType::AssignmentInCondition { line } => {
+                self.tokens[..self.current_index].iter().rev().find(|token| token.type_ == TokenType::Equal && token.line == *line).or(previous)
+            },
+            // The others are raised at the next token, which is not what was expected. If it is on a later line, e.g., a closing
+            // bracket is missing at the end of a line, the error is reported on the line before, so the last token there is pointed at.
+            _ => match next {
+                Some(token) if token.line == error.line() => Some(token),
+                _ => previous.or(next),
+            },
+        }
+    }
+
     /// Returns `Some(token)` and advances the pointer if the type of the next token is one of the `expected_types`.
     /// Otherwise, or if we are at the end of the sequence of tokens, return `None`.
     fn check_and_consume(&mut self, expected_types: &[TokenType]) -> Option<Token> {
@@ -774,6 +965,11 @@ impl Parser {
         }
     }
 
+    /// Returns the line number of the next token, or the current line number if we are at the end of the sequence of tokens.
+    fn next_line(&self) -> usize {
+        self.tokens.get(self.current_index).map_or(self.current_line, |token| token.line)
+    }
+
     /// Returns `true` if the type of the next token is one of the `expected_types`.
     /// Otherwise, or if we are at the end of the sequence of tokens, return `false`.
     /// The difference between this and `check_and_consume()` is that this does not advance the pointer if the token matches what is expected.
@@ -801,17 +997,105 @@ impl Parser {
         }
         Ok(())
     }
+
+    /// As `expect()`, but for the closing bracket matching an opening bracket consumed on `opened_line`.
+    /// If the closing bracket is missing, the error includes where the bracket was opened, as this may be many lines before.
+    fn expect_closing(&mut self, expected_type: TokenType, expected_char: char, opened_line: usize) -> Result<(), ErrorType> {
+        if self.check_and_consume(&[expected_type]).is_none() {
+            return Err(ErrorType::UnclosedBracket {
+                expected: expected_char,
+                opened_line,
+                line: self.current_line,
+            });
+        }
+        Ok(())
+    }
+
+    /// Calls `parse_fn` one level of nesting deeper, raising an error if the maximum nesting depth would be exceeded.
+    /// This wraps every recursive call which makes the abstract syntax tree deeper. If the stack is about to run out,
+    /// `parse_fn` is called on a new segment of it, as the thread parsing may have a small stack.
+    fn nested<T>(&mut self, parse_fn: fn(&mut Self) -> Result<T, ErrorType>) -> Result<T, ErrorType> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err(ErrorType::NestingTooDeep { depth: MAX_NESTING_DEPTH, line: self.next_line() });
+        }
+        self.depth += 1;
+        let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || parse_fn(self));
+        self.depth -= 1;
+        result
+    }
+
+    /// Returns a `KeywordAsName` error if the next token is a keyword, which was presumably meant to be a name.
+    /// The keyword is consumed.
+    fn keyword_as_name(&mut self) -> Option<ErrorType> {
+        let token = self.tokens.get(self.current_index)?;
+        if token::KEYWORDS.contains(&token.lexeme.as_str()) {
+            let keyword = token.lexeme.clone();
+            let line = token.line;
+            self.current_index += 1;
+            self.current_line = line;
+            Some(ErrorType::KeywordAsName { keyword, line })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number identifying the next `if` or `while` statement, counting from 0 in the order they appear,
+    /// as each is numbered before the statements within it.
+    /// Unlike its line and condition, this tells apart, e.g., two `if (a)` on the same line, so coverage can be recorded for each.
+    fn next_branch(&mut self) -> usize {
+        self.branches += 1;
+        self.branches - 1
+    }
+}
+
+/// Returns `true` if the error is raised when the parser expects another token, rather than when it finds an invalid one.
+fn expects_more(error: &ErrorType) -> bool {
+    matches!(error,
+        ErrorType::ExpectedCharacter {..} |
+        ErrorType::ExpectedExpression {..} |
+        ErrorType::ExpectedFunctionName {..} |
+        ErrorType::ExpectedParameterName {..} |
+        ErrorType::ExpectedVariableName {..} |
+        ErrorType::ExpectedSemicolonAfterInit {..} |
+        ErrorType::ExpectedSemicolonAfterCondition {..} |
+        ErrorType::ExpectedParenAfterIncrement {..} |
+        ErrorType::ExpectedColonAfterKey {..} |
+        ErrorType::UnclosedBracket {..} |
+        ErrorType::UnclosedCall {..}
+    )
+}
+
+/// Returns the source code of a sequence of tokens as it was written, e.g., to quote an expression in a message.
+/// Tokens on the same line are separated by as many spaces as their columns are apart, which is the original text
+/// unless it had tabs between them, and tokens on different lines by a single space, so the text is on one line.
+fn source_text(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&Token> = None;
+    for token in tokens {
+        match previous {
+            Some(previous) if previous.line == token.line => {
+                let end = previous.column + previous.lexeme.chars().count();
+                text.push_str(&" ".repeat(token.column.saturating_sub(end)));
+            },
+            Some(..) => text.push(' '),
+            None => {},
+        }
+        text.push_str(&token.lexeme);
+        previous = Some(token);
+    }
+    text
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{token, expr::{Expr, ExprType}, error::ErrorType, tokenizer::Tokenizer, stmt::Stmt, stmt::StmtType};
 
-    use super::Parser;
+    use super::{Parser, MAX_NESTING_DEPTH};
 
     fn parse(source: &str) -> Result<Vec<Stmt>, Vec<ErrorType>> {
         let mut tokenizer = Tokenizer::new(source);
-        let tokens = tokenizer.tokenize().expect("Tokenizer returned error.");
+        let (tokens, tokenizer_errors) = tokenizer.tokenize();
+        assert!(tokenizer_errors.is_empty(), "Tokenizer returned error.");
         let mut parser = Parser::new(tokens);
         parser.parse()
     }
@@ -836,11 +1120,12 @@ mod tests {
                 Stmt { line: 1, stmt_type: StmtType::VarDecl {
                     name: String::from("x"),
                     value: Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(5.0) }},
+                    doc: None,
                 }},
                 Stmt { line: 1, stmt_type: StmtType::While {
                     condition: Expr { line: 1, expr_type: ExprType::Binary {
                         left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }}),
-                        operator: token::Token { type_: token::TokenType::Less, lexeme: String::from("<"), literal: token::Literal::Null, line: 1 },
+                        operator: token::Token { type_: token::TokenType::Less, lexeme: String::from("<"), literal: token::Literal::Null, line: 1, column: 19 },
                         right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(10.0) }}),
                     }},
                     body: Box::new(Stmt { line: 1, stmt_type: StmtType::Block {
@@ -850,6 +1135,7 @@ mod tests {
                                     Stmt { line: 1, stmt_type: StmtType::VarDecl {
                                         name: String::from("y"),
                                         value: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }},
+                                        doc: None,
                                     }},
                                 ],
                             }},
@@ -857,12 +1143,13 @@ mod tests {
                                 target: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }}),
                                 value: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                                     left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }}),
-                                    operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1 },
+                                    operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1, column: 31 },
                                     right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(1.0) }}),
                                 }}),
                             }}}},
                         ],
                     }}),
+                    branch: 0,
                 }},
             ]
         }}]), parse(source));
@@ -875,7 +1162,7 @@ mod tests {
             Stmt { line: 1, stmt_type: StmtType::While {
                 condition: Expr { line: 1, expr_type: ExprType::Binary {
                     left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }}),
-                    operator: token::Token { type_: token::TokenType::Less, lexeme: String::from("<"), literal: token::Literal::Null, line: 1 },
+                    operator: token::Token { type_: token::TokenType::Less, lexeme: String::from("<"), literal: token::Literal::Null, line: 1, column: 10 },
                     right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(10.0) }}),
                 }},
                 body: Box::new(Stmt { line: 1, stmt_type: StmtType::Block {
@@ -885,6 +1172,7 @@ mod tests {
                                 Stmt { line: 1, stmt_type: StmtType::VarDecl {
                                     name: String::from("y"),
                                     value: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }},
+                                    doc: None,
                                 }},
                             ],
                         }},
@@ -892,12 +1180,13 @@ mod tests {
                             target: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }}),
                             value: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                                 left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }}),
-                                operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1 },
+                                operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1, column: 22 },
                                 right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(1.0) }}),
                             }}),
                         }}}},
                     ],
                 }}),
+                branch: 0,
             }},
         ]), parse(source));
     }
@@ -910,6 +1199,7 @@ mod tests {
                 Stmt { line: 1, stmt_type: StmtType::VarDecl {
                     name: String::from("x"),
                     value: Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(5.0) }},
+                    doc: None,
                 }},
                 Stmt { line: 1, stmt_type: StmtType::While {
                     condition: Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Bool(true) }},
@@ -920,6 +1210,7 @@ mod tests {
                                     Stmt { line: 1, stmt_type: StmtType::VarDecl {
                                         name: String::from("y"),
                                         value: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }},
+                                        doc: None,
                                     }},
                                 ],
                             }},
@@ -927,12 +1218,13 @@ mod tests {
                                 target: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }}),
                                 value: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                                     left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }}),
-                                    operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1 },
+                                    operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1, column: 24 },
                                     right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(1.0) }}),
                                 }}),
                             }}}},
                         ],
                     }}),
+                    branch: 0,
                 }},
             ]
         }}]), parse(source));
@@ -946,11 +1238,12 @@ mod tests {
                 Stmt { line: 1, stmt_type: StmtType::VarDecl {
                     name: String::from("x"),
                     value: Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(5.0) }},
+                    doc: None,
                 }},
                 Stmt { line: 1, stmt_type: StmtType::While {
                     condition: Expr { line: 1, expr_type: ExprType::Binary {
                         left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }}),
-                        operator: token::Token { type_: token::TokenType::Less, lexeme: String::from("<"), literal: token::Literal::Null, line: 1 },
+                        operator: token::Token { type_: token::TokenType::Less, lexeme: String::from("<"), literal: token::Literal::Null, line: 1, column: 19 },
                         right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(10.0) }}),
                     }},
                     body: Box::new(Stmt { line: 1, stmt_type: StmtType::Block {
@@ -960,11 +1253,13 @@ mod tests {
                                     Stmt { line: 1, stmt_type: StmtType::VarDecl {
                                         name: String::from("y"),
                                         value: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }},
+                                        doc: None,
                                     }},
                                 ],
                             }},
                         ],
                     }}),
+                    branch: 0,
                 }},
             ]
         }}]), parse(source));
@@ -997,7 +1292,7 @@ mod tests {
     #[test]
     fn unclosed_block() {
         let source = "for (var x = 5; x < 10; x = x + 1) {var y = x";
-        assert!(errors_in_result(parse(source), vec![ErrorType::ExpectedCharacter { expected: '}', line: 1 }]));
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnclosedBracket { expected: '}', opened_line: 1, line: 1 }]));
     }
     
     #[test]
@@ -1010,13 +1305,46 @@ mod tests {
                 Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") }}}},
                 Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("b") }}}},
             ]}}),
+            doc: None,
         }}]), parse(source));
     }
 
+    #[test]
+    fn doc_comments() {
+        let source = "\
+## Adds two numbers.
+##
+##  Indented.
+func add(a, b) {
+    ## The sum.
+    var sum = a + b  ## Not documentation.
+    return sum
+}
+## Separated from the declaration.
+
+var x = 1
+## Not a declaration.
+print x
+## The last.
+var y = 2";
+        let ast = parse(source).unwrap();
+        let docs: Vec<Option<String>> = ast.iter()
+            .filter_map(|stmt| match &stmt.stmt_type {
+                StmtType::Function { doc, .. } | StmtType::VarDecl { doc, .. } => Some(doc.as_deref().map(String::from)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(docs, vec![Some(String::from("Adds two numbers.\n\n Indented.")), None, Some(String::from("The last."))]);
+
+        let StmtType::Function { body, .. } = &ast[0].stmt_type else { panic!() };
+        let StmtType::Block { body } = &body.stmt_type else { panic!() };
+        assert!(matches!(&body[0].stmt_type, StmtType::VarDecl { doc: Some(doc), .. } if &**doc == "The sum."));
+    }
+
     #[test]
     fn func_keyword_name() {
         let source = "func print(a, b) {print a print b}";
-        assert!(errors_in_result(parse(source), vec![ErrorType::ExpectedFunctionName { line: 1 }]));
+        assert!(errors_in_result(parse(source), vec![ErrorType::KeywordAsName { keyword: String::from("print"), line: 1 }]));
     }
 
     #[test]
@@ -1025,11 +1353,12 @@ mod tests {
         assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::If {
             condition: Expr { line: 1, expr_type: ExprType::Binary {
                 left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") }}),
-                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1, column: 7 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(2.0) }}),
             }},
             then_body: Box::new(Stmt { line: 1, stmt_type: StmtType::Block { body: vec![Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") } }}}] }}),
             else_body: None,
+            branch: 0,
         }}]), parse(source));
     }
 
@@ -1039,7 +1368,7 @@ mod tests {
         assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::If {
             condition: Expr { line: 1, expr_type: ExprType::Binary {
                 left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") }}),
-                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1, column: 7 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(2.0) }}),
             }},
             then_body: Box::new(Stmt { line: 1, stmt_type: StmtType::Block { body: vec![Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") } }}}] }}),
@@ -1047,7 +1376,7 @@ mod tests {
                 Stmt { line: 1, stmt_type: StmtType::If {
                     condition: Expr { line: 1, expr_type: ExprType::Binary {
                         left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") }}),
-                        operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1 },
+                        operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1, column: 34 },
                         right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(3.0) }}),
                     }},
                     then_body: Box::new(Stmt { line: 1, stmt_type: StmtType::Block { body: vec![Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("b") } }}}]} }),
@@ -1055,15 +1384,18 @@ mod tests {
                         Stmt { line: 1, stmt_type: StmtType::If {
                             condition: Expr { line: 1, expr_type: ExprType::Binary {
                                 left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") }}),
-                                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1 },
+                                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1, column: 61 },
                                 right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(4.0) }}),
                             }},
                             then_body: Box::new(Stmt { line: 1, stmt_type: StmtType::Block { body: vec![Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("c") } }}}]} }),
                             else_body: None,
+                            branch: 2,
                         }}
                     )),
+                    branch: 1,
                 }}
             )),
+            branch: 0,
         }}]), parse(source));
     }
 
@@ -1073,11 +1405,12 @@ mod tests {
         assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::If {
             condition: Expr { line: 1, expr_type: ExprType::Binary {
                 left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") }}),
-                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1, column: 7 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(2.0) }}),
             }},
             then_body: Box::new(Stmt { line: 1, stmt_type: StmtType::Block { body: vec![Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") } }}}]} }),
             else_body: Some(Box::new(Stmt { line: 1, stmt_type: StmtType::Block { body: vec![Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("b") } }}}]} })),
+            branch: 0,
         }}]), parse(source));
     }
 
@@ -1087,20 +1420,20 @@ mod tests {
         assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Binary {
             left: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                 left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(5.0) }}),
-                operator: token::Token { type_: token::TokenType::Star, lexeme: String::from("*"), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::Star, lexeme: String::from("*"), literal: token::Literal::Null, line: 1, column: 8 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(1.0) }}),
             }}),
-            operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1 },
+            operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1, column: 10 },
             right: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                 left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(2.0) }}),
-                operator: token::Token { type_: token::TokenType::Star, lexeme: String::from("*"), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::Star, lexeme: String::from("*"), literal: token::Literal::Null, line: 1, column: 12 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Grouping {
                     expression: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                         left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(3.0) }}),
-                        operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1 },
+                        operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1, column: 15 },
                         right: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                             left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(4.0) }}),
-                            operator: token::Token { type_: token::TokenType::Slash, lexeme: String::from("/"), literal: token::Literal::Null, line: 1 },
+                            operator: token::Token { type_: token::TokenType::Slash, lexeme: String::from("/"), literal: token::Literal::Null, line: 1, column: 17 },
                             right: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") }}),
                         }}),
                     }}),
@@ -1112,7 +1445,7 @@ mod tests {
     #[test]
     fn var() {
         let source = "var a = 5";
-        assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::VarDecl { name: String::from("a"), value: Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(5.0) } }}}]), parse(source));
+        assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::VarDecl { name: String::from("a"), value: Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(5.0) } }, doc: None}}]), parse(source));
     }
 
     #[test]
@@ -1127,10 +1460,11 @@ mod tests {
         assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::While {
             condition: Expr { line: 1, expr_type: ExprType::Binary {
                 left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") }}),
-                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1, column: 10 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(2.0) }}),
             }},
             body: Box::new(Stmt { line: 1, stmt_type: StmtType::Block { body: vec![Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("b") } }}}]} }),
+            branch: 0,
         }}]), parse(source));
     }
 
@@ -1142,13 +1476,14 @@ mod tests {
             Stmt { line: 1, stmt_type: StmtType::If {
                 condition: Expr { line: 1, expr_type: ExprType::Binary {
                     left: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") }}),
-                    operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1 },
+                    operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1, column: 15 },
                     right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(2.0) }}),
                 }},
                 then_body: Box::new(Stmt { line: 1, stmt_type: StmtType::Block { body: vec![Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") } }}}]} }),
                 else_body: Some(Box::new(Stmt { line: 1, stmt_type: StmtType::Block { body: vec![Stmt { line: 1, stmt_type: StmtType::Print { expression: Expr { line: 1, expr_type: ExprType::Variable { name: String::from("b") } }}}]} })),
+                branch: 0,
             }},
-            Stmt { line: 1, stmt_type: StmtType::VarDecl { name: String::from("c"), value: Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(3.0) } } } },
+            Stmt { line: 1, stmt_type: StmtType::VarDecl { name: String::from("c"), value: Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(3.0) } }, doc: None } },
         ]), parse(source));
     }
 
@@ -1158,20 +1493,20 @@ mod tests {
         assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::Expression { expression: Expr { line: 1, expr_type: ExprType::Binary {
             left: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                 left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(5.0) }}),
-                operator: token::Token { type_: token::TokenType::Star, lexeme: String::from("*"), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::Star, lexeme: String::from("*"), literal: token::Literal::Null, line: 1, column: 2 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(1.0) }}),
             }}),
-            operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1 },
+            operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1, column: 4 },
             right: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                 left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(2.0) }}),
-                operator: token::Token { type_: token::TokenType::Star, lexeme: String::from("*"), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::Star, lexeme: String::from("*"), literal: token::Literal::Null, line: 1, column: 6 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Grouping {
                     expression: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                         left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(3.0) }}),
-                        operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1 },
+                        operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1, column: 9 },
                         right: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                             left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(4.0) }}),
-                            operator: token::Token { type_: token::TokenType::Slash, lexeme: String::from("/"), literal: token::Literal::Null, line: 1 },
+                            operator: token::Token { type_: token::TokenType::Slash, lexeme: String::from("/"), literal: token::Literal::Null, line: 1, column: 11 },
                             right: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("a") }}),
                     }}),
                     }}),
@@ -1187,17 +1522,17 @@ mod tests {
             left: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                 left: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                     left: Box::new(Expr { line: 1, expr_type: ExprType::Literal {value: token::Literal::Bool(true) }}),
-                    operator: token::Token { type_: token::TokenType::And, lexeme: String::from("and"), literal: token::Literal::Null, line: 1 },
+                    operator: token::Token { type_: token::TokenType::And, lexeme: String::from("and"), literal: token::Literal::Null, line: 1, column: 6 },
                     right: Box::new(Expr { line: 1, expr_type: ExprType::Literal {value: token::Literal::Bool(true) }}),
                 }}),
-                operator: token::Token { type_: token::TokenType::Or, lexeme: String::from("or"), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::Or, lexeme: String::from("or"), literal: token::Literal::Null, line: 1, column: 15 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                     left: Box::new(Expr { line: 1, expr_type: ExprType::Literal {value: token::Literal::Bool(false) }}),
-                    operator: token::Token { type_: token::TokenType::And, lexeme: String::from("and"), literal: token::Literal::Null, line: 1 },
+                    operator: token::Token { type_: token::TokenType::And, lexeme: String::from("and"), literal: token::Literal::Null, line: 1, column: 24 },
                     right: Box::new(Expr { line: 1, expr_type: ExprType::Literal {value: token::Literal::Bool(true) }}),
                 }}),
             }}),
-            operator: token::Token { type_: token::TokenType::Or, lexeme: String::from("or"), literal: token::Literal::Null, line: 1 },
+            operator: token::Token { type_: token::TokenType::Or, lexeme: String::from("or"), literal: token::Literal::Null, line: 1, column: 33 },
             right: Box::new(Expr { line: 1, expr_type: ExprType::Literal {value: token::Literal::Bool(false) }}),
         }}}}]), parse(source));
     }
@@ -1216,7 +1551,7 @@ mod tests {
                 }},
                 Expr { line: 1, expr_type: ExprType::Binary {
                     left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(3.0) }}),
-                    operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1 },
+                    operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1, column: 14 },
                     right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(1.0) }}),
                 }},
                 Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::String_(String::from("g")) }},
@@ -1233,23 +1568,39 @@ mod tests {
     #[test]
     fn unclosed_array() {
         let source = "[[5, a, b], 3+1, \"g\"";
-        assert!(errors_in_result(parse(source), vec![ErrorType::ExpectedCharacter { expected: ']', line: 1 }]));
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnclosedBracket { expected: ']', opened_line: 1, line: 1 }]));
         let source = "[[5, a, b, 3+1, \"g\"]";
-        assert!(errors_in_result(parse(source), vec![ErrorType::ExpectedCharacter { expected: ']', line: 1 }]));
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnclosedBracket { expected: ']', opened_line: 1, line: 1 }]));
     }
     
     #[test]
     fn error_line_numbers() {
         let source = "\n[[5, a, b, 3+1, \"g\"]";
-        assert!(errors_in_result(parse(source), vec![ErrorType::ExpectedCharacter { expected: ']', line: 2 }]));
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnclosedBracket { expected: ']', opened_line: 2, line: 2 }]));
         let source = "\n\n[[5, a, b, 3+1, \"g\"]";
-        assert!(errors_in_result(parse(source), vec![ErrorType::ExpectedCharacter { expected: ']', line: 3 }]));
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnclosedBracket { expected: ']', opened_line: 3, line: 3 }]));
+    }
+
+    #[test]
+    fn error_tokens() {
+        let error_columns = |source: &str| {
+            let (tokens, _) = Tokenizer::new(source).tokenize();
+            let mut parser = Parser::new(tokens);
+            let _ = parser.parse();
+            parser.error_tokens().iter().map(|token| token.as_ref().map(|token| (token.line, token.column))).collect::<Vec<_>>()
+        };
+        // The unexpected token, or the token before a missing one at the end of a line.
+        assert_eq!(error_columns("var = 2"), vec![Some((1, 5))]);
+        assert_eq!(error_columns("print (1 + 2\nprint 3"), vec![Some((1, 12))]);
+        // Tokens which were consumed before the error was raised.
+        assert_eq!(error_columns("else {\n}\nvar if = 1"), vec![Some((1, 1)), Some((3, 5))]);
+        assert_eq!(error_columns("while (a = b + 1) {\n}"), vec![Some((1, 10))]);
     }
 
     #[test]
     fn unclosed_grouping() {
         let source = "(5 + 5";
-        assert!(errors_in_result(parse(source), vec![ErrorType::ExpectedCharacter { expected: ')', line: 1 }]));
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnclosedBracket { expected: ')', opened_line: 1, line: 1 }]));
     }
 
     #[test]
@@ -1280,25 +1631,25 @@ mod tests {
             left: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                 left: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                     left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(1.0) }}),
-                    operator: token::Token { type_: token::TokenType::Less, lexeme: String::from("<"), literal: token::Literal::Null, line: 1 },
+                    operator: token::Token { type_: token::TokenType::Less, lexeme: String::from("<"), literal: token::Literal::Null, line: 1, column: 3 },
                     right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(2.0) }}),
                 }}),
-                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1, column: 7 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                     left: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                         left: Box::new(Expr { line: 1, expr_type: ExprType::Binary {
                             left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(3.0) }}),
-                            operator: token::Token { type_: token::TokenType::Greater, lexeme: String::from(">"), literal: token::Literal::Null, line: 1 },
+                            operator: token::Token { type_: token::TokenType::Greater, lexeme: String::from(">"), literal: token::Literal::Null, line: 1, column: 12 },
                             right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(4.0) }}),
                         }}),
-                        operator: token::Token { type_: token::TokenType::LessEqual, lexeme: String::from("<="), literal: token::Literal::Null, line: 1 },
+                        operator: token::Token { type_: token::TokenType::LessEqual, lexeme: String::from("<="), literal: token::Literal::Null, line: 1, column: 16 },
                         right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(5.0) }}),
                     }}),
-                    operator: token::Token { type_: token::TokenType::GreaterEqual, lexeme: String::from(">="), literal: token::Literal::Null, line: 1 },
+                    operator: token::Token { type_: token::TokenType::GreaterEqual, lexeme: String::from(">="), literal: token::Literal::Null, line: 1, column: 21 },
                     right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(6.0) }}),
                 }}),
             }}),
-            operator: token::Token { type_: token::TokenType::BangEqual, lexeme: String::from("!="), literal: token::Literal::Null, line: 1 },
+            operator: token::Token { type_: token::TokenType::BangEqual, lexeme: String::from("!="), literal: token::Literal::Null, line: 1, column: 26 },
             right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(7.0) }}),
         }}}}]), parse(source));
     }
@@ -1318,7 +1669,7 @@ mod tests {
                 Expr { line: 1, expr_type: ExprType::Variable { name: String::from("bc") }},
                 Expr { line: 1, expr_type: ExprType::Binary {
                     left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(2.0) }}),
-                    operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1 },
+                    operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1, column: 16 },
                     right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(3.0) }}),
                 }}
             ],
@@ -1337,22 +1688,74 @@ mod tests {
     #[test]
     fn unclosed_call() {
         let source = "a(1, \"a\"(bc, 2+3)";
-        assert!(errors_in_result(parse(source), vec![ErrorType::ExpectedCharacter { expected: ')', line: 1 }]));
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnclosedCall { opened_line: 1, line: 1 }]));
         let source = "a(1, \"a\")(bc, 2+3";
-        assert!(errors_in_result(parse(source), vec![ErrorType::ExpectedCharacter { expected: ')', line: 1 }]));
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnclosedCall { opened_line: 1, line: 1 }]));
+        let source = "print f(1,\n2,\n3\nprint 4";
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnclosedCall { opened_line: 1, line: 3 }]));
+    }
+
+    #[test]
+    fn unbalanced_brackets() {
+        let source = "func f() {\n    if (a == 1) {\n        print a\n\nprint 2";
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnclosedBracket { expected: '}', opened_line: 2, line: 5 }]));
+        let source = "var a = [1, 2]]";
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnexpectedClosingBracket { bracket: ']', line: 1 }]));
+        let source = "print a)";
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnexpectedClosingBracket { bracket: ')', line: 1 }]));
+    }
+
+    #[test]
+    fn assignment_in_condition() {
+        let source = "if (a = 2) {print a}";
+        assert!(errors_in_result(parse(source), vec![ErrorType::AssignmentInCondition { line: 1 }]));
+        let source = "while (a = 2) {print a}";
+        assert!(errors_in_result(parse(source), vec![ErrorType::AssignmentInCondition { line: 1 }]));
+        let source = "for (var i = 0; i = 2; i = i + 1) {print i}";
+        assert!(errors_in_result(parse(source), vec![ErrorType::AssignmentInCondition { line: 1 }]));
+    }
+
+    #[test]
+    fn else_without_if() {
+        let source = "print a\nelse {print b}";
+        assert!(errors_in_result(parse(source), vec![ErrorType::ElseWithoutIf { line: 2 }]));
+    }
+
+    #[test]
+    fn stray_semicolon() {
+        let source = "var a = 1;\nprint a;";
+        assert!(errors_in_result(parse(source), vec![ErrorType::UnexpectedSemicolon { line: 1 }, ErrorType::UnexpectedSemicolon { line: 2 }]));
+    }
+
+    #[test]
+    fn nesting_too_deep() {
+        let source = format!("print {}1{}", "(".repeat(MAX_NESTING_DEPTH), ")".repeat(MAX_NESTING_DEPTH));
+        assert!(errors_in_result(parse(&source), vec![ErrorType::NestingTooDeep { depth: MAX_NESTING_DEPTH, line: 1 }]));
+        let source = format!("print {}1", "-".repeat(MAX_NESTING_DEPTH - 1));
+        assert!(parse(&source).is_ok());
+    }
+
+    #[test]
+    fn keyword_as_name() {
+        let source = "var if = 1";
+        assert!(errors_in_result(parse(source), vec![ErrorType::KeywordAsName { keyword: String::from("if"), line: 1 }]));
+        let source = "func f(a, return) {}";
+        assert!(errors_in_result(parse(source), vec![ErrorType::KeywordAsName { keyword: String::from("return"), line: 1 }]));
+        let source = "var 123 = 1";
+        assert!(errors_in_result(parse(source), vec![ErrorType::ExpectedVariableName { line: 1 }]));
     }
 
     #[test]
     fn unary() {
         let source = "!!--5";
         assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::Expression { expression: Expr { line: 1, expr_type: ExprType::Unary {
-            operator: token::Token { type_: token::TokenType::Bang, lexeme: String::from("!"), literal: token::Literal::Null, line: 1 },
+            operator: token::Token { type_: token::TokenType::Bang, lexeme: String::from("!"), literal: token::Literal::Null, line: 1, column: 1 },
             right: Box::new(Expr { line: 1, expr_type: ExprType::Unary {
-                operator: token::Token { type_: token::TokenType::Bang, lexeme: String::from("!"), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::Bang, lexeme: String::from("!"), literal: token::Literal::Null, line: 1, column: 2 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Unary {
-                    operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1 },
+                    operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1, column: 3 },
                     right: Box::new(Expr { line: 1, expr_type: ExprType::Unary {
-                        operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1 },
+                        operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1, column: 4 },
                         right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(5.0) }}),
                     }}),
                 }}),
@@ -1365,14 +1768,56 @@ mod tests {
         let source = "5--4";
         assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::Expression { expression: Expr { line: 1, expr_type: ExprType::Binary {
             left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(5.0) }}),
-            operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1 },
+            operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1, column: 2 },
             right: Box::new(Expr { line: 1, expr_type: ExprType::Unary {
-                operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1 },
+                operator: token::Token { type_: token::TokenType::Minus, lexeme: String::from("-"), literal: token::Literal::Null, line: 1, column: 3 },
                 right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(4.0) }}),
             }}),
         }}}}]), parse(source));
     }
 
+    #[test]
+    fn statement_lines() {
+        let source = "var a = [\n1]\nprint a\na = 2\n\nif (a == 2) {\nprint a\n}";
+        let Ok(ast) = parse(source) else {
+            panic!("Parser returned error.");
+        };
+        assert_eq!(ast.iter().map(|stmt| stmt.line).collect::<Vec<usize>>(), vec![1, 3, 4, 6]);
+    }
+
+    #[test]
+    fn error_token_placeholder() {
+        // `var a = 'abc` with an unterminated string: the tokenizer has already reported it, so the parser should not raise another error.
+        let mut tokenizer = Tokenizer::new("var a = 'abc");
+        let (tokens, _) = tokenizer.tokenize();
+        let mut parser = Parser::new(tokens);
+        assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::VarDecl { name: String::from("a"), value: Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Null } }, doc: None}}]), parser.parse());
+    }
+
+    #[test]
+    fn assert_statement() {
+        let source = "assert (x)==1, \"x is \" + x";
+        assert_eq!(Ok(vec![Stmt { line: 1, stmt_type: StmtType::Assert {
+            condition: Expr { line: 1, expr_type: ExprType::Binary {
+                left: Box::new(Expr { line: 1, expr_type: ExprType::Grouping {
+                    expression: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }}),
+                }}),
+                operator: token::Token { type_: token::TokenType::EqualEqual, lexeme: String::from("=="), literal: token::Literal::Null, line: 1, column: 11 },
+                right: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::Number(1.0) }}),
+            }},
+            message: Some(Box::new(Expr { line: 1, expr_type: ExprType::Binary {
+                left: Box::new(Expr { line: 1, expr_type: ExprType::Literal { value: token::Literal::String_(String::from("x is ")) }}),
+                operator: token::Token { type_: token::TokenType::Plus, lexeme: String::from("+"), literal: token::Literal::Null, line: 1, column: 24 },
+                right: Box::new(Expr { line: 1, expr_type: ExprType::Variable { name: String::from("x") }}),
+            }})),
+            condition_text: String::from("(x)==1"),
+        }}]), parse(source));
+
+        // Conditions over several lines are quoted on one.
+        let Ok(ast) = parse("assert size([1,\n    2])  ==  2") else { panic!() };
+        assert!(matches!(&ast[0].stmt_type, StmtType::Assert { condition_text, .. } if condition_text == "size([1, 2])  ==  2"));
+    }
+
     #[test]
     fn sync() {
         let source = "print {\nfor (x = 5; x < 2; x = x + 1 {print x}";
@@ -1381,4 +1826,14 @@ mod tests {
             ErrorType::ExpectedParenAfterIncrement { line: 2 },
         ]));
     }
+
+    #[test]
+    fn partial() {
+        let (tokens, _) = Tokenizer::new("var a = 1\nvar = 2\nfunc f() {}").tokenize();
+        let (statements, errors) = Parser::new(tokens).parse_partial();
+        assert_eq!(errors, vec![ErrorType::ExpectedVariableName { line: 2 }]);
+        let lines: Vec<usize> = statements.iter().map(|statement| statement.line).collect();
+        assert_eq!(lines, vec![1, 3]);
+        assert!(matches!(&statements[1].stmt_type, StmtType::Function { name, .. } if name == "f"));
+    }
 }
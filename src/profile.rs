@@ -0,0 +1,236 @@
+// The profiler used by `nea run --profile`, which measures where a script spends its time.
+// It counts how many times each line and each user-defined function is executed, and how long they take, using the events of
+// the interpreter's hook. The report lists the functions and the slowest lines, and the folded stacks can be turned into
+// a flame graph by tools such as `flamegraph.pl` or `inferno-flamegraph`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::ErrorType;
+use crate::interpreter::{Hook, Interpreter};
+use crate::stmt::Stmt;
+use crate::value::Value;
+
+/// The maximum number of lines listed in the report.
+const MAX_REPORT_LINES: usize = 20;
+
+/// How to profile, given by the options of `nea run --profile`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProfileOptions {
+    pub folded: Option<String>,  // The path of the file to write the folded stacks to, if any.
+}
+
+/// The measurements of a line.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineStats {
+    pub count: usize,  // The number of times statements on the line were executed.
+    pub time: Duration,  // The time spent on the line itself, excluding statements in the blocks and functions it runs.
+}
+
+/// The measurements of a user-defined function.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FunctionStats {
+    pub calls: usize,
+    pub total: Duration,  // The time spent in calls to the function, including the functions it calls. Recursive calls are only counted once.
+    pub self_time: Duration,  // The time spent in the function itself, excluding the functions it calls.
+}
+
+/// The measurements of a run of a script.
+#[derive(Debug, Default)]
+pub struct Profile {
+    lines: HashMap<usize, LineStats>,
+    functions: HashMap<String, FunctionStats>,
+    stacks: HashMap<String, Duration>,  // The time spent in each call stack, given as the names of its functions separated by `;`.
+    total: Duration,  // The time spent executing the top-level statements.
+}
+
+impl Profile {
+    /// Returns a report of the functions, sorted by their total time, and of the lines which took the most time.
+    /// `source` is the source code which was run, so that lines can be shown.
+    pub fn report(&self, source: &str) -> String {
+        let mut report = format!("Profile: {} in total\n", milliseconds(self.total));
+
+        let mut functions: Vec<(&String, &FunctionStats)> = self.functions.iter().collect();
+        functions.sort_by(|a, b| b.1.total.cmp(&a.1.total).then_with(|| a.0.cmp(b.0)));
+        if !functions.is_empty() {
+            report.push_str(&format!("\n{:<20} {:>8} {:>12} {:>12}\n", "function", "calls", "total", "self"));
+            for (name, stats) in functions {
+                report.push_str(&format!("{:<20} {:>8} {:>12} {:>12}\n", name, stats.calls, milliseconds(stats.total), milliseconds(stats.self_time)));
+            }
+        }
+
+        let mut lines: Vec<(&usize, &LineStats)> = self.lines.iter().collect();
+        lines.sort_by(|a, b| b.1.time.cmp(&a.1.time).then_with(|| a.0.cmp(b.0)));
+        if !lines.is_empty() {
+            report.push_str(&format!("\n{:>6} {:>8} {:>12}  code\n", "line", "count", "time"));
+            let source_lines: Vec<&str> = source.lines().collect();
+            for (line, stats) in lines.iter().take(MAX_REPORT_LINES) {
+                let code = source_lines.get(**line - 1).map_or("", |code| code.trim());
+                report.push_str(&format!("{:>6} {:>8} {:>12}  {}\n", line, stats.count, milliseconds(stats.time), code));
+            }
+            if lines.len() > MAX_REPORT_LINES {
+                report.push_str(&format!("({} more lines)\n", lines.len() - MAX_REPORT_LINES));
+            }
+        }
+        report
+    }
+
+    /// Returns the folded stacks: one line for each call stack, with the number of microseconds spent in it, in alphabetical order.
+    /// Stacks which took less than a microsecond in total are left out.
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<(&String, &Duration)> = self.stacks.iter().filter(|(_, time)| time.as_micros() > 0).collect();
+        stacks.sort();
+        stacks.iter().map(|(stack, time)| format!("{} {}\n", stack, time.as_micros())).collect()
+    }
+}
+
+/// Formats a duration as milliseconds, e.g., `1.250 ms`.
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.3} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// A statement or a function call being timed.
+struct Timer {
+    start: Instant,
+    nested: Duration,  // The time spent in the statements or function calls nested in this one, which is not its own time.
+}
+
+impl Timer {
+    fn start() -> Self {
+        Self { start: Instant::now(), nested: Duration::ZERO }
+    }
+}
+
+/// Measures a script as it runs, recording the measurements in a `Profile` which is shared with whoever reports it.
+pub struct Profiler {
+    profile: Arc<Mutex<Profile>>,
+    statements: Vec<Timer>,  // The statements being executed, innermost last.
+    calls: Vec<(String, Timer)>,  // The function calls being executed, innermost last.
+}
+
+impl Profiler {
+    /// Initialises a new instance of `Profiler`, which records its measurements in `profile`.
+    pub fn new(profile: Arc<Mutex<Profile>>) -> Self {
+        Self {
+            profile,
+            statements: Vec::new(),
+            calls: Vec::new(),
+        }
+    }
+}
+
+impl Hook for Profiler {
+    fn before_statement(&mut self, _interpreter: &mut Interpreter, _stmt: &Stmt) -> Result<(), ErrorType> {
+        self.statements.push(Timer::start());
+        Ok(())
+    }
+
+    fn after_statement(&mut self, interpreter: &Interpreter, stmt: &Stmt, _result: &Result<(), ErrorType>) {
+        let Some(timer) = self.statements.pop() else { return };
+        let elapsed = timer.start.elapsed();
+        let own = elapsed.saturating_sub(timer.nested);
+        match self.statements.last_mut() {
+            Some(outer) => outer.nested += elapsed,
+            None => self.profile.lock().unwrap().total += elapsed,
+        }
+
+        let mut profile = self.profile.lock().unwrap();
+        let line = profile.lines.entry(stmt.line).or_default();
+        line.count += 1;
+        line.time += own;
+
+        let stack: Vec<&str> = interpreter.frames().iter().map(|frame| frame.function.as_str()).collect();
+        *profile.stacks.entry(stack.join(";")).or_default() += own;
+    }
+
+    fn function_entered(&mut self, _interpreter: &Interpreter, name: &str, _parameters: &[String], _arguments: &[Value]) {
+        self.calls.push((name.to_string(), Timer::start()));
+    }
+
+    fn function_exited(&mut self, _interpreter: &Interpreter, _name: &str, _result: &Result<Value, ErrorType>) {
+        let Some((name, timer)) = self.calls.pop() else { return };
+        let elapsed = timer.start.elapsed();
+        if let Some((_, outer)) = self.calls.last_mut() {
+            outer.nested += elapsed;
+        }
+
+        let mut profile = self.profile.lock().unwrap();
+        let stats = profile.functions.entry(name.clone()).or_default();
+        stats.calls += 1;
+        stats.self_time += elapsed.saturating_sub(timer.nested);
+        // The time of a recursive call is already part of the time of the outermost call.
+        if !self.calls.iter().any(|(outer, _)| *outer == name) {
+            stats.total += elapsed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::tokenizer::Tokenizer;
+
+    use super::{Profile, Profiler};
+
+    const SOURCE: &str = "\
+func fib(n) {
+    if (n < 2) {
+        return n
+    }
+    return fib(n - 1) + fib(n - 2)
+}
+var i = 0
+while (i < 3) {
+    i = i + 1
+}
+print(fib(5))
+";
+
+    /// Runs `SOURCE` with the profiler and returns the profile.
+    fn profile() -> Profile {
+        let (tokens, _) = Tokenizer::new(SOURCE).tokenize();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let profile = Arc::new(Mutex::new(Profile::default()));
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(Box::new(io::sink()));
+        interpreter.set_hook(Box::new(Profiler::new(Arc::clone(&profile))));
+        interpreter.interpret(ast).unwrap();
+        drop(interpreter);
+        Arc::try_unwrap(profile).unwrap().into_inner().unwrap()
+    }
+
+    #[test]
+    fn counts() {
+        let profile = profile();
+        let mut lines: Vec<(usize, usize)> = profile.lines.iter().map(|(line, stats)| (*line, stats.count)).collect();
+        lines.sort();
+        // `fib(5)` makes 15 calls, of which 8 have `n < 2`.
+        assert_eq!(lines, vec![(1, 1), (2, 15), (3, 8), (5, 7), (7, 1), (8, 1), (9, 3), (11, 1)]);
+
+        let fib = &profile.functions["fib"];
+        assert_eq!(fib.calls, 15);
+        assert!(fib.self_time <= fib.total);
+        assert!(fib.total <= profile.total);
+    }
+
+    #[test]
+    fn report() {
+        let profile = profile();
+        let report = profile.report(SOURCE);
+        assert!(report.starts_with("Profile: "));
+        assert!(report.contains("\nfunction                calls        total         self\nfib                        15 "));
+        assert!(report.contains("  return fib(n - 1) + fib(n - 2)\n"));
+
+        // Each stack is the names of the functions in it, followed by a number of microseconds.
+        for line in profile.folded().lines() {
+            let (stack, time) = line.rsplit_once(' ').unwrap();
+            assert!(stack == "<script>" || stack.starts_with("<script>;fib"));
+            assert!(time.parse::<u128>().unwrap() > 0);
+        }
+    }
+}
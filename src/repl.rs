@@ -0,0 +1,359 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use crate::cli::{Options, WarningMode};
+use crate::dump::{self, DumpFormat};
+use crate::error::{self, ErrorType};
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::suggestion;
+use crate::token;
+use crate::tokenizer::Tokenizer;
+use crate::value::Value;
+
+/// The prompt shown when a new statement can be entered.
+const PROMPT: &str = "> ";
+/// The prompt shown when the input so far is incomplete, e.g., inside an unclosed block.
+const CONTINUATION_PROMPT: &str = "... ";
+/// The name of the file in the user's home directory which stores the history of entered lines across sessions.
+const HISTORY_FILE_NAME: &str = ".nea_history";
+
+/// The meta-commands which can be entered instead of source code, with their arguments and descriptions, as listed by `:help`.
+const COMMANDS: [(&str, &str, &str); 7] = [
+    (":ast", "<code>", "show the abstract syntax tree of the code"),
+    (":help", "", "show this list of commands"),
+    (":load", "<file>", "run a script in the current session"),
+    (":reset", "", "clear all variables and functions"),
+    (":time", "<code>", "run the code and show how long it took"),
+    (":tokens", "<code>", "show the tokens of the code"),
+    (":vars", "", "list the variables and functions declared in the session"),
+];
+
+/// Runs the interactive REPL interface in the console.
+/// `options` apply to scripts run with `:load`, while warnings are never shown for the code entered.
+pub fn run_repl(options: &Options) {
+    // The line editor provides arrow keys, history and tab completion.
+    let mut editor: Editor<ReplHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Failed to start the REPL: {}", e);
+            return;
+        },
+    };
+    editor.set_helper(Some(ReplHelper { names: Vec::new() }));
+
+    // Load the history of previous sessions. If there is none yet, e.g., on the first run, start with an empty history.
+    let history_path = history_path();
+    if let Some(path) = &history_path {
+        let _ = editor.load_history(path);
+    }
+
+    // We need the same `Interpreter` instance across all REPL source code inputs to preserve the variables and functions stored in the environment.
+    let mut interpreter = Interpreter::new();
+    interpreter.set_echo(true);
+
+    // The lines of the statement being entered, which may span many lines, e.g., a function declaration.
+    let mut source = String::new();
+    loop {
+        // Complete the names declared so far, which change as statements are executed.
+        if let Some(helper) = editor.helper_mut() {
+            helper.names = interpreter.names();
+        }
+
+        // Read user input into `line`.
+        let line = match editor.readline(if source.is_empty() { PROMPT } else { CONTINUATION_PROMPT }) {
+            Ok(line) => line,
+            // Ctrl-C discards the input entered so far, but does not end the session.
+            Err(ReadlineError::Interrupted) => {
+                source.clear();
+                continue;
+            },
+            // Ctrl-D, or the end of piped input, ends the session.
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("Failed to read line: {}", e);
+                break;
+            },
+        };
+
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+            // Save after every line, so that the history is kept even if the session does not end cleanly.
+            if let Some(path) = &history_path {
+                let _ = editor.save_history(path);
+            }
+        }
+
+        if source.is_empty() && line.trim_start().starts_with(':') {
+            // Meta-commands are only recognised at the start of a new statement.
+            run_command(line.trim(), &mut interpreter, options);
+            continue;
+        }
+
+        // An empty line while continuing runs the input as it is, so that the user can always get out of an incomplete input.
+        let force = !source.is_empty() && line.trim().is_empty();
+        source.push_str(&line);
+        source.push('\n');
+        if !force && is_incomplete(&source) {
+            // Keep reading lines until the statement is complete.
+            continue;
+        }
+
+        crate::run(&source, "<repl>", &mut interpreter, &entered_code_options(options));
+        source.clear();
+    }
+}
+
+/// Runs a meta-command, e.g., `:vars`. `line` is the whole line, including the command's argument.
+fn run_command(line: &str, interpreter: &mut Interpreter, options: &Options) {
+    let (command, argument) = match line.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (line, ""),
+    };
+
+    match command {
+        ":ast" => {
+            let (tokens, mut errors) = Tokenizer::new(argument).tokenize();
+            match Parser::new(tokens).parse() {
+                Ok(ast) if errors.is_empty() => print!("{}", dump::dump_ast(&ast, DumpFormat::Tree)),
+                Ok(..) => error::report_errors(&errors),
+                Err(parser_errors) => {
+                    errors.extend(parser_errors);
+                    error::report_errors(&errors);
+                },
+            }
+        },
+        ":help" => {
+            for (name, argument, description) in COMMANDS {
+                println!("{:<16} {}", format!("{} {}", name, argument), description);
+            }
+        },
+        ":load" => {
+            if argument.is_empty() {
+                println!("Usage: :load <file>");
+                return;
+            }
+            match fs::read_to_string(argument) {
+                Ok(source) => {
+                    // The script is run as it would be from the command line, i.e., with warnings and without echoing values.
+                    interpreter.set_echo(false);
+                    crate::run(&source, argument, interpreter, options);
+                    interpreter.set_echo(true);
+                },
+                Err(e) => println!("Failed to read file `{}`: {}", argument, e),
+            }
+        },
+        ":reset" => {
+            *interpreter = Interpreter::new();
+            interpreter.set_echo(true);
+        },
+        ":time" => {
+            let start = Instant::now();
+            crate::run(argument, "<repl>", interpreter, &entered_code_options(options));
+            println!("Took {:?}.", start.elapsed());
+        },
+        ":tokens" => {
+            let (tokens, errors) = Tokenizer::new(argument).tokenize();
+            print!("{}", dump::dump_tokens(&tokens, DumpFormat::Tree));
+            if !errors.is_empty() {
+                error::report_errors(&errors);
+            }
+        },
+        ":vars" => {
+            for line in list_vars(interpreter) {
+                println!("{}", line);
+            }
+        },
+        _ => {
+            let names = COMMANDS.iter().map(|(name, _, _)| *name);
+            match suggestion::closest_match(command, names) {
+                Some(name) => println!("Unknown command `{}`. Did you mean `{}`?", command, name),
+                None => println!("Unknown command `{}`. Enter `:help` for a list of commands.", command),
+            }
+        },
+    }
+}
+
+/// Returns the options for running code entered in the REPL.
+/// Warnings are not shown, as, e.g., a variable declared in one line is only used in later lines.
+fn entered_code_options(options: &Options) -> Options {
+    Options { warnings: WarningMode::Hide, ..options.clone() }
+}
+
+/// Returns a line for each variable and function declared in the interpreter, with its type and value.
+/// The built-in functions are left out, as they are always declared.
+fn list_vars(interpreter: &Interpreter) -> Vec<String> {
+    interpreter.bindings()
+        .into_iter()
+        .filter(|(name, value)| !matches!(value, Value::NativeFunction(function) if function.name == *name))
+        .map(|(name, value)| format!("{}: {} = {}", name, value.type_to_string(), value.repr()))
+        .collect()
+}
+
+/// Returns the path of the history file in the user's home directory, if the home directory is known.
+fn history_path() -> Option<PathBuf> {
+    // `HOME` is set on Unix-like systems, and `USERPROFILE` on Windows.
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(|home| PathBuf::from(home).join(HISTORY_FILE_NAME))
+}
+
+/// Provides tab completion to the line editor.
+struct ReplHelper {
+    names: Vec<String>,  // The names currently declared in the interpreter's environment, including the built-in functions.
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, candidates) = completions(line, pos, &self.names);
+        let pairs = candidates
+            .into_iter()
+            .map(|candidate| Pair { display: candidate.clone(), replacement: candidate })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+// The other parts of a helper are not needed, so they use the default behaviour.
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+impl Highlighter for ReplHelper {}
+impl Validator for ReplHelper {}
+impl Helper for ReplHelper {}
+
+/// Returns the start of the word being typed before `pos` in `line`, and the keywords and `names` which could complete it.
+fn completions(line: &str, pos: usize, names: &[String]) -> (usize, Vec<String>) {
+    // The word consists of the characters allowed in identifiers, i.e., alphanumeric characters and underscores.
+    let before = &line[..pos];
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+        .last()
+        .map_or(pos, |(i, _)| i);
+    let word = &line[start..pos];
+
+    if before.starts_with(':') && !before.contains(char::is_whitespace) {
+        // Complete the name of a meta-command.
+        let candidates = COMMANDS
+            .iter()
+            .map(|(name, _, _)| name.to_string())
+            .filter(|name| name.starts_with(before))
+            .collect();
+        return (0, candidates);
+    }
+
+    if word.is_empty() {
+        return (pos, Vec::new());
+    }
+
+    let mut candidates: Vec<String> = token::KEYWORDS
+        .iter()
+        .map(|keyword| keyword.to_string())
+        .chain(names.iter().cloned())
+        .filter(|candidate| candidate.starts_with(word))
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+    (start, candidates)
+}
+
+/// Returns `true` if `source` is the start of a statement which continues on the next line, i.e.,
+/// it has an unterminated string, or it only fails to parse because the tokens ended too early.
+fn is_incomplete(source: &str) -> bool {
+    let (tokens, errors) = Tokenizer::new(source).tokenize();
+    match errors.as_slice() {
+        [] => (),
+        // An unterminated string always runs to the end of the source code, so it may be closed on a later line.
+        [ErrorType::UnterminatedString {..}] => return true,
+        // Any other lexical error cannot be fixed by more input.
+        _ => return false,
+    }
+
+    let mut parser = Parser::new(tokens);
+    parser.parse().is_err() && parser.is_incomplete()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::tokenizer::Tokenizer;
+
+    use super::{completions, is_incomplete, list_vars};
+
+    #[test]
+    fn complete() {
+        assert!(!is_incomplete(""));
+        assert!(!is_incomplete("print 1 + 2\n"));
+        assert!(!is_incomplete("func f(x) {\n    return x\n}\n"));
+        assert!(!is_incomplete("if (a == 1) {\n    print a\n} else {\n    print b\n}\n"));
+    }
+
+    #[test]
+    fn incomplete() {
+        assert!(is_incomplete("func f(x) {\n"));
+        assert!(is_incomplete("func f(x) {\n    if (x) {\n        return [1,\n"));
+        assert!(is_incomplete("var a = \"multi-line\n"));
+        assert!(is_incomplete("if (a == 1) {\n    print a\n} else\n"));
+        assert!(is_incomplete("var a = 1 +\n"));
+        assert!(is_incomplete("print f(1,\n"));
+        assert!(is_incomplete("var d = {\"a\":\n"));
+    }
+
+    #[test]
+    fn errors_are_not_incomplete() {
+        // These can never be fixed by more input, so they should be reported straight away.
+        assert!(!is_incomplete("print a)\n"));
+        assert!(!is_incomplete("print a;\n"));
+        assert!(!is_incomplete("else\n"));
+        assert!(!is_incomplete("var if\n"));
+        assert!(!is_incomplete("print @ {\n"));
+        assert!(!is_incomplete("print a)\nfunc f() {\n"));
+    }
+
+    #[test]
+    fn complete_names() {
+        let names = vec![String::from("append"), String::from("apple"), String::from("total"), String::from("to_string")];
+        assert_eq!(completions("ap", 2, &names), (0, vec![String::from("append"), String::from("apple")]));
+        assert_eq!(completions("print to", 8, &names), (6, vec![String::from("to_string"), String::from("total")]));
+        assert_eq!(completions("var x = f", 9, &names), (8, vec![String::from("false"), String::from("for"), String::from("func")]));
+        // Only the part of the word before the cursor is completed.
+        assert_eq!(completions("wh(", 2, &names), (0, vec![String::from("while")]));
+        assert_eq!(completions("print ", 6, &names), (6, vec![]));
+    }
+
+    #[test]
+    fn complete_commands() {
+        assert_eq!(completions(":t", 2, &[]), (0, vec![String::from(":time"), String::from(":tokens")]));
+        assert_eq!(completions(":", 1, &[]).1.len(), 7);
+    }
+
+    #[test]
+    fn vars() {
+        let source = "var a = 1\nvar s = \"x\"\nfunc f(x) {\n    return x\n}\nvar size_ = [s]";
+        let (tokens, _) = Tokenizer::new(source).tokenize();
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(Parser::new(tokens).parse().unwrap()).unwrap();
+        assert_eq!(list_vars(&interpreter), vec![
+            String::from("a: Number = 1"),
+            String::from("f: Function = <function>"),
+            String::from("s: String = \"x\""),
+            String::from("size_: Array = [\"x\"]"),
+        ]);
+    }
+}
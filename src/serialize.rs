@@ -0,0 +1,139 @@
+// `Serialize` and `Deserialize` for `Value` and `HashTable`, enabled by the `serde` feature, so that scripts can exchange data
+// in any format serde supports. Dictionaries are maps, and `null` is a unit, e.g., `null` in JSON.
+// Functions cannot be serialized, as they are code rather than data.
+
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, Serializer};
+
+use crate::hash_table::HashTable;
+use crate::value::Value;
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Number(x) => serializer.serialize_f64(*x),
+            Self::String_(s) => serializer.serialize_str(s),
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            Self::Array(array) => serializer.collect_seq(array),
+            Self::Dictionary(dict) => dict.serialize(serializer),
+            Self::Function {..} | Self::NativeFunction(..) => Err(ser::Error::custom("functions cannot be serialized")),
+            Self::Null => serializer.serialize_unit(),
+        }
+    }
+}
+
+impl Serialize for HashTable {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let entries = self.flatten();
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for entry in &entries {
+            map.serialize_entry(&entry.key, &entry.value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for HashTable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::Dictionary(dict) => Ok(dict),
+            value => Err(de::Error::custom(format!("expected a map, got a value of type {}", value.type_to_string()))),
+        }
+    }
+}
+
+/// Builds a `Value` from whatever the format contains. Every number becomes a `Number`, as that is the only numeric type.
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a Boolean, number, string, sequence, map or null")
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, x: i64) -> Result<Value, E> {
+        Ok(Value::from(x))
+    }
+
+    fn visit_u64<E: de::Error>(self, x: u64) -> Result<Value, E> {
+        Ok(Value::from(x))
+    }
+
+    fn visit_f64<E: de::Error>(self, x: f64) -> Result<Value, E> {
+        Ok(Value::Number(x))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::from(s))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<Value, E> {
+        Ok(Value::String_(s))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut array = Vec::new();
+        while let Some(element) = seq.next_element()? {
+            array.push(element);
+        }
+        Ok(Value::Array(array))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut dict = HashTable::new();
+        while let Some((key, value)) = map.next_entry::<Value, Value>()? {
+            // E.g., a key which is itself a map cannot be hashed. There is no line in the source code to report, so line 0 is used.
+            dict.insert(key, value, 0).map_err(|e| de::Error::custom(e.message()))?;
+        }
+        Ok(Value::Dictionary(dict))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash_table::HashTable;
+    use crate::value::{Arity, NativeFunction, Value};
+
+    #[test]
+    fn json_round_trip() {
+        let mut dict = HashTable::new();
+        dict.insert(Value::from("xs"), Value::from(vec![Some(1.5), None]), 1).unwrap();
+        let value = Value::Array(vec![Value::Dictionary(dict), Value::Bool(true), Value::from("s")]);
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"[{"xs":[1.5,null]},true,"s"]"#);
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+        assert_eq!(serde_json::from_str::<Value>("3").unwrap(), Value::Number(3.0));
+    }
+
+    #[test]
+    fn functions_are_not_data() {
+        let function = Value::NativeFunction(NativeFunction::new("f", Arity::Exact(0), |_, _| Ok(Value::Null)));
+        assert!(serde_json::to_string(&function).is_err());
+        assert!(serde_json::from_str::<HashTable>("[1]").is_err());
+    }
+}
@@ -1,5 +1,9 @@
 use crate::expr::Expr;
 
+/// The text of the documentation comments of a declaration, without the `##`s, with a line for each comment.
+/// It is boxed so that it does not make every `Stmt` larger, as most declarations are not documented.
+pub type Doc = Option<Box<str>>;
+
 /// A statement.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Stmt {
@@ -10,6 +14,11 @@ pub struct Stmt {
 /// Possible types of statements.
 #[derive(Clone, Debug, PartialEq)]
 pub enum StmtType {
+    Assert {
+        condition: Expr,
+        message: Option<Box<Expr>>,  // The message to report if the assertion fails, if one was given.
+        condition_text: String,  // The source code of the condition, which is quoted in the report if the assertion fails.
+    },
     Block {
         body: Vec<Stmt>,
     },
@@ -21,11 +30,13 @@ pub enum StmtType {
         name: String,
         parameters: Vec<String>,
         body: Box<Stmt>,
+        doc: Doc,  // The text of the `##` comments before the function, if any.
     },
     If {
         condition: Expr,
         then_body: Box<Stmt>,
         else_body: Option<Box<Stmt>>,
+        branch: usize,  // Identifies the statement among the `if` and `while` statements of the script (see `Parser::next_branch()`).
     },
     Print {
         expression: Expr,
@@ -36,9 +47,11 @@ pub enum StmtType {
     VarDecl {
         name: String,
         value: Expr,
+        doc: Doc,  // The text of the `##` comments before the declaration, if any.
     },
     While {
         condition: Expr,
         body: Box<Stmt>,
+        branch: usize,  // As for `If`.
     },
 }
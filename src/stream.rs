@@ -0,0 +1,194 @@
+// The streams an interpreter reads input from and writes output and error reports to (see `Interpreter::set_output()`,
+// `Interpreter::set_input()` and `Interpreter::set_error_output()`), so that programs embedding it can capture or provide them.
+
+use std::io::{self, BufRead, Cursor, Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Returns a stream which writes to stdout. This is where output and error reports are written by default.
+pub fn stdout() -> Box<dyn Write + Send> {
+    Box::new(io::stdout())
+}
+
+/// Returns a stream which reads lines from stdin. This is where input is read from by default.
+/// Unlike `io::stdin().lock()`, stdin is only locked while a line is being read, so other code can still read it in between.
+pub fn stdin() -> Box<dyn BufRead + Send> {
+    Box::new(LineSource::new(|| {
+        let mut line = String::new();
+        let read = io::stdin().read_line(&mut line)?;
+        Ok(if read == 0 { None } else { Some(line) })
+    }))
+}
+
+/// Returns a stream which reads the lines of `text`, e.g., to give a script its input in a test.
+pub fn text_input(text: &str) -> Box<dyn BufRead + Send> {
+    Box::new(Cursor::new(text.to_string()))
+}
+
+/// An in-memory stream which collects the text written to it. Clones share the same text,
+/// so one can be given to the interpreter while the other is used to read what was written, even on another thread.
+#[derive(Clone, Debug, Default)]
+pub struct Buffer {
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Buffer {
+    /// Initialises a new, empty instance of `Buffer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the text written so far.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.bytes()).into_owned()
+    }
+
+    /// Returns the text written so far, and empties the buffer.
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.bytes());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    /// Locks the text. A panic while it was locked cannot have left it invalid, so it is still used then.
+    fn bytes(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.bytes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A stream which passes the text written to it to a function, e.g., to show it in a window.
+/// The text is passed a line at a time, including the line ending, and the rest of the text when the stream is flushed or dropped,
+/// e.g., the prompt of `input()`.
+pub struct Callback<F: FnMut(&str)> {
+    function: F,
+    pending: Vec<u8>,  // The text written since the end of the last line passed to the function.
+}
+
+impl<F: FnMut(&str)> Callback<F> {
+    /// Initialises a new instance of `Callback` which passes text to `function`.
+    pub fn new(function: F) -> Self {
+        Self { function, pending: Vec::new() }
+    }
+}
+
+impl<F: FnMut(&str)> Write for Callback<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        while let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            (self.function)(&String::from_utf8_lossy(&line));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            let text = std::mem::take(&mut self.pending);
+            (self.function)(&String::from_utf8_lossy(&text));
+        }
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&str)> Drop for Callback<F> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// A stream which gets each line of input from a function, e.g., to ask the user of a graphical program for it.
+/// The function returns `None` when there is no more input, which `input()` reports as an error.
+pub struct LineSource<F: FnMut() -> io::Result<Option<String>>> {
+    function: F,
+    line: Vec<u8>,  // The line being read.
+    position: usize,  // The number of bytes of `line` which have been read.
+}
+
+impl<F: FnMut() -> io::Result<Option<String>>> LineSource<F> {
+    /// Initialises a new instance of `LineSource` which gets lines from `function`.
+    pub fn new(function: F) -> Self {
+        Self { function, line: Vec::new(), position: 0 }
+    }
+}
+
+impl<F: FnMut() -> io::Result<Option<String>>> Read for LineSource<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl<F: FnMut() -> io::Result<Option<String>>> BufRead for LineSource<F> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.position == self.line.len() {
+            // The line has all been read, so get the next one. Lines without a line ending are given one,
+            // so that each is read as a separate line.
+            let mut line = (self.function)()?.unwrap_or_default();
+            if !line.is_empty() && !line.ends_with('\n') {
+                line.push('\n');
+            }
+            self.line = line.into_bytes();
+            self.position = 0;
+        }
+        Ok(&self.line[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.line.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{BufRead, Write};
+    use std::rc::Rc;
+
+    use super::{Buffer, Callback, LineSource};
+
+    #[test]
+    fn buffer() {
+        let buffer = Buffer::new();
+        write!(buffer.clone(), "a{}", 1).unwrap();
+        assert_eq!(buffer.contents(), "a1");
+        assert_eq!(buffer.take(), "a1");
+        assert_eq!(buffer.contents(), "");
+    }
+
+    #[test]
+    fn callback_passes_lines() {
+        let texts = Rc::new(RefCell::new(Vec::new()));
+        let mut callback = Callback::new(|text: &str| texts.borrow_mut().push(text.to_string()));
+        write!(callback, "one\ntw").unwrap();
+        write!(callback, "o\nprompt: ").unwrap();
+        callback.flush().unwrap();
+        drop(callback);
+        assert_eq!(*texts.borrow(), vec!["one\n", "two\n", "prompt: "]);
+    }
+
+    #[test]
+    fn line_source() {
+        let mut lines = vec![String::from("first"), String::from("second\n")].into_iter();
+        let mut source = LineSource::new(move || Ok(lines.next()));
+        let mut line = String::new();
+        assert_eq!(source.read_line(&mut line).unwrap(), 6);
+        assert_eq!(line, "first\n");
+        line.clear();
+        source.read_line(&mut line).unwrap();
+        assert_eq!(line, "second\n");
+        line.clear();
+        assert_eq!(source.read_line(&mut line).unwrap(), 0);
+    }
+}
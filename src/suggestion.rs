@@ -0,0 +1,110 @@
+/// Returns the closest candidate to `name`, if there is one that is close enough to be a likely typo.
+/// Used to provide "did you mean" hints in error reports.
+pub fn closest_match<'a, I>(name: &str, candidates: I) -> Option<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    // The maximum edit distance we accept scales with the length of the name, so that short names
+    // do not match everything, e.g., `a` should not suggest `b`.
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    let mut best: Option<(usize, &str)> = None;
+    for candidate in candidates {
+        if candidate == name {
+            // An exact match is not a typo.
+            continue;
+        }
+
+        let distance = edit_distance(name, candidate);
+        let close = distance <= max_distance && distance < candidate.chars().count();
+        if !close && !is_abbreviation(name, candidate) {
+            // Too different to be a typo of `candidate`.
+            continue;
+        }
+
+        // Keep the candidate with the lowest distance. Ties are broken alphabetically so that the suggestion
+        // does not depend on the order of `candidates` (scopes are stored in hash maps).
+        best = match best {
+            Some((best_distance, best_candidate)) if (best_distance, best_candidate) <= (distance, candidate) => best,
+            _ => Some((distance, candidate)),
+        };
+    }
+
+    best.map(|(_, candidate)| candidate.to_string())
+}
+
+/// Returns `true` if one of the strings is a prefix of the other, e.g., `function` and `func`.
+/// This catches names borrowed from other languages, which are usually too far away in edit distance.
+fn is_abbreviation(a: &str, b: &str) -> bool {
+    let (shorter, longer) = if a.len() < b.len() { (a, b) } else { (b, a) };
+    // Very short prefixes would match almost anything, so require at least three characters.
+    shorter.chars().count() >= 3 && longer.starts_with(shorter)
+}
+
+/// Computes the edit distance between two strings, i.e., the minimum number of insertions, deletions, substitutions,
+/// and transpositions of adjacent characters needed to turn `a` into `b` (the 'optimal string alignment' distance).
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // `table[i][j]` is the distance between the first `i` characters of `a` and the first `j` characters of `b`.
+    // The first row and column are the distances from the empty string.
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    table[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            table[i][j] = (table[i - 1][j] + 1)  // Deletion.
+                .min(table[i][j - 1] + 1)  // Insertion.
+                .min(table[i - 1][j - 1] + cost);  // Substitution.
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                // Transposition, e.g., `pritn` -> `print`.
+                table[i][j] = table[i][j].min(table[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    table[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closest_match, edit_distance};
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", "abc"), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("pritn", "print"), 1);
+        assert_eq!(edit_distance("function", "func"), 4);
+    }
+
+    #[test]
+    fn suggests_closest() {
+        assert_eq!(closest_match("to_strng", ["to_string", "to_number", "size"]), Some(String::from("to_string")));
+        assert_eq!(closest_match("lenght", ["length", "height"]), Some(String::from("length")));
+        assert_eq!(closest_match("fnuc", ["func", "for"]), Some(String::from("func")));
+        assert_eq!(closest_match("function", ["func", "for", "return"]), Some(String::from("func")));
+    }
+
+    #[test]
+    fn ties_broken_alphabetically() {
+        assert_eq!(closest_match("ab", ["bb", "ac", "aa"]), Some(String::from("aa")));
+        assert_eq!(closest_match("ab", ["aa", "ac", "bb"]), Some(String::from("aa")));
+    }
+
+    #[test]
+    fn no_suggestion_when_too_different() {
+        assert_eq!(closest_match("x", ["y"]), None);
+        assert_eq!(closest_match("counter", ["append", "input"]), None);
+        assert_eq!(closest_match("abc", ["abc"]), None);
+        assert_eq!(closest_match("ab", ["abstract"]), None);
+    }
+}
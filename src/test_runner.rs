@@ -0,0 +1,190 @@
+// The test runner used by `nea test`, which runs the `test_*` functions of scripts, e.g., ones using `assert` statements.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::cli;
+use crate::coverage::{self, CoverageOptions, FileCoverage, Recorder};
+use crate::error::ErrorType;
+use crate::interpreter::Interpreter;
+use crate::stmt::{Stmt, StmtType};
+
+/// The prefix of the names of test functions.
+const TEST_PREFIX: &str = "test_";
+
+/// The outcome of a test, or of loading a file of tests.
+struct Failure {
+    name: String,  // The test function, or the file if it could not be loaded.
+    errors: Vec<ErrorType>,
+}
+
+/// Runs the tests in each path, which is either a script or a directory searched recursively for scripts,
+/// and prints the result of each test followed by a summary. Fails if any test fails or any script could not be loaded.
+/// If `coverage` is given, the coverage of the scripts which were loaded is also reported, and the run fails if it is
+/// below the minimums given.
+pub fn run_tests(paths: &[String], coverage: Option<&CoverageOptions>) -> ExitCode {
+    let start = Instant::now();
+    let mut files = Vec::new();
+    for path in paths {
+        if let Err(e) = cli::find_scripts(Path::new(path), &mut files) {
+            eprintln!("Failed to read `{}`: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut passed = 0;
+    let mut failures = Vec::new();
+    let mut coverages = Vec::new();
+    let mut stdout = io::stdout();
+    for file in &files {
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Failed to read file `{}`: {}", file.display(), e);
+                return ExitCode::FAILURE;
+            },
+        };
+        let ast = match cli::parse_script(&source) {
+            Ok(ast) => ast,
+            Err(errors) => {
+                let _ = writeln!(stdout, "{} ... could not be loaded", file.display());
+                failures.push(Failure { name: file.display().to_string(), errors });
+                continue;
+            },
+        };
+
+        let file_coverage = coverage.map(|_| Arc::new(Mutex::new(FileCoverage::new(&file.display().to_string(), &ast))));
+        let tests = test_functions(&ast);
+        let _ = writeln!(stdout, "running {} test(s) in {}", tests.len(), file.display());
+        for name in tests {
+            let _ = write!(stdout, "test {} ... ", name);
+            let _ = stdout.flush();
+
+            let test_start = Instant::now();
+            let result = run_test(&ast, &name, file_coverage.as_ref());
+            let elapsed = test_start.elapsed();
+            match result {
+                Ok(()) => {
+                    let _ = writeln!(stdout, "ok ({})", format_duration(elapsed));
+                    passed += 1;
+                },
+                Err(e) => {
+                    let _ = writeln!(stdout, "FAILED ({})", format_duration(elapsed));
+                    failures.push(Failure { name: format!("{}: {}", file.display(), name), errors: vec![e] });
+                },
+            }
+        }
+        if let Some(file_coverage) = file_coverage {
+            coverages.push(file_coverage.lock().unwrap().clone());
+        }
+    }
+
+    if !failures.is_empty() {
+        let _ = writeln!(stdout, "\nfailures:");
+        for failure in &failures {
+            let _ = writeln!(stdout, "\n{}", failure.name);
+            for error in &failure.errors {
+                let _ = writeln!(stdout, "    {}", error.message());
+                if let Some(hint) = error.hint() {
+                    let _ = writeln!(stdout, "        Hint: {}", hint);
+                }
+            }
+        }
+    }
+
+    let result = if failures.is_empty() { "ok" } else { "FAILED" };
+    let _ = writeln!(
+        stdout,
+        "\ntest result: {}. {} passed; {} failed; finished in {}",
+        result, passed, failures.len(), format_duration(start.elapsed()),
+    );
+
+    let mut coverage_passed = true;
+    if let Some(options) = coverage {
+        let _ = write!(stdout, "\n{}", coverage::summary(&coverages));
+        if let Some(path) = &options.lcov {
+            if let Err(e) = fs::write(path, coverage::lcov(&coverages)) {
+                eprintln!("Failed to write the coverage to `{}`: {}", path, e);
+                coverage_passed = false;
+            }
+        }
+        for message in coverage::check_thresholds(&coverages, options) {
+            let _ = writeln!(stdout, "{}", message);
+            coverage_passed = false;
+        }
+    }
+
+    if failures.is_empty() && coverage_passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Returns the names of the test functions declared at the top level, in the order they are declared.
+/// These are the functions whose names start with `test_` and which take no parameters.
+fn test_functions(ast: &[Stmt]) -> Vec<String> {
+    ast.iter()
+        .filter_map(|stmt| match &stmt.stmt_type {
+            StmtType::Function { name, parameters, .. } if name.starts_with(TEST_PREFIX) && parameters.is_empty() => Some(name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Runs a test with a fresh interpreter, so that tests cannot affect each other.
+/// The top-level statements of the script are run first, e.g., to declare the functions being tested.
+/// If `coverage` is given, what the test runs is added to it.
+fn run_test(ast: &[Stmt], name: &str, coverage: Option<&Arc<Mutex<FileCoverage>>>) -> Result<(), ErrorType> {
+    let mut interpreter = Interpreter::new();
+    if let Some(coverage) = coverage {
+        interpreter.set_hook(Box::new(Recorder::new(Arc::clone(coverage))));
+    }
+    interpreter.interpret(ast.to_vec())?;
+    interpreter.call(name, Vec::new())?;
+    Ok(())
+}
+
+/// Formats a duration in milliseconds, e.g., `1.25ms`.
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ErrorType;
+
+    use crate::cli::parse_script as parse;
+
+    use super::{run_test, test_functions};
+
+    #[test]
+    fn discover_tests() {
+        let ast = parse("func test_a() {}\nfunc helper() {}\nfunc test_b(x) {}\nif (true) {\n    func test_c() {}\n}\nfunc test_d() {}").unwrap();
+        assert_eq!(test_functions(&ast), vec![String::from("test_a"), String::from("test_d")]);
+    }
+
+    #[test]
+    fn assertions() {
+        let ast = parse("var count = 0\nfunc test_pass() {\n    count = count + 1\n    assert count == 1\n}\nfunc test_fail() {\n    assert size( [1] ) == 2, \"size was \" + to_string(size([1]))\n}").unwrap();
+        // Each test has a fresh interpreter, so `count` is 0 at the start of each.
+        assert_eq!(run_test(&ast, "test_pass", None), Ok(()));
+        assert_eq!(run_test(&ast, "test_pass", None), Ok(()));
+        assert_eq!(run_test(&ast, "test_fail", None), Err(ErrorType::AssertionFailed {
+            condition: String::from("size( [1] ) == 2"),
+            message: Some(String::from("size was 1")),
+            line: 7,
+        }));
+    }
+
+    #[test]
+    fn errors_fail_tests() {
+        let ast = parse("func test_error() {\n    var x = 1 / 0\n}\nfunc test_non_boolean() {\n    assert 1\n}").unwrap();
+        assert_eq!(run_test(&ast, "test_error", None), Err(ErrorType::DivideByZero { line: 2 }));
+        assert_eq!(run_test(&ast, "test_non_boolean", None), Err(ErrorType::AssertConditionNotBoolean { line: 5 }));
+    }
+}
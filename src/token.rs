@@ -18,13 +18,29 @@ pub enum TokenType {
     True, False, String_, Number,
 
     // Keywords.
-    And, Break, Else,
+    And, Assert, Break, Else,
     Func, For, If, Null, Or, Print,
     Return, Var, While,
 
-    Identifier, Eof
+    Identifier, Eof,
+
+    // Only produced if the tokenizer is asked to keep comments (see `Tokenizer::with_comments()`).
+    Comment,
+
+    // A `##` comment documenting the declaration after it. The parser attaches these to `func` and `var` statements.
+    // If the tokenizer is asked to keep comments, these are produced as `Comment` tokens instead.
+    DocComment,
+
+    // Placeholder for characters which could not be tokenized. The error itself is recorded by the tokenizer.
+    Error,
 }
 
+/// The reserved words of the language. These cannot be used as names.
+pub const KEYWORDS: [&str; 15] = [
+    "and", "assert", "break", "else", "false", "for", "func", "if",
+    "null", "or", "print", "return", "true", "var", "while",
+];
+
 /// Literal values declared in the source code.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Literal {
@@ -41,4 +57,5 @@ pub struct Token {
     pub lexeme: String,  // The source code substring from which the token was constructed.
     pub literal: Literal,  // The literal value (number/string/Boolean) the token represents; if the token is not a literal, will be set to the `Null` variant.
     pub line: usize,  // The line number of the source code from which the token was constructed.
+    pub column: usize,  // The column number of the first character of the token, counting characters from 1.
 }
@@ -1,5 +1,5 @@
 use crate::token::{Token, TokenType, Literal};
-use crate::error::{self, ErrorType};
+use crate::error::ErrorType;
 
 /// The states of the DFA.
 #[derive(Debug)]
@@ -38,29 +38,50 @@ enum State {
 }
 
 /// Performs lexical analysis.
-pub struct Tokenizer<'a> {
-    source: &'a str,  // The source code string.
+pub struct Tokenizer {
+    source: Vec<char>,  // The source code, as characters so that it can be indexed directly.
     tokens: Vec<Token>,  // The result sequence of tokens.
+    errors: Vec<ErrorType>,  // The lexical errors found so far.
     start: usize,  // An index pointing to the start of the current token. This will be used to set the value of lexemes and literals.
+    start_line: usize,  // The line number at the start of the current token.
+    start_column: usize,  // The column number at the start of the current token.
     current_index: usize,  // An index pointing to the next character to be scanned.
     current_line: usize,  // The current line number.
+    current_column: usize,  // The column number of the next character to be scanned.
+    keep_comments: bool,  // Whether to produce `Comment` tokens rather than discarding comments.
 }
 
-impl<'a> Tokenizer<'a> {
+impl Tokenizer {
     /// Constructs a `Tokenizer` instance with the given source code string.
-    pub fn new(source: &'a str) -> Self {
+    pub fn new(source: &str) -> Self {
         Self {
-            source,  // Equivalent to `souce: source`.
+            source: source.chars().collect(),
             tokens: Vec::new(),
+            errors: Vec::new(),
             start: 0,
+            start_line: 1,
+            start_column: 1,
             current_index: 0,
             current_line: 1,
+            current_column: 1,
+            keep_comments: false,
         }
     }
 
-    /// The interface method which creates and returns an array of tokens.
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, ErrorType> {
-        while self.source.chars().nth(self.current_index).is_some() {
+    /// Constructs a `Tokenizer` instance which keeps comments as `Comment` tokens, e.g., for the formatter.
+    /// The parser does not accept `Comment` tokens, so these cannot be parsed.
+    pub fn with_comments(source: &str) -> Self {
+        Self {
+            keep_comments: true,
+            ..Self::new(source)
+        }
+    }
+
+    /// The interface method which creates and returns an array of tokens, along with any lexical errors.
+    /// Like the parser, we aim to find as many errors as possible in one run: when an error occurs, an `Error` token
+    /// is recorded in its place and tokenization continues, so that the errors can be reported together with the parser's.
+    pub fn tokenize(&mut self) -> (Vec<Token>, Vec<ErrorType>) {
+        while self.current_index < self.source.len() {
             // If `current_index` has not reached the end of the source code, scan the next token.
             match self.scan_token() {
                 Ok(token_opt) => {
@@ -72,10 +93,11 @@ impl<'a> Tokenizer<'a> {
                     }
                 },
                 Err(error) => {
-                    // If an error has occurred during the `scan_token()` call, report the error.
-                    error::report_errors(&[error.clone()]);
-                    // Return an `Err` variant so that the driver code knows to end execution.
-                    return Err(error);
+                    // If an error has occurred during the `scan_token()` call, collect the error and
+                    // replace the offending characters with an `Error` token.
+                    self.errors.push(error);
+                    let error_token = self.construct_token(TokenType::Error);
+                    self.tokens.push(error_token);
                 }
             }
         }
@@ -85,10 +107,11 @@ impl<'a> Tokenizer<'a> {
             type_: TokenType::Eof,
             lexeme: String::from(""),
             literal: Literal::Null,
-            line: self.current_line
+            line: self.current_line,
+            column: self.current_column,
         });
 
-        Ok(self.tokens.clone())
+        (self.tokens.clone(), self.errors.clone())
     }
 
     /// Scans the token starting from `current_index` by simulating the DFA.
@@ -98,11 +121,14 @@ impl<'a> Tokenizer<'a> {
         loop {
             // It is possible that the tokenizer reaches the end of the source code before `scan_token()` returns.
             // So, we account for `current_char_opt` being None in all possible current states.
-            let current_char_opt = self.source.chars().nth(self.current_index);
+            let current_char_opt = self.source.get(self.current_index).copied();
 
             match current_state {
                 State::Start => {
-                    self.start = self.current_index;  // The next token starts here.
+                    // The next token starts here.
+                    self.start = self.current_index;
+                    self.start_line = self.current_line;
+                    self.start_column = self.current_column;
                     if let Some(current_char) = current_char_opt {
                         match current_char {
                             '(' => current_state = State::GotLeftParen,
@@ -141,16 +167,16 @@ impl<'a> Tokenizer<'a> {
                             // Whitespace.
                             ' ' | '\r' | '\t' => current_state = State::NoOp,
     
-                            '\n' => {
-                                self.current_line += 1;
-                                current_state = State::NoOp;
-                            },
+                            '\n' => current_state = State::NoOp,
     
                             other => {
                                 // If the character does not match any of the above rules, raise an `UnexpectedCharacter` error.
+                                // The character is consumed so that tokenization can continue after it.
+                                self.advance();
                                 return Err(ErrorType::UnexpectedCharacter {
                                     character: other,
                                     line: self.current_line,
+                                    column: self.start_column,
                                 });
                             },
                         }
@@ -219,7 +245,7 @@ impl<'a> Tokenizer<'a> {
                         current_state = State::GotString;
                     } else if current_char_opt.is_none() {
                         // We have reached the end and there was no closing `"`.
-                        return Err(ErrorType::UnterminatedString);
+                        return Err(self.unterminated_string());
                     }
                 },
                 State::InStringSingle => {
@@ -227,13 +253,13 @@ impl<'a> Tokenizer<'a> {
                         current_state = State::GotString;
                     } else if current_char_opt.is_none() {
                         // We have reached the end and there was no closing `'`.
-                        return Err(ErrorType::UnterminatedString);
+                        return Err(self.unterminated_string());
                     }
                 },
                 State::GotString => {
                     return Ok(Some(self.construct_token_with_literal(
                         TokenType::String_,
-                        Literal::String_(self.source[self.start+1..self.current_index-1].to_owned())
+                        Literal::String_(self.substring(self.start+1, self.current_index-1))
                     )));
                 },
 
@@ -246,7 +272,7 @@ impl<'a> Tokenizer<'a> {
                                 // If it is not '0'-'9' (or a '.'), we have reached the end of the number.
                                 return Ok(Some(self.construct_token_with_literal(
                                     TokenType::Number,
-                                    Literal::Number(self.source[self.start..self.current_index].parse().unwrap())
+                                    self.number_literal()?
                                 )));
                             }
                             // If it is a digit, we stay in this state and keep consuming digits.
@@ -255,7 +281,7 @@ impl<'a> Tokenizer<'a> {
                             // If we have reached the end of the source code, then we can return with the number we constructed so far.
                             return Ok(Some(self.construct_token_with_literal(
                                 TokenType::Number,
-                                Literal::Number(self.source[self.start..self.current_index].parse().unwrap())
+                                self.number_literal()?
                             )));
                         }
                     }
@@ -268,7 +294,7 @@ impl<'a> Tokenizer<'a> {
                                 // We have reached the end of the number.
                                 return Ok(Some(self.construct_token_with_literal(
                                     TokenType::Number,
-                                    Literal::Number(self.source[self.start..self.current_index].parse().unwrap())
+                                    self.number_literal()?
                                 )));
                             }
                             // If it is a digit, we stay in this state and keep consuming digits.
@@ -277,20 +303,21 @@ impl<'a> Tokenizer<'a> {
                             // Again, if we have reached the end of the source code, then we can return with the number we constructed so far.
                             return Ok(Some(self.construct_token_with_literal(
                                 TokenType::Number,
-                                Literal::Number(self.source[self.start..self.current_index].parse().unwrap())
+                                self.number_literal()?
                             )));
                         }
                     }
                 },
 
                 State::InWord => {
-                    if current_char_opt.map_or(true, |current_char| !(current_char.is_ascii_alphanumeric() || current_char == '_')) {
+                    if current_char_opt.is_none_or(|current_char| !(current_char.is_ascii_alphanumeric() || current_char == '_')) {
                         // Construct the token now if:
                         // we are at the end of the source code, or
                         // if the current character is not alphanumeric or an `_` (i.e., we have now scanned through the complete word).
-                        let lexeme = &self.source[self.start..self.current_index];
-                        return Ok(Some(match lexeme {
+                        let lexeme = self.substring(self.start, self.current_index);
+                        return Ok(Some(match lexeme.as_str() {
                             "and" => self.construct_token(TokenType::And),
+                            "assert" => self.construct_token(TokenType::Assert),
                             "break" => self.construct_token(TokenType::Break),
                             "else" => self.construct_token(TokenType::Else),
                             "false" => self.construct_token_with_literal(TokenType::False, Literal::Bool(false)),
@@ -311,10 +338,15 @@ impl<'a> Tokenizer<'a> {
                 
                 State::InComment => {
                     // If we have a new line or we have reached the end of the file, the comment has ended.
-                    if current_char_opt == Some('\n') {
-                        self.current_line += 1;
-                        current_state = State::NoOp;
-                    } else if current_char_opt.is_none() {
+                    if current_char_opt == Some('\n') || current_char_opt.is_none() {
+                        if self.keep_comments {
+                            // The new line is not part of the comment, so it is left to be scanned as whitespace.
+                            return Ok(Some(self.construct_token(TokenType::Comment)));
+                        }
+                        if self.source.get(self.start + 1) == Some(&'#') {
+                            // Documentation comments are kept for the parser even when other comments are discarded.
+                            return Ok(Some(self.construct_token(TokenType::DocComment)));
+                        }
                         current_state = State::NoOp;
                     }
                 },
@@ -323,7 +355,41 @@ impl<'a> Tokenizer<'a> {
             }
 
             // Increment the pointer to the next character.
-            self.current_index += 1;
+            self.advance();
+        }
+    }
+
+    /// Moves the pointer past the current character, keeping track of the line and column numbers.
+    fn advance(&mut self) {
+        if self.source.get(self.current_index) == Some(&'\n') {
+            self.current_line += 1;
+            self.current_column = 1;
+        } else {
+            self.current_column += 1;
+        }
+        self.current_index += 1;
+    }
+
+    /// Returns the source code between the two character indices as a `String`.
+    fn substring(&self, start: usize, end: usize) -> String {
+        self.source[start..end].iter().collect()
+    }
+
+    /// Converts the number lexeme being tokenized into a `Literal`.
+    /// The DFA only accepts digits with at most one '.', so this should always succeed.
+    fn number_literal(&self) -> Result<Literal, ErrorType> {
+        let lexeme = self.substring(self.start, self.current_index);
+        match lexeme.parse() {
+            Ok(x) => Ok(Literal::Number(x)),
+            Err(..) => Err(ErrorType::InternalError { message: format!("could not convert `{}` into a number", lexeme), line: self.start_line }),
+        }
+    }
+
+    /// Constructs an `UnterminatedString` error pointing at where the string was opened.
+    fn unterminated_string(&self) -> ErrorType {
+        ErrorType::UnterminatedString {
+            line: self.start_line,
+            column: self.start_column,
         }
     }
 
@@ -331,9 +397,10 @@ impl<'a> Tokenizer<'a> {
     fn construct_token_with_literal(&mut self, token_type: TokenType, literal: Literal) -> Token {
         Token {
             type_: token_type,
-            lexeme: String::from(&self.source[self.start..self.current_index]),
+            lexeme: self.substring(self.start, self.current_index),
             literal,
-            line: self.current_line,
+            line: self.start_line,
+            column: self.start_column,
         }
     }
 
@@ -349,30 +416,35 @@ mod tests {
 
     use super::Tokenizer;
 
-    fn tokenize(source: &str) -> Result<Vec<Token>, ErrorType> {
+    fn tokenize(source: &str) -> Result<Vec<Token>, Vec<ErrorType>> {
         let mut tokenizer = Tokenizer::new(source);
-        tokenizer.tokenize()
+        let (tokens, errors) = tokenizer.tokenize();
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
+        }
     }
 
     #[test]
     fn one_char_tokens() {
         let source = "( ) { } [ ] : , - % + ; / *";
         assert_eq!(Ok(vec![
-            Token { type_: TokenType::LeftParen, lexeme: String::from("("), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::RightParen, lexeme: String::from(")"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::LeftCurly, lexeme: String::from("{"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::RightCurly, lexeme: String::from("}"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::LeftSquare, lexeme: String::from("["), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::RightSquare, lexeme: String::from("]"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Colon, lexeme: String::from(":"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Comma, lexeme: String::from(","), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Minus, lexeme: String::from("-"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Percent, lexeme: String::from("%"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Plus, lexeme: String::from("+"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Semicolon, lexeme: String::from(";"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Slash, lexeme: String::from("/"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Star, lexeme: String::from("*"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 1 },
+            Token { type_: TokenType::LeftParen, lexeme: String::from("("), literal: Literal::Null, line: 1, column: 1 },
+            Token { type_: TokenType::RightParen, lexeme: String::from(")"), literal: Literal::Null, line: 1, column: 3 },
+            Token { type_: TokenType::LeftCurly, lexeme: String::from("{"), literal: Literal::Null, line: 1, column: 5 },
+            Token { type_: TokenType::RightCurly, lexeme: String::from("}"), literal: Literal::Null, line: 1, column: 7 },
+            Token { type_: TokenType::LeftSquare, lexeme: String::from("["), literal: Literal::Null, line: 1, column: 9 },
+            Token { type_: TokenType::RightSquare, lexeme: String::from("]"), literal: Literal::Null, line: 1, column: 11 },
+            Token { type_: TokenType::Colon, lexeme: String::from(":"), literal: Literal::Null, line: 1, column: 13 },
+            Token { type_: TokenType::Comma, lexeme: String::from(","), literal: Literal::Null, line: 1, column: 15 },
+            Token { type_: TokenType::Minus, lexeme: String::from("-"), literal: Literal::Null, line: 1, column: 17 },
+            Token { type_: TokenType::Percent, lexeme: String::from("%"), literal: Literal::Null, line: 1, column: 19 },
+            Token { type_: TokenType::Plus, lexeme: String::from("+"), literal: Literal::Null, line: 1, column: 21 },
+            Token { type_: TokenType::Semicolon, lexeme: String::from(";"), literal: Literal::Null, line: 1, column: 23 },
+            Token { type_: TokenType::Slash, lexeme: String::from("/"), literal: Literal::Null, line: 1, column: 25 },
+            Token { type_: TokenType::Star, lexeme: String::from("*"), literal: Literal::Null, line: 1, column: 27 },
+            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 1, column: 28 },
         ]), tokenize(source));
     }
 
@@ -380,15 +452,15 @@ mod tests {
     fn one_two_char_tokens() {
         let source = "! != = == > >= < <=";
         assert_eq!(Ok(vec![
-            Token { type_: TokenType::Bang, lexeme: String::from("!"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::BangEqual, lexeme: String::from("!="), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Equal, lexeme: String::from("="), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::EqualEqual, lexeme: String::from("=="), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Greater, lexeme: String::from(">"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::GreaterEqual, lexeme: String::from(">="), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Less, lexeme: String::from("<"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::LessEqual, lexeme: String::from("<="), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 1 },
+            Token { type_: TokenType::Bang, lexeme: String::from("!"), literal: Literal::Null, line: 1, column: 1 },
+            Token { type_: TokenType::BangEqual, lexeme: String::from("!="), literal: Literal::Null, line: 1, column: 3 },
+            Token { type_: TokenType::Equal, lexeme: String::from("="), literal: Literal::Null, line: 1, column: 6 },
+            Token { type_: TokenType::EqualEqual, lexeme: String::from("=="), literal: Literal::Null, line: 1, column: 8 },
+            Token { type_: TokenType::Greater, lexeme: String::from(">"), literal: Literal::Null, line: 1, column: 11 },
+            Token { type_: TokenType::GreaterEqual, lexeme: String::from(">="), literal: Literal::Null, line: 1, column: 13 },
+            Token { type_: TokenType::Less, lexeme: String::from("<"), literal: Literal::Null, line: 1, column: 16 },
+            Token { type_: TokenType::LessEqual, lexeme: String::from("<="), literal: Literal::Null, line: 1, column: 18 },
+            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 1, column: 20 },
         ]), tokenize(source));
     }
 
@@ -396,15 +468,15 @@ mod tests {
     fn literals() {
         let source = "\"abc\" 123 \"abc123\" 123.5 \"\" 123abc 5.5";
         assert_eq!(Ok(vec![
-            Token { type_: TokenType::String_, lexeme: String::from("\"abc\""), literal: Literal::String_(String::from("abc")), line: 1 },
-            Token { type_: TokenType::Number, lexeme: String::from("123"), literal: Literal::Number(123.0), line: 1 },
-            Token { type_: TokenType::String_, lexeme: String::from("\"abc123\""), literal: Literal::String_(String::from("abc123")), line: 1 },
-            Token { type_: TokenType::Number, lexeme: String::from("123.5"), literal: Literal::Number(123.5), line: 1 },
-            Token { type_: TokenType::String_, lexeme: String::from("\"\""), literal: Literal::String_(String::from("")), line: 1 },
-            Token { type_: TokenType::Number, lexeme: String::from("123"), literal: Literal::Number(123.0), line: 1 },
-            Token { type_: TokenType::Identifier, lexeme: String::from("abc"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Number, lexeme: String::from("5.5"), literal: Literal::Number(5.5), line: 1 },
-            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 1 },
+            Token { type_: TokenType::String_, lexeme: String::from("\"abc\""), literal: Literal::String_(String::from("abc")), line: 1, column: 1 },
+            Token { type_: TokenType::Number, lexeme: String::from("123"), literal: Literal::Number(123.0), line: 1, column: 7 },
+            Token { type_: TokenType::String_, lexeme: String::from("\"abc123\""), literal: Literal::String_(String::from("abc123")), line: 1, column: 11 },
+            Token { type_: TokenType::Number, lexeme: String::from("123.5"), literal: Literal::Number(123.5), line: 1, column: 20 },
+            Token { type_: TokenType::String_, lexeme: String::from("\"\""), literal: Literal::String_(String::from("")), line: 1, column: 26 },
+            Token { type_: TokenType::Number, lexeme: String::from("123"), literal: Literal::Number(123.0), line: 1, column: 29 },
+            Token { type_: TokenType::Identifier, lexeme: String::from("abc"), literal: Literal::Null, line: 1, column: 32 },
+            Token { type_: TokenType::Number, lexeme: String::from("5.5"), literal: Literal::Number(5.5), line: 1, column: 36 },
+            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 1, column: 39 },
         ]), tokenize(source));
     }
 
@@ -412,29 +484,63 @@ mod tests {
     fn line_count() {
         let source = "12\n23";
         assert_eq!(Ok(vec![
-            Token { type_: TokenType::Number, lexeme: String::from("12"), literal: Literal::Number(12.0), line: 1 },
-            Token { type_: TokenType::Number, lexeme: String::from("23"), literal: Literal::Number(23.0), line: 2 },
-            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 2 },
+            Token { type_: TokenType::Number, lexeme: String::from("12"), literal: Literal::Number(12.0), line: 1, column: 1 },
+            Token { type_: TokenType::Number, lexeme: String::from("23"), literal: Literal::Number(23.0), line: 2, column: 1 },
+            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 2, column: 3 },
         ]), tokenize(source));
     }
 
     #[test]
     fn unterminated_string() {
         let source = "\"abc\nabc\nabc";
-        assert_eq!(Err(ErrorType::UnterminatedString), tokenize(source));
+        assert_eq!(Err(vec![ErrorType::UnterminatedString { line: 1, column: 1 }]), tokenize(source));
+        let source = "print 1\n  print 'abc";
+        assert_eq!(Err(vec![ErrorType::UnterminatedString { line: 2, column: 9 }]), tokenize(source));
+    }
+
+    #[test]
+    fn multiple_errors() {
+        let source = "a @ b\nc $ \"d";
+        let mut tokenizer = Tokenizer::new(source);
+        let (tokens, errors) = tokenizer.tokenize();
+        assert_eq!(errors, vec![
+            ErrorType::UnexpectedCharacter { character: '@', line: 1, column: 3 },
+            ErrorType::UnexpectedCharacter { character: '$', line: 2, column: 3 },
+            ErrorType::UnterminatedString { line: 2, column: 5 },
+        ]);
+        // Tokenization continues after each error, with an `Error` token in place of the offending characters.
+        assert_eq!(tokens, vec![
+            Token { type_: TokenType::Identifier, lexeme: String::from("a"), literal: Literal::Null, line: 1, column: 1 },
+            Token { type_: TokenType::Error, lexeme: String::from("@"), literal: Literal::Null, line: 1, column: 3 },
+            Token { type_: TokenType::Identifier, lexeme: String::from("b"), literal: Literal::Null, line: 1, column: 5 },
+            Token { type_: TokenType::Identifier, lexeme: String::from("c"), literal: Literal::Null, line: 2, column: 1 },
+            Token { type_: TokenType::Error, lexeme: String::from("$"), literal: Literal::Null, line: 2, column: 3 },
+            Token { type_: TokenType::Error, lexeme: String::from("\"d"), literal: Literal::Null, line: 2, column: 5 },
+            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 2, column: 7 },
+        ]);
+    }
+
+    #[test]
+    fn non_ascii() {
+        let source = "'héllo' é 1";
+        assert_eq!(Err(vec![ErrorType::UnexpectedCharacter { character: 'é', line: 1, column: 9 }]), tokenize(source));
+        let mut tokenizer = Tokenizer::new(source);
+        let (tokens, _) = tokenizer.tokenize();
+        assert_eq!(tokens[0], Token { type_: TokenType::String_, lexeme: String::from("'héllo'"), literal: Literal::String_(String::from("héllo")), line: 1, column: 1 });
+        assert_eq!(tokens[2], Token { type_: TokenType::Number, lexeme: String::from("1"), literal: Literal::Number(1.0), line: 1, column: 11 });
     }
 
     #[test]
     fn identifiers_and_keywords() {
         let source = "a a2 if and or ifandor";
         assert_eq!(Ok(vec![
-            Token { type_: TokenType::Identifier, lexeme: String::from("a"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Identifier, lexeme: String::from("a2"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::If, lexeme: String::from("if"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::And, lexeme: String::from("and"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Or, lexeme: String::from("or"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Identifier, lexeme: String::from("ifandor"), literal: Literal::Null, line: 1 },
-            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 1 },
+            Token { type_: TokenType::Identifier, lexeme: String::from("a"), literal: Literal::Null, line: 1, column: 1 },
+            Token { type_: TokenType::Identifier, lexeme: String::from("a2"), literal: Literal::Null, line: 1, column: 3 },
+            Token { type_: TokenType::If, lexeme: String::from("if"), literal: Literal::Null, line: 1, column: 6 },
+            Token { type_: TokenType::And, lexeme: String::from("and"), literal: Literal::Null, line: 1, column: 9 },
+            Token { type_: TokenType::Or, lexeme: String::from("or"), literal: Literal::Null, line: 1, column: 13 },
+            Token { type_: TokenType::Identifier, lexeme: String::from("ifandor"), literal: Literal::Null, line: 1, column: 16 },
+            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 1, column: 23 },
         ]), tokenize(source));
     }
 
@@ -442,9 +548,33 @@ mod tests {
     fn comments() {
         let source = "1\n#abc\n#abc\n1";
         assert_eq!(Ok(vec![
-            Token { type_: TokenType::Number, lexeme: String::from("1"), literal: Literal::Number(1.0), line: 1 },
-            Token { type_: TokenType::Number, lexeme: String::from("1"), literal: Literal::Number(1.0), line: 4 },
-            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 4 },
+            Token { type_: TokenType::Number, lexeme: String::from("1"), literal: Literal::Number(1.0), line: 1, column: 1 },
+            Token { type_: TokenType::Number, lexeme: String::from("1"), literal: Literal::Number(1.0), line: 4, column: 1 },
+            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 4, column: 2 },
         ]), tokenize(source));
     }
+
+    #[test]
+    fn doc_comments() {
+        let source = "## Doc.\n# Not doc.\nvar x = 1";
+        let (tokens, _) = Tokenizer::new(source).tokenize();
+        assert_eq!(tokens[0], Token { type_: TokenType::DocComment, lexeme: String::from("## Doc."), literal: Literal::Null, line: 1, column: 1 });
+        assert_eq!(tokens[1].type_, TokenType::Var);
+
+        // The formatter keeps them as ordinary comments.
+        let (tokens, _) = Tokenizer::with_comments(source).tokenize();
+        assert_eq!(tokens[0].type_, TokenType::Comment);
+    }
+
+    #[test]
+    fn kept_comments() {
+        let mut tokenizer = Tokenizer::with_comments("1  # one\n#two");
+        let (tokens, _) = tokenizer.tokenize();
+        assert_eq!(tokens, vec![
+            Token { type_: TokenType::Number, lexeme: String::from("1"), literal: Literal::Number(1.0), line: 1, column: 1 },
+            Token { type_: TokenType::Comment, lexeme: String::from("# one"), literal: Literal::Null, line: 1, column: 4 },
+            Token { type_: TokenType::Comment, lexeme: String::from("#two"), literal: Literal::Null, line: 2, column: 1 },
+            Token { type_: TokenType::Eof, lexeme: String::from(""), literal: Literal::Null, line: 2, column: 5 },
+        ]);
+    }
 }
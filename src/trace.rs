@@ -0,0 +1,186 @@
+// The tracer used by `nea run --trace`, which logs what a script does as it runs, e.g., to see where a student's code goes wrong.
+// Each executed statement is logged with its line, each call to a user-defined function with its arguments and return value,
+// and each declaration and assignment of a variable with its old and new values. Lines are indented by the depth of function calls.
+
+use std::io::Write;
+
+use crate::error::ErrorType;
+use crate::interpreter::{Frame, Hook, Interpreter};
+use crate::stmt::Stmt;
+use crate::value::Value;
+
+/// The maximum number of characters of a value to show, so that large arrays and dictionaries do not flood the trace.
+const MAX_VALUE_LENGTH: usize = 60;
+
+/// What to trace, given by the options of `nea run --trace`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceOptions {
+    pub functions: Vec<String>,  // Only trace within calls to these functions, if any are given.
+    pub max_depth: Option<usize>,  // Only trace up to this depth of nested function calls, where the top level is 0.
+}
+
+/// Logs the events of the interpreter as it runs a script.
+pub struct Tracer {
+    lines: Vec<String>,  // The lines of the source code, so that statements can be shown.
+    options: TraceOptions,
+    output: Box<dyn Write + Send>,  // Where the trace is written, normally stderr so that it does not mix with the output of the script.
+}
+
+impl Tracer {
+    /// Initialises a new instance of `Tracer` for the given source code.
+    pub fn new(source: &str, options: TraceOptions, output: Box<dyn Write + Send>) -> Self {
+        Self {
+            lines: source.lines().map(String::from).collect(),
+            options,
+            output,
+        }
+    }
+
+    /// Writes a line of the trace at the given depth of function calls, if the options include it.
+    /// `frames` is the call stack, which decides whether the event is within one of the functions to trace.
+    fn log(&mut self, frames: &[Frame], depth: usize, text: &str) {
+        if self.options.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            return;
+        }
+        // The first frame is the top level of the script, which is not a function.
+        if !self.options.functions.is_empty() && !frames.iter().skip(1).any(|frame| self.options.functions.contains(&frame.function)) {
+            return;
+        }
+        // As with `print`, the script keeps running if the trace cannot be written.
+        let _ = writeln!(self.output, "trace: {}{}", "  ".repeat(depth), text);
+    }
+}
+
+/// Returns the representation of a value, shortened to `MAX_VALUE_LENGTH` characters.
+fn show(value: &Value) -> String {
+    let repr = value.repr();
+    if repr.chars().count() <= MAX_VALUE_LENGTH {
+        repr
+    } else {
+        format!("{}...", repr.chars().take(MAX_VALUE_LENGTH).collect::<String>())
+    }
+}
+
+/// Returns the depth of the function call being executed, where the top level is 0.
+fn depth(interpreter: &Interpreter) -> usize {
+    interpreter.frames().len().saturating_sub(1)
+}
+
+impl Hook for Tracer {
+    fn before_statement(&mut self, interpreter: &mut Interpreter, stmt: &Stmt) -> Result<(), ErrorType> {
+        let text = self.lines.get(stmt.line.wrapping_sub(1)).map_or(String::new(), |text| text.trim().to_string());
+        self.log(interpreter.frames(), depth(interpreter), &format!("line {}: {}", stmt.line, text));
+        Ok(())
+    }
+
+    fn function_entered(&mut self, interpreter: &Interpreter, name: &str, parameters: &[String], arguments: &[Value]) {
+        // Calls are shown at the depth of the caller, and the statements in them one level deeper.
+        let arguments: Vec<String> = parameters.iter()
+            .zip(arguments)
+            .map(|(parameter, argument)| format!("{} = {}", parameter, show(argument)))
+            .collect();
+        self.log(interpreter.frames(), depth(interpreter).saturating_sub(1), &format!("call {}({})", name, arguments.join(", ")));
+    }
+
+    fn function_exited(&mut self, interpreter: &Interpreter, name: &str, result: &Result<Value, ErrorType>) {
+        let text = match result {
+            Ok(value) => format!("{} returned {}", name, show(value)),
+            Err(e) => format!("{} failed: {}", name, e.message()),
+        };
+        self.log(interpreter.frames(), depth(interpreter).saturating_sub(1), &text);
+    }
+
+    fn variable_declared(&mut self, interpreter: &Interpreter, name: &str, old: Option<&Value>, value: &Value) {
+        let text = match old {
+            Some(old) => format!("var {} = {} (was {})", name, show(value), show(old)),
+            None => format!("var {} = {}", name, show(value)),
+        };
+        self.log(interpreter.frames(), depth(interpreter), &text);
+    }
+
+    fn variable_updated(&mut self, interpreter: &Interpreter, name: &str, old: &Value, new: &Value) {
+        self.log(interpreter.frames(), depth(interpreter), &format!("{} = {} (was {})", name, show(new), show(old)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nea::stream::Buffer;
+
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::tokenizer::Tokenizer;
+    use crate::value::Value;
+
+    use super::{show, TraceOptions, Tracer};
+
+    const SOURCE: &str = "\
+func double(n) {
+    var result = n * 2
+    return result
+}
+func quadruple(n) {
+    return double(double(n))
+}
+var a = [1]
+a[0] = quadruple(1)
+append(a, 5)
+";
+
+    /// Runs `SOURCE` with the tracer and returns the trace.
+    fn trace(options: TraceOptions) -> String {
+        let (tokens, _) = Tokenizer::new(SOURCE).tokenize();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let output = Buffer::new();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_hook(Box::new(Tracer::new(SOURCE, options, Box::new(output.clone()))));
+        interpreter.interpret(ast).unwrap();
+        drop(interpreter);
+        output.contents()
+    }
+
+    #[test]
+    fn everything() {
+        assert_eq!(trace(TraceOptions::default()), "\
+trace: line 1: func double(n) {
+trace: line 5: func quadruple(n) {
+trace: line 8: var a = [1]
+trace: var a = [1]
+trace: line 9: a[0] = quadruple(1)
+trace: call quadruple(n = 1)
+trace:   line 6: return double(double(n))
+trace:   call double(n = 1)
+trace:     line 2: var result = n * 2
+trace:     var result = 2
+trace:     line 3: return result
+trace:   double returned 2
+trace:   call double(n = 2)
+trace:     line 2: var result = n * 2
+trace:     var result = 4
+trace:     line 3: return result
+trace:   double returned 4
+trace: quadruple returned 4
+trace: a = [4] (was [1])
+trace: line 10: append(a, 5)
+trace: a = [4, 5] (was [4])
+");
+    }
+
+    #[test]
+    fn filters() {
+        // Only the calls to `quadruple` themselves are at depth 0.
+        assert_eq!(trace(TraceOptions { functions: vec![String::from("quadruple")], max_depth: Some(0) }), "\
+trace: call quadruple(n = 1)
+trace: quadruple returned 4
+");
+        assert_eq!(trace(TraceOptions { functions: vec![String::from("double")], max_depth: None }).lines().count(), 10);
+        assert_eq!(trace(TraceOptions { functions: Vec::new(), max_depth: Some(0) }).lines().count(), 10);
+    }
+
+    #[test]
+    fn long_values() {
+        let array = Value::Array((0..100).map(|x| Value::Number(x as f64)).collect());
+        assert_eq!(show(&array).len(), super::MAX_VALUE_LENGTH + 3);
+        assert_eq!(show(&Value::String_(String::from("é"))), "\"é\"");
+    }
+}
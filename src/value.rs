@@ -1,7 +1,10 @@
 use std::fmt;
+use std::sync::Arc;
 
-use crate::stmt::Stmt;
+use crate::error::ErrorType;
 use crate::hash_table::HashTable;
+use crate::interpreter::Interpreter;
+use crate::stmt::Stmt;
 
 /// Represents evaluated/stored values within the interpreter.
 #[derive(Clone, Debug, PartialEq)]
@@ -15,7 +18,7 @@ pub enum Value {
         parameters: Vec<String>,
         body: Stmt,
     },
-    BuiltinFunction(BuiltinFunction),
+    NativeFunction(NativeFunction),
     Null,
 }
 
@@ -28,24 +31,38 @@ impl Value {
             Self::Bool(..) => String::from("Boolean"),
             Self::Array(..) => String::from("Array"),
             Self::Dictionary(..) => String::from("Dictionary"),
-            Self::Function {..} | Self::BuiltinFunction(..) => String::from("Function"),
+            Self::Function {..} | Self::NativeFunction(..) => String::from("Function"),
             Self::Null => String::from("Null"),
         }
     }
-}
 
-/// Used when printing `Value`s.
-impl fmt::Display for Value {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// Returns a debug-style representation of the `Value`, e.g., for echoing values in the REPL.
+    /// Unlike when the value is printed, strings are quoted, so that, e.g., `"1"` and `1` can be told apart.
+    pub fn repr(&self) -> String {
+        let mut repr = String::new();
+        // Writing to a `String` cannot fail.
+        let _ = self.write_to(&mut repr, true);
+        repr
+    }
+
+    /// Writes the `Value` to `f`, quoting strings if `quote_strings` is `true`.
+    /// Strings nested in arrays and dictionaries are quoted in the same way.
+    fn write_to<W: fmt::Write>(&self, f: &mut W, quote_strings: bool) -> fmt::Result {
         match self {
             Self::Number(x) => write!(f, "{}", x),
-            Self::String_(x) => write!(f, "{}", x),
+            Self::String_(x) => {
+                if quote_strings {
+                    write!(f, "\"{}\"", x)
+                } else {
+                    write!(f, "{}", x)
+                }
+            },
             Self::Bool(x) => write!(f, "{}", x),
             Self::Array(array) => {
                 write!(f, "[")?;
                 let mut it = array.iter().peekable();
                 while let Some(x) = it.next() {
-                    x.fmt(f)?;
+                    x.write_to(f, quote_strings)?;
                     if it.peek().is_some() {
                         write!(f, ", ")?;
                     }
@@ -57,29 +74,132 @@ impl fmt::Display for Value {
                 write!(f, "{{")?;
                 let mut it = flattened.iter().peekable();
                 while let Some(key_value) = it.next() {
-                    key_value.key.fmt(f)?;
+                    key_value.key.write_to(f, quote_strings)?;
                     write!(f, ": ")?;
-                    key_value.value.fmt(f)?;
+                    key_value.value.write_to(f, quote_strings)?;
                     if it.peek().is_some() {
                         write!(f, ", ")?;
                     }
                 }
                 write!(f, "}}")
             }
-            Self::Function {..} | Self::BuiltinFunction(..) => write!(f, "<function>"),
+            Self::Function {..} | Self::NativeFunction(..) => write!(f, "<function>"),
             Self::Null => write!(f, "null"),
         }
     }
 }
 
-/// Built-in functions.
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub enum BuiltinFunction {
-    Append,
-    Input,
-    Remove,
-    Size,
-    Sort,
-    ToNumber,
-    ToString,
+/// Used when printing `Value`s.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_to(f, false)
+    }
+}
+
+/// The number of arguments a native function accepts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+    Between(usize, usize),  // From the first to the second number, inclusive.
+}
+
+impl Arity {
+    /// Returns `true` if a call with `arg_number` arguments is accepted.
+    pub fn accepts(&self, arg_number: usize) -> bool {
+        match *self {
+            Self::Exact(number) => arg_number == number,
+            Self::AtLeast(min) => arg_number >= min,
+            Self::Between(min, max) => (min..=max).contains(&arg_number),
+        }
+    }
+}
+
+/// Used in error messages, e.g., "accepts at least 1".
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact(number) => write!(f, "{}", number),
+            Self::AtLeast(min) => write!(f, "at least {}", min),
+            Self::Between(min, max) => write!(f, "{} to {}", min, max),
+        }
+    }
+}
+
+/// The Rust code of a native function. It is given the interpreter, e.g., to write output or find the line of the call,
+/// and the evaluated arguments, of which there are as many as the function's `Arity` accepts.
+/// It must be `Send` and `Sync`, so that values and interpreters holding it can be sent to other threads.
+pub type NativeCode = dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, ErrorType> + Send + Sync;
+
+/// A function implemented in Rust rather than in the language, such as the built-in functions (see `builtins`)
+/// and the functions registered by programs embedding the interpreter (see `Interpreter::register_function()`).
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: String,  // The name the function was declared under, e.g., for the REPL to tell it apart from variables.
+    pub arity: Arity,
+    // Whether the function changes its first argument, like `append()`. The argument must then be a variable or an element,
+    // and the value the function returns is assigned back to it.
+    pub updates_argument: bool,
+    pub code: Arc<NativeCode>,
+}
+
+impl NativeFunction {
+    /// Constructs a native function which does not change its arguments.
+    pub fn new(name: &str, arity: Arity, code: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, ErrorType> + Send + Sync + 'static) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            updates_argument: false,
+            code: Arc::new(code),
+        }
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .field("updates_argument", &self.updates_argument)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Native functions are equal if they are the same function, as closures cannot be compared.
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && Arc::ptr_eq(&self.code, &other.code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash_table::HashTable;
+
+    use super::{Arity, Value};
+
+    #[test]
+    fn repr_quotes_strings() {
+        assert_eq!(Value::String_(String::from("1")).repr(), "\"1\"");
+        assert_eq!(Value::Number(1.0).repr(), "1");
+        assert_eq!(Value::String_(String::from("1")).to_string(), "1");
+    }
+
+    #[test]
+    fn repr_nested() {
+        let mut dict = HashTable::new();
+        let _ = dict.insert(Value::String_(String::from("k")), Value::Array(vec![Value::Null, Value::String_(String::from("v"))]), 1);
+        let array = Value::Array(vec![Value::Bool(true), Value::Dictionary(dict), Value::Number(2.5)]);
+        assert_eq!(array.repr(), "[true, {\"k\": [null, \"v\"]}, 2.5]");
+        assert_eq!(array.to_string(), "[true, {k: [null, v]}, 2.5]");
+    }
+
+    #[test]
+    fn arity() {
+        assert!(Arity::Exact(2).accepts(2) && !Arity::Exact(2).accepts(1));
+        assert!(Arity::AtLeast(1).accepts(5) && !Arity::AtLeast(1).accepts(0));
+        assert!(Arity::Between(1, 2).accepts(2) && !Arity::Between(1, 2).accepts(3));
+        assert_eq!(Arity::Between(1, 2).to_string(), "1 to 2");
+        assert_eq!(Arity::AtLeast(1).to_string(), "at least 1");
+    }
 }
@@ -0,0 +1,94 @@
+use std::io::{self, Write};
+
+use crate::value::Arity;
+
+/// Possible warnings found by the linter. Unlike `ErrorType`, these do not stop the program from being executed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WarningType {
+    UnusedVariable {
+        name: String,
+        line: usize,
+    },
+    UnusedParameter {
+        name: String,
+        function: String,
+        line: usize,
+    },
+    ShadowedVariable {
+        name: String,
+        line: usize,
+    },
+    UnreachableCode {
+        line: usize,
+    },
+    NonBooleanCondition {
+        got: String,
+        line: usize,
+    },
+    BuiltinArgNumberMismatch {
+        name: String,
+        arg_number: usize,
+        arity: Arity,
+        line: usize,
+    },
+}
+
+impl WarningType {
+    /// Returns the name of the kind of warning, as used to suppress it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UnusedVariable {..} => "unused_variable",
+            Self::UnusedParameter {..} => "unused_parameter",
+            Self::ShadowedVariable {..} => "shadowed_variable",
+            Self::UnreachableCode {..} => "unreachable_code",
+            Self::NonBooleanCondition {..} => "non_boolean_condition",
+            Self::BuiltinArgNumberMismatch {..} => "builtin_arity",
+        }
+    }
+
+    /// Returns the line the warning refers to.
+    pub fn line(&self) -> usize {
+        match self {
+            Self::UnusedVariable { line, .. } |
+            Self::UnusedParameter { line, .. } |
+            Self::ShadowedVariable { line, .. } |
+            Self::UnreachableCode { line } |
+            Self::NonBooleanCondition { line, .. } |
+            Self::BuiltinArgNumberMismatch { line, .. } => *line,
+        }
+    }
+}
+
+/// Prints the warning message for each warning in `warnings`.
+pub fn report_warnings(warnings: &[WarningType]) {
+    // As in `error::write_errors()`, `writeln!()` is used so that a closed stdout does not cause a panic.
+    let mut stdout = io::stdout().lock();
+    for warning in warnings {
+        let _ = writeln!(stdout, "{}", message(warning));
+    }
+}
+
+/// Returns the warning message for an individual warning, followed by the name which can be used to suppress it.
+pub fn message(warning: &WarningType) -> String {
+    let message = match warning {
+        WarningType::UnusedVariable { name, .. } => {
+            format!("variable `{}` is declared but never used.", name)
+        },
+        WarningType::UnusedParameter { name, function, .. } => {
+            format!("parameter `{}` of function `{}` is never used.", name, function)
+        },
+        WarningType::ShadowedVariable { name, .. } => {
+            format!("variable `{}` shadows a variable of the same name in an outer scope.", name)
+        },
+        WarningType::UnreachableCode { .. } => {
+            String::from("this code will never be executed as it comes after a `return` or `break`.")
+        },
+        WarningType::NonBooleanCondition { got, .. } => {
+            format!("the condition is always a {}, not a Boolean value, so this will fail when executed.", got)
+        },
+        WarningType::BuiltinArgNumberMismatch { name, arg_number, arity, .. } => {
+            format!("`{}` is called with {} argument(s), but it accepts {}.", name, arg_number, arity)
+        },
+    };
+    format!("Line {}: warning: {} [{}]", warning.line(), message, warning.name())
+}
@@ -0,0 +1,243 @@
+// Tests of the library's public API, used as a Rust program embedding the interpreter would use it.
+
+// Native functions return `ErrorType`, which is large by design (see `lib.rs`).
+#![allow(clippy::result_large_err)]
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use nea::{Arity, Error, ErrorType, Frame, HashTable, Hook, Interpreter, Parser, Stmt, StmtType, Tokenizer, Value};
+use nea::stream::{self, Buffer, LineSource};
+
+/// An interpreter whose `print` output is discarded, so that it does not clutter the output of the tests.
+fn interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(io::sink()));
+    interpreter
+}
+
+#[test]
+fn eval_str() {
+    let mut interpreter = interpreter();
+    assert_eq!(interpreter.eval_str("var x = 2\nx * 21"), Ok(Value::Number(42.0)));
+    // Declarations are kept between calls, and statements other than expressions evaluate to `null`.
+    assert_eq!(interpreter.eval_str("x = x + 1"), Ok(Value::Number(3.0)));
+    assert_eq!(interpreter.eval_str("print x"), Ok(Value::Null));
+    assert_eq!(interpreter.eval_str(""), Ok(Value::Null));
+}
+
+#[test]
+fn errors() {
+    let mut interpreter = interpreter();
+    let Err(Error::Syntax(errors)) = interpreter.eval_str("var x = 1\nvar = 2") else { panic!() };
+    assert_eq!(errors, vec![ErrorType::ExpectedVariableName { line: 2 }]);
+    // Nothing is run if there are syntax errors.
+    assert_eq!(interpreter.get_global("x"), None);
+
+    let error = interpreter.eval_str("var y = 1\n1 / 0").unwrap_err();
+    assert_eq!(error, Error::Runtime(ErrorType::DivideByZero { line: 2 }));
+    assert_eq!(error.to_string(), ErrorType::DivideByZero { line: 2 }.message());
+    // The statements before the error were run.
+    assert_eq!(interpreter.get_global("y"), Some(Value::Number(1.0)));
+}
+
+#[test]
+fn globals_and_calls() {
+    let mut interpreter = interpreter();
+    interpreter.set_global("greeting", Value::String_(String::from("Hello")));
+    interpreter.eval_str("func greet(name) {\n    return greeting + \", \" + name\n}").unwrap();
+    let name = Value::String_(String::from("Nea"));
+    assert_eq!(interpreter.call("greet", vec![name.clone()]), Ok(Value::String_(String::from("Hello, Nea"))));
+
+    interpreter.set_global("greeting", Value::String_(String::from("Bye")));
+    assert_eq!(interpreter.call("greet", vec![name]), Ok(Value::String_(String::from("Bye, Nea"))));
+    assert!(matches!(interpreter.get_global("greet"), Some(Value::Function {..})));
+    assert!(matches!(interpreter.get_global("size"), Some(Value::NativeFunction(..))));
+    assert!(interpreter.call("missing", Vec::new()).is_err());
+}
+
+#[test]
+fn native_functions() {
+    let mut interpreter = interpreter();
+    interpreter.register_function("add_all", Arity::AtLeast(1), |interpreter, arguments| {
+        let mut total = 0.0;
+        for argument in arguments {
+            match argument {
+                Value::Number(x) => total += x,
+                _ => return Err(ErrorType::ExpectedType { expected: String::from("Number"), got: argument.type_to_string(), line: interpreter.call_line() }),
+            }
+        }
+        Ok(Value::Number(total))
+    });
+    assert_eq!(interpreter.eval_str("add_all(1, 2, 3)"), Ok(Value::Number(6.0)));
+    assert_eq!(interpreter.call("add_all", vec![Value::Number(4.0)]), Ok(Value::Number(4.0)));
+    // Native functions can be passed around like any other function.
+    assert_eq!(interpreter.eval_str("var f = add_all\nf(5)"), Ok(Value::Number(5.0)));
+
+    // Errors are raised in the script, on the line of the call.
+    assert_eq!(interpreter.eval_str("\nadd_all(1, \"2\")"), Err(Error::Runtime(ErrorType::ExpectedType {
+        expected: String::from("Number"), got: String::from("String"), line: 2,
+    })));
+    let Err(Error::Runtime(error)) = interpreter.eval_str("add_all()") else { panic!() };
+    assert_eq!(error.message(), "Line 1: attempted to call function with 0 argument(s), but function accepts at least 1.");
+
+    // Built-in functions use the same mechanism, so they can be called from Rust too.
+    let array = Value::Array(vec![Value::Number(1.0)]);
+    assert_eq!(interpreter.call("append", vec![array, Value::Null]), Ok(Value::Array(vec![Value::Number(1.0), Value::Null])));
+}
+
+#[test]
+fn streams() {
+    let mut interpreter = Interpreter::new();
+    let output = Buffer::new();
+    interpreter.set_output(Box::new(output.clone()));
+    let mut answers = vec!["Nea", "42"].into_iter();
+    interpreter.set_input(Box::new(LineSource::new(move || Ok(answers.next().map(String::from)))));
+    interpreter.eval_str("var name = input(\"Name: \")\nprint \"Hello, \" + name\nprint to_number(input(\"Age: \")) + 1").unwrap();
+    assert_eq!(output.take(), "Name: Hello, Nea\nAge: 43\n");
+    assert_eq!(interpreter.eval_str("input(\"\")"), Err(Error::Runtime(ErrorType::EndOfInput { line: 1 })));
+
+    interpreter.set_input(stream::text_input("line\n"));
+    assert_eq!(interpreter.eval_str("input(\"> \")"), Ok(Value::String_(String::from("line"))));
+    assert_eq!(output.contents(), "> ");
+
+    let errors = Buffer::new();
+    interpreter.set_error_output(Box::new(errors.clone()));
+    interpreter.report_errors(&[ErrorType::DivideByZero { line: 3 }]);
+    assert_eq!(errors.contents(), format!("An error has occurred.\n{}\n", ErrorType::DivideByZero { line: 3 }.message()));
+}
+
+#[test]
+fn stages() {
+    // The tokenizer and parser can also be used separately, e.g., to check scripts without running them.
+    let (tokens, errors) = Tokenizer::new("print 1 + 2").tokenize();
+    assert!(errors.is_empty());
+    let ast = Parser::new(tokens).parse().unwrap();
+    let mut interpreter = interpreter();
+    assert_eq!(interpreter.interpret(ast), Ok(()));
+}
+
+#[test]
+fn deep_recursion() {
+    // Scripts can recurse up to the call limit with the default settings, whatever the size of the stack of the thread running them.
+    for stack_size in [2 * 1024 * 1024, 8 * 1024 * 1024] {
+        let thread = std::thread::Builder::new().stack_size(stack_size).spawn(|| {
+            let mut interpreter = interpreter();
+            interpreter.eval_str("func f(n) {\n    if (n == 0) {\n        return 0\n    }\n    return f(n - 1) + 1\n}").unwrap();
+            assert_eq!(interpreter.eval_str("f(500)"), Ok(Value::Number(500.0)));
+            assert_eq!(interpreter.eval_str("f(990)"), Ok(Value::Number(990.0)));
+            assert!(matches!(interpreter.eval_str("f(5000)"), Err(Error::Runtime(ErrorType::StackOverflow {..}))));
+
+            let nested = format!("{}1{}", "(".repeat(500), ")".repeat(500));
+            assert_eq!(interpreter.eval_str(&nested), Ok(Value::Number(1.0)));
+        }).unwrap();
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn stack_limit() {
+    let thread = std::thread::Builder::new().stack_size(2 * 1024 * 1024).spawn(|| {
+        let mut interpreter = interpreter();
+        // Each call of this function uses far more of the stack than a call in a script, so the stack of the thread soon runs out.
+        interpreter.register_function("deep", Arity::Exact(1), |interpreter, arguments| {
+            let Value::Number(n) = arguments[0] else { return Ok(Value::Null) };
+            let frame = std::hint::black_box([0u8; 64 * 1024]);
+            if n == 0.0 {
+                return Ok(Value::Number(frame[0] as f64));
+            }
+            interpreter.call("deep", vec![Value::Number(n - 1.0)])
+        });
+        assert_eq!(interpreter.eval_str("deep(500)"), Ok(Value::Number(0.0)));
+
+        // Beyond the limit on the extra stack the interpreter may allocate, it raises an error instead.
+        interpreter.set_max_stack_usage(0);
+        assert!(matches!(interpreter.eval_str("deep(500)"), Err(Error::Runtime(ErrorType::StackOverflow {..}))));
+    }).unwrap();
+    thread.join().unwrap();
+}
+
+#[test]
+fn reentrant_calls() {
+    let mut interpreter = interpreter();
+    // Native functions can call back into the interpreter, e.g., to call functions of the script.
+    interpreter.register_function("twice", Arity::Exact(1), |interpreter, arguments| {
+        let Value::String_(name) = &arguments[0] else { return Ok(Value::Null) };
+        let once = interpreter.call(name, vec![Value::Number(1.0)])?;
+        interpreter.call(name, vec![once])
+    });
+    interpreter.eval_str("func double(x) {\n    return x * 2\n}").unwrap();
+    assert_eq!(interpreter.eval_str("twice(\"double\") + twice(\"double\")"), Ok(Value::Number(8.0)));
+
+    // A runaway recursion through a native function has to raise an error too, rather than overflow the stack.
+    interpreter.register_function("again", Arity::Exact(0), |interpreter, _| interpreter.call("again", Vec::new()));
+    assert_eq!(interpreter.eval_str("again()"), Err(Error::Runtime(ErrorType::StackOverflow { line: 0 })));
+    interpreter.eval_str("func f(x) {\n    return twice(\"f\")\n}").unwrap();
+    assert!(matches!(interpreter.eval_str("f(0)"), Err(Error::Runtime(ErrorType::StackOverflow {..}))));
+}
+
+#[test]
+fn evaluate() {
+    // Expressions can be evaluated on their own, without running a script first.
+    let (tokens, _) = Tokenizer::new("1 + 2").tokenize();
+    let ast = Parser::new(tokens).parse().unwrap();
+    let StmtType::Expression { expression } = &ast[0].stmt_type else { panic!() };
+    assert_eq!(Interpreter::new().evaluate(expression), Ok(Value::Number(3.0)));
+}
+
+#[test]
+fn hooks() {
+    // A hook sees each statement as it runs, e.g., to trace which functions the lines run in.
+    struct Lines(Arc<Mutex<Vec<(usize, String)>>>);
+    impl Hook for Lines {
+        fn before_statement(&mut self, interpreter: &mut Interpreter, stmt: &Stmt) -> Result<(), ErrorType> {
+            let Frame { function, .. } = interpreter.frames().last().unwrap();
+            self.0.lock().unwrap().push((stmt.line, function.clone()));
+            Ok(())
+        }
+    }
+
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let mut interpreter = interpreter();
+    interpreter.set_hook(Box::new(Lines(lines.clone())));
+    interpreter.eval_str("func f() {\n    return 1\n}\nvar x = f()").unwrap();
+    let expected = [(1, "<script>"), (4, "<script>"), (2, "f")].map(|(line, function)| (line, function.to_string()));
+    assert_eq!(*lines.lock().unwrap(), expected);
+}
+
+#[test]
+fn thread_safety() {
+    // Values, errors and buffers can be sent to and shared with other threads, e.g., to pass results between them.
+    fn is_send_sync<T: Send + Sync>() {}
+    is_send_sync::<Value>();
+    is_send_sync::<ErrorType>();
+    is_send_sync::<Error>();
+    is_send_sync::<Buffer>();
+
+    // An interpreter can be moved to another thread, e.g., to run a script in the background, with its output read from this one.
+    let output = Buffer::new();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(output.clone()));
+    interpreter.eval_str("var x = 2").unwrap();
+    let thread = std::thread::spawn(move || interpreter.eval_str("print x * 3"));
+    assert_eq!(thread.join().unwrap(), Ok(Value::Null));
+    assert_eq!(output.contents(), "6\n");
+}
+
+#[test]
+fn dictionaries() {
+    // Dictionaries can be built in Rust and passed to scripts, and the ones scripts build can be read.
+    let mut prices = HashTable::new();
+    prices.insert(Value::from("apple"), Value::Number(0.5), 0).unwrap();
+    prices.insert(Value::from("pear"), Value::Number(0.75), 0).unwrap();
+    let mut interpreter = interpreter();
+    interpreter.set_global("prices", Value::Dictionary(prices));
+    interpreter.eval_str("prices[\"plum\"] = prices[\"apple\"] * 2").unwrap();
+
+    let Some(Value::Dictionary(mut prices)) = interpreter.get_global("prices") else { panic!() };
+    assert_eq!(prices.size(), 3);
+    assert_eq!(prices.get(&Value::from("plum"), 0), Ok(&Value::Number(1.0)));
+    let total: f64 = prices.iter().map(|(_, price)| f64::try_from(price.clone()).unwrap()).sum();
+    assert_eq!(total, 2.25);
+    assert!(matches!(prices.insert(Value::Dictionary(HashTable::new()), Value::Null, 0), Err(ErrorType::CannotHashDictionary {..})));
+}
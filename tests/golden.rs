@@ -0,0 +1,179 @@
+// Golden-file tests, which run each script in `tests/cases` with the built binary and compare what it does with what is expected.
+//
+// For a script `name.nea`, the expectations are kept in the files next to it:
+//     name.stdout    The output expected on stdout. Empty if missing.
+//     name.stderr    The output expected on stderr. Empty if missing.
+//     name.status    The exit code expected. 0 if missing.
+// The script can also be given input in these files:
+//     name.args      The arguments passed to the script, separated by whitespace.
+//     name.stdin     The text entered on stdin, e.g., for `input()`.
+//
+// To update the expectations after an intended change in behaviour, run `NEA_BLESS=1 cargo test --test golden`
+// and review the changes to the files with `git diff`.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+
+/// The directory of the scripts, relative to the root of the crate.
+const CASES_DIR: &str = "tests/cases";
+
+/// The environment variable which, when set, makes the tests write the actual results as the expectations instead of comparing them.
+const BLESS_VAR: &str = "NEA_BLESS";
+
+/// What running a script did, or is expected to do.
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    stdout: String,
+    stderr: String,
+    status: i32,
+}
+
+#[test]
+fn golden() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(CASES_DIR);
+    let cases = find_cases(&dir).expect("failed to read the test cases");
+    assert!(!cases.is_empty(), "no test cases found in `{}`", dir.display());
+
+    let bless = env::var_os(BLESS_VAR).is_some();
+    let mut failures = Vec::new();
+    for case in &cases {
+        let actual = run_case(&dir, case);
+        if bless {
+            write_outcome(&dir, case, &actual).expect("failed to write the expectations");
+            continue;
+        }
+
+        let expected = read_outcome(&dir, case);
+        if actual != expected {
+            failures.push(describe_failure(case, &expected, &actual));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} cases failed:\n\n{}\nRun with {}=1 to update the expectations if the changes are intended.",
+        failures.len(), cases.len(), failures.join("\n"), BLESS_VAR,
+    );
+}
+
+/// Returns the names of the scripts in `dir`, without their extension, in alphabetical order.
+fn find_cases(dir: &Path) -> io::Result<Vec<String>> {
+    let mut cases = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|extension| extension == "nea"))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .collect::<Vec<String>>();
+    cases.sort();
+    Ok(cases)
+}
+
+/// Returns the contents of the file for `case` with the given extension, or `None` if there is no such file.
+fn read_file(dir: &Path, case: &str, extension: &str) -> Option<String> {
+    fs::read_to_string(dir.join(format!("{}.{}", case, extension))).ok()
+}
+
+/// Runs the script with the built binary. It is run from the directory of the cases and given by its file name,
+/// so that the output does not depend on where the crate is.
+fn run_case(dir: &Path, case: &str) -> Outcome {
+    let args = read_file(dir, case, "args").unwrap_or_default();
+    let stdin = read_file(dir, case, "stdin").unwrap_or_default();
+
+    let output = Command::cargo_bin("nea")
+        .expect("failed to find the binary")
+        .current_dir(dir)
+        .arg(format!("{}.nea", case))
+        .args(args.split_whitespace())
+        .write_stdin(stdin)
+        .output()
+        .expect("failed to run the binary");
+
+    Outcome {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        // A process killed by a signal has no exit code.
+        status: output.status.code().unwrap_or(-1),
+    }
+}
+
+/// Reads the expectations for `case`. Missing files mean no output and success.
+fn read_outcome(dir: &Path, case: &str) -> Outcome {
+    Outcome {
+        stdout: read_file(dir, case, "stdout").unwrap_or_default(),
+        stderr: read_file(dir, case, "stderr").unwrap_or_default(),
+        status: read_file(dir, case, "status").map_or(0, |status| {
+            status.trim().parse().unwrap_or_else(|_| panic!("`{}.status` is not an exit code", case))
+        }),
+    }
+}
+
+/// Writes `outcome` as the expectations for `case`. Only the files which are needed are kept, i.e., ones which are not empty
+/// and a status which is not 0, so that most cases only have a `.stdout` file.
+fn write_outcome(dir: &Path, case: &str, outcome: &Outcome) -> io::Result<()> {
+    let status = if outcome.status == 0 { String::new() } else { format!("{}\n", outcome.status) };
+    for (extension, contents) in [("stdout", &outcome.stdout), ("stderr", &outcome.stderr), ("status", &status)] {
+        let path = dir.join(format!("{}.{}", case, extension));
+        if !contents.is_empty() {
+            fs::write(&path, contents)?;
+        } else if path.exists() {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Describes how the outcome of `case` differs from what was expected.
+fn describe_failure(case: &str, expected: &Outcome, actual: &Outcome) -> String {
+    let mut description = format!("--- {}.nea\n", case);
+    if expected.status != actual.status {
+        description.push_str(&format!("exit code: expected {}, got {}\n", expected.status, actual.status));
+    }
+    for (stream, expected, actual) in [("stdout", &expected.stdout, &actual.stdout), ("stderr", &expected.stderr, &actual.stderr)] {
+        if expected != actual {
+            description.push_str(&format!("{} differs (- expected, + actual):\n{}", stream, diff_lines(expected, actual)));
+        }
+    }
+    description
+}
+
+/// Returns a line-by-line diff of two texts, where lines only in `expected` start with `-`, lines only in `actual` start with `+`
+/// and lines in both start with a space. It is based on the longest common subsequence of the lines, which is plenty fast
+/// for the outputs of test cases.
+fn diff_lines(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // `lengths[i][j]` is the length of the longest common subsequence of `expected[i..]` and `actual[j..]`.
+    let mut lengths = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = if expected[i] == actual[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            diff.push_str(&format!("  {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < expected.len() && (j == actual.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            diff.push_str(&format!("- {}\n", expected[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+ {}\n", actual[j]));
+            j += 1;
+        }
+    }
+    diff
+}